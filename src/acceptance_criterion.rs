@@ -0,0 +1,249 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use simple_error::bail;
+use std::error::Error;
+
+/// Decision que toma un criterio de aceptacion sobre una solucion candidata
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision{
+    /// Nos movemos a la solucion candidata
+    Accept,
+
+    /// Nos quedamos con la solucion actual
+    Reject,
+
+    /// Descartamos la solucion actual y reiniciamos la busqueda desde una solucion aleatoria
+    Restart,
+}
+
+/// Criterio con el que una busqueda por trayectorias decide si se mueve a una solucion candidata
+///
+/// Lo usan la busqueda local (para cada vecino), el enfriamiento simulado (para cada vecino
+/// aleatorio) y la busqueda local reiterada (para cada nuevo optimo local). Los criterios pueden
+/// guardar estado, por ejemplo para contar los fallos consecutivos
+pub trait AcceptanceCriterion: Send{
+    /// Decide si nos movemos de la solucion actual a la candidata, dados sus fitness
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, rng: &mut StdRng) -> Decision;
+
+    /// Los criterios que dependen de una temperatura la actualizan aqui. El enfriamiento simulado
+    /// lo llama cada vez que enfria. Por defecto no hace nada
+    fn set_temperature(&mut self, _temperature: f64){}
+}
+
+/// Solo acepta soluciones estrictamente mejores
+#[derive(Debug, Clone)]
+pub struct ImproveOnly;
+
+impl AcceptanceCriterion for ImproveOnly{
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, _rng: &mut StdRng) -> Decision{
+        if candidate_fitness < current_fitness{
+            return Decision::Accept;
+        }
+        return Decision::Reject;
+    }
+}
+
+/// Acepta soluciones que no empeoran, lo que permite moverse por mesetas
+#[derive(Debug, Clone)]
+pub struct ImproveOrEqual;
+
+impl AcceptanceCriterion for ImproveOrEqual{
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, _rng: &mut StdRng) -> Decision{
+        if candidate_fitness <= current_fitness{
+            return Decision::Accept;
+        }
+        return Decision::Reject;
+    }
+}
+
+/// Criterio de Metropolis: acepta siempre las soluciones que no empeoran, y las que empeoran con
+/// probabilidad exp(-(candidate_fitness - current_fitness) / temperature)
+#[derive(Debug, Clone)]
+pub struct Metropolis{
+    temperature: f64,
+}
+
+impl Metropolis{
+    pub fn new(temperature: f64) -> Self{
+        return Self{temperature};
+    }
+
+    pub fn get_temperature(&self) -> f64{
+        return self.temperature;
+    }
+}
+
+impl AcceptanceCriterion for Metropolis{
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, rng: &mut StdRng) -> Decision{
+        // Es negativo cuando la candidata es peor
+        let delta_fitness = current_fitness - candidate_fitness;
+        if delta_fitness >= 0.0{
+            return Decision::Accept;
+        }
+
+        if rng.gen::<f64>() < (delta_fitness / self.temperature).exp(){
+            return Decision::Accept;
+        }
+        return Decision::Reject;
+    }
+
+    fn set_temperature(&mut self, temperature: f64){
+        self.temperature = temperature;
+    }
+}
+
+/// Acepta las soluciones que no empeoran la actual en mas de threshold veces su fitness
+#[derive(Debug, Clone)]
+pub struct Threshold{
+    threshold: f64,
+}
+
+impl Threshold{
+    pub fn new(threshold: f64) -> Self{
+        return Self{threshold};
+    }
+}
+
+impl AcceptanceCriterion for Threshold{
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, _rng: &mut StdRng) -> Decision{
+        if candidate_fitness <= current_fitness + self.threshold * current_fitness.abs(){
+            return Decision::Accept;
+        }
+        return Decision::Reject;
+    }
+}
+
+/// Acepta cualquier solucion. Las busquedas guardan aparte la mejor solucion encontrada
+#[derive(Debug, Clone)]
+pub struct RandomWalk;
+
+impl AcceptanceCriterion for RandomWalk{
+    fn decide(&mut self, _current_fitness: f64, _candidate_fitness: f64, _rng: &mut StdRng) -> Decision{
+        return Decision::Accept;
+    }
+}
+
+/// Solo acepta soluciones estrictamente mejores, y pide reiniciar la busqueda tras max_failures
+/// rechazos consecutivos
+#[derive(Debug, Clone)]
+pub struct RestartOnStagnation{
+    max_failures: u32,
+    failures: u32,
+}
+
+impl RestartOnStagnation{
+    pub fn new(max_failures: u32) -> Self{
+        return Self{max_failures, failures: 0};
+    }
+}
+
+impl AcceptanceCriterion for RestartOnStagnation{
+    fn decide(&mut self, current_fitness: f64, candidate_fitness: f64, _rng: &mut StdRng) -> Decision{
+        if candidate_fitness < current_fitness{
+            self.failures = 0;
+            return Decision::Accept;
+        }
+
+        self.failures += 1;
+        if self.failures >= self.max_failures{
+            self.failures = 0;
+            return Decision::Restart;
+        }
+
+        return Decision::Reject;
+    }
+}
+
+/// Construye un criterio de aceptacion a partir de su descripcion, tal y como se pasa en los
+/// parametros de los algoritmos. Los criterios con parametro lo llevan tras dos puntos:
+/// improve, improve_or_equal, metropolis:<temperatura>, threshold:<fraccion del fitness>,
+/// random_walk y restart:<fallos consecutivos>
+/// Metropolis sin temperatura empieza a temperatura cero (solo acepta mejoras), y esta pensado
+/// para los algoritmos que fijan la temperatura, como el enfriamiento simulado
+pub fn parse_acceptance_criterion(description: &str) -> Result<Box<dyn AcceptanceCriterion>, Box<dyn Error>>{
+    let (name, value) = match description.split_once(':'){
+        Some((name, value)) => (name, Some(value)),
+        None => (description, None),
+    };
+
+    match (name, value){
+        ("improve", None) => return Ok(Box::new(ImproveOnly)),
+        ("improve_or_equal", None) => return Ok(Box::new(ImproveOrEqual)),
+        ("random_walk", None) => return Ok(Box::new(RandomWalk)),
+        ("metropolis", None) => return Ok(Box::new(Metropolis::new(0.0))),
+        ("metropolis", Some(value)) => return Ok(Box::new(Metropolis::new(parse_value(description, value)?))),
+        ("threshold", Some(value)) => return Ok(Box::new(Threshold::new(parse_value(description, value)?))),
+        ("restart", Some(value)) => return Ok(Box::new(RestartOnStagnation::new(parse_value(description, value)?))),
+        _ => bail!("El criterio de aceptacion {} no existe", description),
+    }
+}
+
+/// Convierte el parametro de un criterio de aceptacion, o devuelve error si no es valido
+fn parse_value<T: std::str::FromStr>(description: &str, value: &str) -> Result<T, Box<dyn Error>>{
+    match value.parse::<T>(){
+        Ok(value) => return Ok(value),
+        Err(_) => bail!("Valor {} no valido en el criterio de aceptacion {}", value, description),
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::acceptance_criterion::{parse_acceptance_criterion, Decision, Metropolis, AcceptanceCriterion};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_parse_and_decide(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+
+        let mut improve = parse_acceptance_criterion("improve").unwrap();
+        assert_eq!(improve.decide(1.0, 0.5, &mut rng), Decision::Accept);
+        assert_eq!(improve.decide(1.0, 1.0, &mut rng), Decision::Reject);
+
+        let mut improve_or_equal = parse_acceptance_criterion("improve_or_equal").unwrap();
+        assert_eq!(improve_or_equal.decide(1.0, 1.0, &mut rng), Decision::Accept);
+        assert_eq!(improve_or_equal.decide(1.0, 1.1, &mut rng), Decision::Reject);
+
+        let mut threshold = parse_acceptance_criterion("threshold:0.1").unwrap();
+        assert_eq!(threshold.decide(1.0, 1.05, &mut rng), Decision::Accept);
+        assert_eq!(threshold.decide(1.0, 1.2, &mut rng), Decision::Reject);
+
+        let mut random_walk = parse_acceptance_criterion("random_walk").unwrap();
+        assert_eq!(random_walk.decide(1.0, 100.0, &mut rng), Decision::Accept);
+
+        // Reiniciamos tras tres fallos consecutivos, y una mejora resetea la cuenta
+        let mut restart = parse_acceptance_criterion("restart:3").unwrap();
+        assert_eq!(restart.decide(1.0, 2.0, &mut rng), Decision::Reject);
+        assert_eq!(restart.decide(1.0, 2.0, &mut rng), Decision::Reject);
+        assert_eq!(restart.decide(1.0, 0.5, &mut rng), Decision::Accept);
+        assert_eq!(restart.decide(0.5, 2.0, &mut rng), Decision::Reject);
+        assert_eq!(restart.decide(0.5, 2.0, &mut rng), Decision::Reject);
+        assert_eq!(restart.decide(0.5, 2.0, &mut rng), Decision::Restart);
+
+        assert!(parse_acceptance_criterion("no_existe").is_err());
+        assert!(parse_acceptance_criterion("metropolis:abc").is_err());
+        assert!(parse_acceptance_criterion("restart:abc").is_err());
+        assert!(parse_acceptance_criterion("improve:3").is_err());
+    }
+
+    #[test]
+    fn test_metropolis_acceptance(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let mut metropolis = Metropolis::new(0.001);
+
+        // Las soluciones que no empeoran se aceptan siempre, y a temperatura muy baja no
+        // aceptamos empeoramientos
+        for _ in 0..100{
+            assert_eq!(metropolis.decide(1.0, 0.5, &mut rng), Decision::Accept);
+            assert_eq!(metropolis.decide(1.0, 1.0, &mut rng), Decision::Accept);
+            assert_eq!(metropolis.decide(0.0, 1.0, &mut rng), Decision::Reject);
+        }
+
+        // Las que empeoran se aceptan con probabilidad exp(-delta / T)
+        metropolis.set_temperature(1.0);
+        let accepted = (0..10000).filter(|_| metropolis.decide(0.0, 1.0, &mut rng) == Decision::Accept).count();
+        let exp_accepted = 10000.0 * (-1.0_f64).exp();
+        assert!((accepted as f64 - exp_accepted).abs() < 200.0);
+    }
+}
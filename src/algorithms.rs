@@ -9,3 +9,132 @@ pub mod memetic;
 pub mod multistart_local_search;
 pub mod iterative_local_search;
 pub mod simulated_annealing;
+pub mod tabu_search;
+pub mod variable_neighbourhood_search;
+pub mod grasp;
+pub mod late_acceptance;
+pub mod threshold_accepting;
+pub mod great_deluge;
+pub mod random_neighbour_search;
+pub mod iterated_greedy;
+pub mod ant_colony;
+pub mod scatter_search;
+pub mod path_relinking;
+pub mod nsga2;
+pub mod island_model;
+pub mod chc;
+
+use crate::metaheuristic::Metaheuristic;
+use crate::problem_datatypes::population::MemeticType;
+
+/// Registro con todos los algoritmos disponibles, en el orden en el que se muestran en la ayuda
+/// Para añadir un algoritmo nuevo basta con implementar Metaheuristic y añadirlo aqui
+///
+/// Los algoritmos greedy van los ultimos porque no usan el fitness para guiar la busqueda
+pub fn all_metaheuristics() -> Vec<Box<dyn Metaheuristic>>{
+    return vec![
+        Box::new(local_search::LocalSearch),
+        Box::new(simulated_annealing::SimulatedAnnealing),
+        Box::new(late_acceptance::LateAcceptance),
+        Box::new(threshold_accepting::ThresholdAccepting),
+        Box::new(great_deluge::GreatDeluge::new(false)),
+        Box::new(great_deluge::GreatDeluge::new(true)),
+        Box::new(multistart_local_search::MultiStartLocalSearch),
+        Box::new(iterative_local_search::IterativeLocalSearch::new(true)),
+        Box::new(iterative_local_search::IterativeLocalSearch::new(false)),
+        Box::new(tabu_search::TabuSearch),
+        Box::new(variable_neighbourhood_search::VariableNeighbourhoodSearch::new(false)),
+        Box::new(variable_neighbourhood_search::VariableNeighbourhoodSearch::new(true)),
+        Box::new(grasp::Grasp),
+        Box::new(iterated_greedy::IteratedGreedy),
+        Box::new(ant_colony::AntColony),
+        Box::new(scatter_search::ScatterSearch),
+
+        Box::new(generational_genetic::GenerationalGenetic::new(true)),
+        Box::new(generational_genetic::GenerationalGenetic::new(false)),
+        Box::new(steady_genetic::SteadyGenetic::new(true)),
+        Box::new(steady_genetic::SteadyGenetic::new(false)),
+        Box::new(nsga2::Nsga2),
+        Box::new(island_model::IslandModel),
+        Box::new(chc::Chc),
+
+        Box::new(memetic::Memetic::new(MemeticType::All)),
+        Box::new(memetic::Memetic::new(MemeticType::Random)),
+        Box::new(memetic::Memetic::new(MemeticType::Elitist)),
+
+        Box::new(copkmeans::Copkmeans::new(true)),
+        Box::new(copkmeans::Copkmeans::new(false)),
+    ];
+}
+
+/// Busca en el registro el algoritmo con el nombre dado
+pub fn find_metaheuristic(name: &str) -> Option<Box<dyn Metaheuristic>>{
+    return all_metaheuristics().into_iter().find(|metaheuristic| metaheuristic.name() == name);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::{all_metaheuristics, find_metaheuristic};
+    use crate::metaheuristic::Parameters;
+    use crate::problem_datatypes::ProblemInstance;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_names_are_unique(){
+        let names: Vec<&str> = all_metaheuristics().iter().map(|metaheuristic| metaheuristic.name()).collect();
+        let unique_names: HashSet<&str> = names.iter().cloned().collect();
+        assert_eq!(names.len(), unique_names.len());
+
+        for name in names{
+            assert_eq!(find_metaheuristic(name).unwrap().name(), name);
+        }
+        assert!(find_metaheuristic("no_existe").is_none());
+    }
+
+    #[test]
+    // Todos los algoritmos del registro se pueden lanzar con sus parametros por defecto (salvo el
+    // numero de evaluaciones, que reducimos para que el test sea rapido) sin pasarse del maximo de
+    // evaluaciones, que es el mismo para todos. Tambien los greedy, que acotan sus iteraciones
+    fn test_every_metaheuristic_runs(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let max_fitness_evaluations = 300;
+
+        for metaheuristic in all_metaheuristics(){
+            let mut params = Parameters::new();
+            let specs = metaheuristic.parameters();
+            if specs.iter().any(|spec| spec.get_name() == "max_fitness_evaluations"){
+                params.insert("max_fitness_evaluations", &max_fitness_evaluations.to_string());
+            }
+
+            let result = metaheuristic.run(&instance, &params, &mut rng).unwrap();
+            assert!(result.get_solution().is_valid() == true, "{} devuelve una solucion no valida", metaheuristic.name());
+            assert!(result.get_fitness_evaluations() <= max_fitness_evaluations, "{} se pasa del maximo de evaluaciones", metaheuristic.name());
+        }
+    }
+
+    #[test]
+    // Sin evaluaciones del fitness, ningun algoritmo debe fallar: devuelven una solucion valida
+    // sin evaluar y no consumen ninguna evaluacion
+    fn test_every_metaheuristic_runs_without_budget(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+
+        for metaheuristic in all_metaheuristics(){
+            let specs = metaheuristic.parameters();
+            if specs.iter().any(|spec| spec.get_name() == "max_fitness_evaluations") == false{
+                continue;
+            }
+
+            let mut params = Parameters::new();
+            params.insert("max_fitness_evaluations", "0");
+
+            let result = metaheuristic.run(&instance, &params, &mut rng).unwrap();
+            assert!(result.get_solution().is_valid() == true, "{} devuelve una solucion no valida", metaheuristic.name());
+            assert_eq!(result.get_fitness_evaluations(), 0, "{} consume evaluaciones sin tenerlas", metaheuristic.name());
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::problem_datatypes::{Solution, ProblemInstance, Point};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::{copkmeans, local_search};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::ImproveOnly;
+use crate::utils;
+
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Sistema de hormigas MAX-MIN: cada hormiga construye una solucion asignando los puntos a
+/// clusters segun una matriz de feromona (punto x cluster) y una informacion heuristica
+pub struct AntColony;
+
+impl Metaheuristic for AntColony{
+    fn name(&self) -> &'static str{
+        return "ant_colony";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Sistema de hormigas MAX-MIN";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("number_of_ants", "Hormigas que construyen una solucion en cada iteracion", Some("10")),
+            ParameterSpec::new("pheromone_weight", "Exponente de la feromona en la regla de transicion", Some("1")),
+            ParameterSpec::new("heuristic_weight", "Exponente de la informacion heuristica en la regla de transicion", Some("2")),
+            ParameterSpec::new("evaporation", "Fraccion de la feromona que se evapora en cada iteracion", Some("0.1")),
+            ParameterSpec::new("local_search", "Aplicar busqueda local a la mejor hormiga de cada iteracion", Some("false")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let colony = ColonyParameters{
+            number_of_ants: params.get::<usize>("number_of_ants")?,
+            pheromone_weight: params.get::<f64>("pheromone_weight")?,
+            heuristic_weight: params.get::<f64>("heuristic_weight")?,
+            evaporation: params.get::<f64>("evaporation")?,
+            local_search: params.get::<bool>("local_search")?,
+        };
+
+        return Ok(run(instance, max_fitness_evaluations, colony, rng));
+    }
+}
+
+/// Parametros del sistema de hormigas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColonyParameters{
+    /// Hormigas que construyen una solucion en cada iteracion
+    pub number_of_ants: usize,
+
+    /// Exponente de la feromona en la regla de transicion
+    pub pheromone_weight: f64,
+
+    /// Exponente de la informacion heuristica en la regla de transicion
+    pub heuristic_weight: f64,
+
+    /// Fraccion de la feromona que se evapora en cada iteracion
+    pub evaporation: f64,
+
+    /// Aplicar busqueda local a la mejor hormiga de cada iteracion antes de depositar feromona
+    pub local_search: bool,
+}
+
+/// Ejecuta el sistema de hormigas consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, colony: ColonyParameters, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, colony, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta el sistema de hormigas descontando las evaluaciones del fitness del evaluador dado
+///
+/// Partimos de la solucion greedy de copkmeans, que da los centroides de la informacion heuristica
+/// y el valor inicial de la feromona. En cada iteracion cada hormiga construye y evalua una
+/// solucion, y solo la mejor hormiga de la iteracion deposita feromona. La feromona se mantiene
+/// entre los limites de MAX-MIN, que dependen del fitness de la mejor solucion encontrada. En la
+/// evolucion del fitness guardamos el fitness de la mejor hormiga de cada iteracion
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, colony: ColonyParameters, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let mut best_solution = copkmeans::randomized_greedy_solution(instance, 0.0, rng);
+    let mut best_fitness = match evaluator.fitness(&best_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (best_solution, fitness_evolution),
+    };
+
+    // Empezamos con toda la feromona en el limite superior, como en MAX-MIN
+    let number_of_points = instance.number_of_points();
+    let (_, max_pheromone) = pheromone_bounds(best_fitness, colony.evaporation, number_of_points);
+    let mut pheromone = Array2::from_elem((number_of_points, instance.get_number_of_clusters() as usize), max_pheromone);
+
+    while evaluator.budget_left() == true{
+        // Las hormigas se guian por los centroides de la mejor solucion encontrada
+        let centroids = solution_centroids(&best_solution);
+
+        let mut iteration_best: Option<(Solution, f64)> = None;
+        for _ in 0..colony.number_of_ants{
+            let ant = construct_solution(instance, &pheromone, &centroids, colony, rng);
+            let ant_fitness = match evaluator.fitness(&ant){
+                Some(fitness) => fitness,
+                None => break,
+            };
+
+            let improves = match &iteration_best{
+                Some((_, iteration_best_fitness)) => ant_fitness < *iteration_best_fitness,
+                None => true,
+            };
+            if improves == true{
+                iteration_best = Some((ant, ant_fitness));
+            }
+        }
+
+        let (mut iteration_best_solution, mut iteration_best_fitness) = match iteration_best{
+            Some(iteration_best) => iteration_best,
+
+            // Se han agotado las evaluaciones antes de evaluar ninguna hormiga
+            None => break,
+        };
+
+        if colony.local_search == true{
+            let (local_solution, _) = local_search::run_from_init_sol(evaluator, &iteration_best_solution, &mut ImproveOnly, rng);
+            if let Some(local_fitness) = evaluator.fitness(&local_solution){
+                iteration_best_solution = local_solution;
+                iteration_best_fitness = local_fitness;
+            }
+        }
+
+        fitness_evolution.add_iteration(iteration_best_fitness);
+
+        if iteration_best_fitness < best_fitness{
+            best_solution = iteration_best_solution.clone();
+            best_fitness = iteration_best_fitness;
+        }
+
+        update_pheromone(&mut pheromone, &iteration_best_solution, iteration_best_fitness, best_fitness, colony.evaporation);
+    }
+
+    return (best_solution, fitness_evolution);
+}
+
+/// Limites (minimo, maximo) de la feromona en MAX-MIN, segun el fitness de la mejor solucion
+/// encontrada. El maximo es 1 / (evaporation * best_fitness), el valor al que converge la feromona
+/// si la mejor solucion deposita siempre, y el minimo es el maximo dividido entre
+/// 2 * number_of_points
+fn pheromone_bounds(best_fitness: f64, evaporation: f64, number_of_points: usize) -> (f64, f64){
+    let max_pheromone = 1.0 / (evaporation * best_fitness.max(f64::EPSILON));
+    let min_pheromone = max_pheromone / (2.0 * number_of_points as f64);
+    return (min_pheromone, max_pheromone);
+}
+
+/// Evapora la feromona y deposita 1 / fitness en las asignaciones de la solucion dada, dejando
+/// despues la feromona entre los limites de MAX-MIN
+fn update_pheromone(pheromone: &mut Array2<f64>, solution: &Solution, fitness: f64, best_fitness: f64, evaporation: f64){
+    let (min_pheromone, max_pheromone) = pheromone_bounds(best_fitness, evaporation, pheromone.nrows());
+
+    pheromone.mapv_inplace(|value| (1.0 - evaporation) * value);
+    for (point_index, cluster) in solution.get_cluster_indexes().iter().enumerate(){
+        pheromone[[point_index, *cluster as usize]] += 1.0 / fitness.max(f64::EPSILON);
+    }
+
+    pheromone.mapv_inplace(|value| value.max(min_pheromone).min(max_pheromone));
+}
+
+/// Centroides de cada uno de los clusters de una solucion valida
+fn solution_centroids(solution: &Solution) -> Vec<Point>{
+    return (0..solution.get_number_of_clusters() as u32)
+        .map(|cluster| Point::calculate_centroid(&solution.get_points_in_cluster(cluster)))
+        .collect();
+}
+
+/// Una hormiga construye una solucion recorriendo los puntos en orden aleatorio. Cada punto se
+/// asigna a un cluster con probabilidad proporcional a feromona^pheromone_weight *
+/// heuristica^heuristic_weight, donde la heuristica es 1 / (1 + coste) y el coste el de
+/// copkmeans::get_assignment_costs: restricciones violadas con los puntos ya asignados mas la
+/// distancia normalizada al centroide. Si algun cluster queda vacio, reparamos la solucion
+fn construct_solution(
+    instance: &Arc<ProblemInstance>,
+    pheromone: &Array2<f64>,
+    centroids: &[Point],
+    colony: ColonyParameters,
+    rng: &mut StdRng
+) -> Solution{
+    let data_points = instance.get_data_points();
+    let number_of_clusters = instance.get_number_of_clusters();
+
+    let mut cluster_indixes = vec![copkmeans::UNASSIGNED; data_points.len()];
+    let mut point_indexes: Vec<usize> = (0..data_points.len()).collect();
+    point_indexes.shuffle(rng);
+
+    for point_index in point_indexes{
+        let costs = copkmeans::get_assignment_costs(
+            &cluster_indixes,
+            number_of_clusters,
+            instance.get_constraints(),
+            point_index as u32,
+            &data_points.get_points()[point_index],
+            centroids,
+        );
+
+        let weights: Vec<f64> = costs.iter().enumerate()
+            .map(|(cluster, cost)| {
+                let heuristic = 1.0 / (1.0 + cost);
+                pheromone[[point_index, cluster]].powf(colony.pheromone_weight) * heuristic.powf(colony.heuristic_weight)
+            })
+            .collect();
+
+        cluster_indixes[point_index] = utils::roulette_indexes(&weights, 1, rng)[0] as u32;
+    }
+
+    let mut solution = Solution::new(cluster_indixes, instance);
+    if solution.is_valid() == false{
+        solution.repair_solution(rng);
+    }
+
+    return solution;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::ant_colony::{update_pheromone, pheromone_bounds};
+    use crate::problem_datatypes::{ProblemInstance, Solution};
+
+    use ndarray::Array2;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // Tras actualizar, la feromona queda entre los limites de MAX-MIN y es mayor en las
+    // asignaciones de la solucion que deposita
+    fn test_update_pheromone(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let mut solution = Solution::generate_random_solution(&instance, &mut rng);
+        solution.repair_solution(&mut rng);
+
+        let fitness = 0.5;
+        let evaporation = 0.1;
+        let number_of_points = instance.number_of_points();
+        let number_of_clusters = instance.get_number_of_clusters() as usize;
+        let (min_pheromone, max_pheromone) = pheromone_bounds(fitness, evaporation, number_of_points);
+
+        let mut pheromone = Array2::from_elem((number_of_points, number_of_clusters), min_pheromone);
+        for _ in 0..100{
+            update_pheromone(&mut pheromone, &solution, fitness, fitness, evaporation);
+        }
+
+        for value in pheromone.iter(){
+            assert!(*value >= min_pheromone && *value <= max_pheromone);
+        }
+
+        let cluster_indexes = solution.get_cluster_indexes();
+        for point_index in 0..number_of_points{
+            for cluster in 0..number_of_clusters{
+                if cluster_indexes[point_index] as usize == cluster{
+                    assert!(pheromone[[point_index, cluster]] > max_pheromone * 0.99);
+                }else{
+                    assert_eq!(pheromone[[point_index, cluster]], min_pheromone);
+                }
+            }
+        }
+    }
+}
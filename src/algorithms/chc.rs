@@ -0,0 +1,167 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::problem_datatypes::population::Population;
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Algoritmo genetico adaptativo CHC (Eshelman): seleccion elitista, cruce HUX con prevencion de
+/// incesto y reinicio cataclismico de la poblacion cuando converge
+pub struct Chc;
+
+impl Metaheuristic for Chc{
+    fn name(&self) -> &'static str{
+        return "chc";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Algoritmo genetico CHC, con prevencion de incesto y reinicio cataclismico";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion", Some("50")),
+            ParameterSpec::new("initial_threshold", "Umbral inicial de la prevencion de incesto (por defecto, numero de puntos / 4)", None),
+            ParameterSpec::new("divergence_rate", "Proporcion de puntos que se reasignan al reiniciar la poblacion", Some("0.35")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion", Some("1")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let population_size = params.get::<i32>("population_size")?;
+        let initial_threshold = params.get_optional::<usize>("initial_threshold")?.unwrap_or(instance.number_of_points() / 4);
+        let divergence_rate = params.get::<f64>("divergence_rate")?;
+        let number_of_workers = params.get::<usize>("number_of_workers")?;
+
+        return Ok(run(instance, max_fitness_evaluations, population_size, initial_threshold, divergence_rate, number_of_workers, rng));
+    }
+}
+
+/// Ejecuta el CHC consumiendo como mucho max_fitness_evaluations evaluaciones del fitness
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    population_size: i32,
+    initial_threshold: usize,
+    divergence_rate: f64,
+    number_of_workers: usize,
+    rng: &mut StdRng
+) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, population_size, initial_threshold, divergence_rate, number_of_workers, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta el CHC descontando las evaluaciones del fitness del evaluador dado
+///
+/// En cada generacion emparejamos aleatoriamente a toda la poblacion y cruzamos con HUX las
+/// parejas que superan el umbral de incesto (ver Population::cross_population_hux). Padres e
+/// hijos compiten por quedarse en la poblacion. Si no entra ningun hijo, decrementamos el umbral,
+/// y cuando llega a cero reiniciamos la poblacion a partir del mejor individuo y devolvemos el
+/// umbral a su valor inicial. En la evolucion del fitness guardamos el fitness del mejor
+/// individuo de la poblacion en cada generacion
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    population_size: i32,
+    initial_threshold: usize,
+    divergence_rate: f64,
+    number_of_workers: usize,
+    rng: &mut StdRng
+) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let mut current_population = Population::new_random_population(instance, population_size, rng);
+    current_population.evaluate_all_individuals(number_of_workers, evaluator);
+    let mut incest_prevention = IncestPrevention::new(initial_threshold);
+
+    while evaluator.budget_left() == true{
+        let offspring = current_population.cross_population_hux(incest_prevention.threshold, rng);
+        offspring.evaluate_all_individuals(number_of_workers, evaluator);
+
+        let (new_population, entered_children) = current_population.elitist_survival(&offspring, evaluator);
+        current_population = new_population;
+
+        if incest_prevention.after_generation(entered_children) == true{
+            current_population = current_population.cataclysmic_restart(divergence_rate, evaluator, rng);
+            current_population.evaluate_all_individuals(number_of_workers, evaluator);
+        }
+
+        let (best_individual, _) = current_population.get_best_individual(evaluator);
+        fitness_evolution.add_iteration(best_individual.fitness());
+    }
+
+    return (current_population.best_solution(evaluator, rng), fitness_evolution);
+}
+
+/// Umbral de la prevencion de incesto: solo cruzamos las parejas que difieren en mas puntos
+struct IncestPrevention{
+    initial_threshold: usize,
+    threshold: usize,
+}
+
+impl IncestPrevention{
+    fn new(initial_threshold: usize) -> Self{
+        return Self{initial_threshold, threshold: initial_threshold};
+    }
+
+    /// Actualiza el umbral tras una generacion en la que han entrado entered_children hijos en la
+    /// poblacion. Si no ha entrado ninguno, relajamos la prevencion de incesto y, si ya no
+    /// podemos, devolvemos true para que se reinicie la poblacion, volviendo el umbral a su
+    /// valor inicial
+    fn after_generation(&mut self, entered_children: usize) -> bool{
+        if entered_children > 0{
+            return false;
+        }
+
+        self.threshold = self.threshold.saturating_sub(1);
+        if self.threshold == 0{
+            self.threshold = self.initial_threshold;
+            return true;
+        }
+
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::chc::IncestPrevention;
+
+    #[test]
+    // El umbral solo baja en las generaciones en las que no entra ningun hijo, y al llegar a cero
+    // se reinicia la poblacion y el umbral vuelve a su valor inicial
+    fn test_incest_threshold_decreases_until_restart(){
+        let mut incest_prevention = IncestPrevention::new(3);
+
+        assert!(incest_prevention.after_generation(2) == false);
+        assert_eq!(incest_prevention.threshold, 3);
+
+        assert!(incest_prevention.after_generation(0) == false);
+        assert_eq!(incest_prevention.threshold, 2);
+        assert!(incest_prevention.after_generation(1) == false);
+        assert_eq!(incest_prevention.threshold, 2);
+        assert!(incest_prevention.after_generation(0) == false);
+        assert_eq!(incest_prevention.threshold, 1);
+
+        assert!(incest_prevention.after_generation(0) == true);
+        assert_eq!(incest_prevention.threshold, 3);
+
+        // Con umbral inicial 1, la primera generacion sin hijos nuevos ya reinicia la poblacion
+        let mut incest_prevention = IncestPrevention::new(1);
+        assert!(incest_prevention.after_generation(0) == true);
+        assert_eq!(incest_prevention.threshold, 1);
+    }
+}
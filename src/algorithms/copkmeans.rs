@@ -1,114 +1,161 @@
-use crate::problem_datatypes;
-use crate::problem_datatypes::ConstraintType;
 use crate::problem_datatypes::Constraints;
 use crate::problem_datatypes::DataPoints;
 use crate::problem_datatypes::Point;
 use crate::problem_datatypes::Solution;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::arg_parser::SearchType;
-use crate::utils;
+use crate::run_result::RunResult;
+use crate::metaheuristic;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
 
 use rand::rngs::StdRng;
-use std::process::exit; // Para hacer shuffle de un vector
-use rand::seq::SliceRandom;
+use rand::seq::SliceRandom; // Para hacer shuffle de un vector
+use simple_error::bail;
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Lanza el algoritmo y muestra los resultados (solucion, tiempos...)
-/// Esto para que la funcion main no sea demasiado grande
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, rng: &mut StdRng, robust: bool){
-    // Realizamos la busqueda greedy
-    //
-    // Si devuelve None, es porque la generacion aleatoria de centroides ha dejado
-    // clusters sin elementos, y hay que repetir el algoritmo
-    //
-    // Estoy contabilizando el tiempo que perdemos cuando tenemos que repetir la asignacion de
-    // centroides aleatorios, pero gracias a que devolvemos Option<Solution> esto es muy facil de
-    // cambiar
-    //
-    // Permitimos un numero maximo de reseteos para evitar ciclar infinitamente
-    let before = Instant::now();
-    let mut greedy_solution: Option<problem_datatypes::Solution>;
-    let mut fitness_evolution: FitnessEvolution;
-    let max_resets = 100;
-    let mut current_reset = 0;
-    loop {
-        let (greedy_result, fit_result) = run(&data_points, &constraints, program_arguments.get_number_of_clusters(), rng, robust);
-        greedy_solution = greedy_result;
-        fitness_evolution = fit_result;
-
-        match greedy_solution {
-            // Hemos contrado solucion, paramos de iterar
-            Some(_) => break,
-
-            // No hemos encontrado solucion, por lo que no hacemos nada, lo que provoca que sigamos
-            // iterando
-            None => (),
+/// Valor que toma en la asignacion de clusters un punto que todavia no esta asignado a ningun
+/// cluster. Las restricciones con puntos sin asignar no cuentan como violadas
+pub const UNASSIGNED: u32 = u32::MAX;
+
+/// Busqueda greedy COPKMeans
+/// Si robust es true, los centroides iniciales son puntos del conjunto de datos en vez de puntos
+/// completamente aleatorios
+pub struct Copkmeans{
+    robust: bool,
+}
+
+impl Copkmeans{
+    pub fn new(robust: bool) -> Self{
+        return Self{robust};
+    }
+}
+
+impl Metaheuristic for Copkmeans{
+    fn name(&self) -> &'static str{
+        if self.robust == true{
+            return "copkmeans_robust";
         }
+        return "copkmeans";
+    }
 
-            current_reset = current_reset + 1;
-            if current_reset == max_resets{
-                println!("--> Se han agotado los {} reseteos maximos por dejar clusters vacios", max_resets);
-                break;
-            }
+    fn description(&self) -> &'static str{
+        if self.robust == true{
+            return "Busqueda greedy, centroides iniciales tomados de los datos";
+        }
+        return "Busqueda greedy";
     }
-    let after = Instant::now();
 
-    // Calculamos la duracion en el formato que se nos especifica
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        // Permitimos un numero maximo de reseteos y de iteraciones para evitar ciclar infinitamente
+        return vec![
+            ParameterSpec::new("max_resets", "Repeticiones permitidas cuando los centroides aleatorios dejan clusters vacios", Some("100")),
+            ParameterSpec::new("max_iterations", "Maximo de reasignaciones de los puntos en cada repeticion, aunque los centroides sigan cambiando", Some("50")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let (result, _) = self.run_greedy(instance, params, rng)?;
+        return Ok(result);
+    }
 
-    // Tomamos la solucion del Option
-    let greedy_solution = match greedy_solution{
-        Some(sol) => sol,
-        None => {
-            println!("Como hemos agotado todos los reseteos, no podemos mostrar métricas");
-            exit(-1);
+    /// Ademas del resultado, mostramos si alguna pasada ha terminado por agotar las iteraciones
+    /// en lugar de porque los centroides dejen de cambiar
+    fn run_and_show_results(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<(), Box<dyn Error>>{
+        let (result, iterations_exhausted) = self.run_greedy(instance, params, rng)?;
+        metaheuristic::show_results(self.name(), self.description(), &result);
+        if iterations_exhausted == true{
+            println!("--> Hemos acabado copkmeans al agotar las iteraciones maximas");
+            println!();
         }
-    };
 
-    // Para que no sea mutable
-    let duration_numeric = duration_numeric;
+        return Ok(());
+    }
+}
+
+impl Copkmeans{
+    /// Lanza la busqueda greedy con los parametros dados. Devuelve el resultado y si la pasada
+    /// que lo ha generado ha terminado por agotar las iteraciones
+    fn run_greedy(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<(RunResult, bool), Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_resets = params.get::<i32>("max_resets")?;
+        let max_iterations = params.get::<i32>("max_iterations")?;
+
+        if instance.number_of_points() == 0 || instance.get_number_of_clusters() <= 0{
+            bail!("La busqueda greedy necesita al menos un punto y un cluster");
+        }
+
+        match run_with_details(instance, rng, self.robust, max_resets, max_iterations){
+            Some(result) => return Ok(result),
+            None => bail!("Se han agotado los {} reseteos maximos por dejar clusters vacios", max_resets),
+        }
+    }
+}
+
+/// Realiza la busqueda greedy y devuelve su resultado
+///
+/// Si la generacion aleatoria de centroides deja clusters sin elementos, hay que repetir el
+/// algoritmo. Repetimos como mucho max_resets veces, devolviendo None si en ninguna encontramos
+/// solucion. El tiempo perdido en las repeticiones se contabiliza en el resultado. Tambien
+/// devolvemos None si la instancia no tiene puntos o clusters
+///
+/// Cada repeticion reasigna los puntos hasta que los centroides no cambian, o como mucho
+/// max_iterations veces, pues la asignacion puede ciclar sin que los centroides se estabilicen
+///
+/// Este algoritmo no usa el fitness para guiar la busqueda (solo lo calculamos para guardar su
+/// evolucion), asi que el resultado indica cero evaluaciones del fitness consumidas
+pub fn run(instance: &Arc<ProblemInstance>, rng: &mut StdRng, robust: bool, max_resets: i32, max_iterations: i32) -> Option<RunResult>{
+    let (result, _) = run_with_details(instance, rng, robust, max_resets, max_iterations)?;
+    return Some(result);
+}
+
+/// Igual que run, pero devuelve ademas si la pasada que ha encontrado la solucion ha terminado
+/// por agotar las iteraciones
+fn run_with_details(instance: &Arc<ProblemInstance>, rng: &mut StdRng, robust: bool, max_resets: i32, max_iterations: i32) -> Option<(RunResult, bool)>{
+    if instance.number_of_points() == 0 || instance.get_number_of_clusters() <= 0{
+        return None;
+    }
 
-    // Mostramos los resultados
-    println!("==> Busqueda greedy");
-    println!("La distancia global instracluster de la solucion es: {}", greedy_solution.global_cluster_mean_distance());
-    println!("Las distancias intraclusters son:");
-    println!("El numero de restricciones violadas (infeasibility) es: {}", greedy_solution.infeasibility());
-    println!("El valor de fitness es: {}", greedy_solution.fitness());
-    println!("El valor de lambda es: {}", greedy_solution.get_lambda());
-    println!("Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(SearchType::LocalSearch)));
-    println!("");
+    let before = Instant::now();
+    for _ in 0..max_resets{
+        let (greedy_solution, fitness_evolution, iterations_exhausted) = run_once(instance, rng, robust, max_iterations);
 
+        // Si no encontramos solucion, seguimos iterando
+        if let Some(greedy_solution) = greedy_solution{
+            return Some((RunResult::new(greedy_solution, 0, before.elapsed(), fitness_evolution), iterations_exhausted));
+        }
+    }
 
+    return None;
 }
 
-/// Ejecuta la metaheuristica de busqueda local y devuelve la solucion encontrada
+/// Ejecuta una pasada de la busqueda greedy y devuelve la solucion encontrada, o None si deja
+/// clusters vacios, junto a la evolucion del fitness y si hemos agotado las iteraciones
 /// Si robust es true, entonces aplicamos como centroides iniciales puntos
 /// del dataset, como nos indica el profesor de prácticas, porque asi es mas robusto
-fn run<'a, 'b>(
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
+/// Reasignamos los puntos como mucho max_iterations veces
+/// La instancia debe tener al menos un punto y un cluster
+fn run_once(
+    instance: &Arc<ProblemInstance>,
     rng: &mut StdRng,
-    robust: bool
-) -> (Option<Solution<'a, 'b>>, FitnessEvolution) {
+    robust: bool,
+    max_iterations: i32
+) -> (Option<Solution>, FitnessEvolution, bool) {
+    let data_points = instance.get_data_points();
+    let constraints = instance.get_constraints();
+    let number_of_clusters = instance.get_number_of_clusters();
+
     // Para llevar la cuenta de como evoluciona el valor del fitness de las
     // iteraciones obtenidas
     let mut fitness_evolution = FitnessEvolution::new();
 
     // Numero de coordenadas que componen cada uno de los puntos
     // Necesario para saber cuantas coordenadas debe tener nuestros centroides aleatorios
+    // Sin puntos no hay solucion que devolver
     let point_dimension = match data_points.point_dimension() {
         Some(dimension) => dimension as i32,
-        None => {
-            eprintln!(
-                "[Err] la estructura DataPoints no tiene puntos de los que obtener su dimension"
-            );
-            eprintln!("No se puede continuar con la busqueda greedy");
-            exit(-1);
-        }
+        None => return (None, fitness_evolution, false),
     };
 
     // Tomamos los centroides aleatorios
@@ -124,14 +171,13 @@ fn run<'a, 'b>(
     //
     // Lo que vamos a hacer es ir modificando esta solucion. Necesitamos dos variables para ir
     // comparando como cambian, y parar en caso de que no cambien
-    let mut current_cluster_indixes: Vec<u32> = vec![0; data_points.len() as usize];
+    let mut current_cluster_indixes: Vec<u32> = vec![0; data_points.len()];
 
     // Iteramos hasta que los centroides no cambien
     let mut centroids_have_changed = true;
 
-    // Controlamos las iteracione en caso de que robust = true
-    // En otro caso, iteramos hasta que los centroides no cambien
-    let max_iterations = 50;
+    // Acotamos las iteraciones, pues la asignacion puede ciclar sin que los centroides dejen
+    // de cambiar
     let mut curr_iteration = 0;
 
     while centroids_have_changed == true && curr_iteration < max_iterations{
@@ -140,8 +186,8 @@ fn run<'a, 'b>(
         // asignando al cluster que menos restricciones viole en esa iteracion. En caso de empates,
         // se toma el cluster con centroide mas cercano
         let new_cluster_indixes = assign_points_to_clusters(
-            &data_points,
-            &constraints,
+            data_points,
+            constraints,
             &current_centroids,
             &current_cluster_indixes,
             number_of_clusters,
@@ -153,28 +199,16 @@ fn run<'a, 'b>(
         // generamos centroides aleatorios. No se si esto es exclusivo de la primera
         // iteracion con centroides aleatorios
         if valid_cluster_configuration(&new_cluster_indixes, number_of_clusters) == false {
-            // Para mostrar algunos datos de la solucion problematica
-            let tmp_solution = Solution::new(new_cluster_indixes.clone(), data_points, constraints, number_of_clusters);
-
-            eprintln!("[Err] La solucion greedy actual ha dejado clusters sin puntos");
-            eprintln!(
-                "Estos clusters vacios son: {:?}",
-                get_cluster_without_point_indixes(&new_cluster_indixes, number_of_clusters)
-            );
-            eprintln!("Restricciones violadas: {:?}", tmp_solution.infeasibility());
-            eprintln!("Devolvemos una solucion vacia para que se vuelva a iniciar el algoritmo con otros centroides aleatorios");
-            eprintln!("Este contratiempo cuenta en el tiempo de ejecucion del algoritmo");
-
             // Devuelvo Option::None para que desde el punto en el que se llama
             // al algoritmo, se reinicie la búsqueda y se tome la decision de si
             // contabilizar el tiempo extra de volver a genera una primera solucion
             // aleatoria o si no contabilizarlo (mas control al caller)
-            return (None, FitnessEvolution::new());
+            return (None, FitnessEvolution::new(), false);
         }
 
         // Calculamos los nuevos centroides
         let new_centroids =
-            calculate_new_centroids(&new_cluster_indixes, &data_points, number_of_clusters);
+            calculate_new_centroids(&new_cluster_indixes, data_points, number_of_clusters);
 
         // Comprobamos si los centroides han cambiado
         centroids_have_changed = centroids_are_different(&current_centroids, &new_centroids);
@@ -185,41 +219,22 @@ fn run<'a, 'b>(
 
 
         // Calculamos la solucion actual para tener el fitness en esta iteracion
-        let curr_sol = Solution::new(
-            current_cluster_indixes.clone(),
-            data_points,
-            constraints,
-            number_of_clusters,
-        );
+        let curr_sol = Solution::new(current_cluster_indixes.clone(), instance);
         fitness_evolution.add_iteration(curr_sol.fitness());
 
-        // En caso de que robust = true, acotamos el numero de iteraciones de forma
-        // efectiva aumentando el contador. En otro caso, al no tocar el contador
-        // no estamos teniendo en cuenta este parametro
-        if robust == true{
-            curr_iteration = curr_iteration + 1;
-        }
-    }
-
-    // Mostramos si hemos acabado por agotar las iteraciones cuando robust = true
-    if robust == true && curr_iteration > 0{
-        println!("--> Hemos acabado copkmeans al agotar las {} iteraciones maximas", max_iterations);
+        curr_iteration += 1;
     }
 
     // Devuelvo la solucion a partir del vector de asignacion de clusters y la cuenta
-    // de la evolucion del fitness
-    return (Some(Solution::new(
-        current_cluster_indixes,
-        data_points,
-        constraints,
-        number_of_clusters,
-    )), fitness_evolution);
+    // de la evolucion del fitness. Si los centroides siguen cambiando, hemos acabado por agotar
+    // las iteraciones
+    return (Some(Solution::new(current_cluster_indixes, instance)), fitness_evolution, centroids_have_changed);
 }
 
 /// Comprueba si dados dos conjuntos de centroides, estos son diferentes o no
 /// Esto es util para saber si alguno de los centroides ha cambiado en el proceso,
 /// y por tanto, si alguna asignacion de cluster ha cambiado o no
-fn centroids_are_different(past_centroids: &Vec<Point>, new_centroids: &Vec<Point>) -> bool {
+fn centroids_are_different(past_centroids: &[Point], new_centroids: &[Point]) -> bool {
     for index in 0..past_centroids.len() {
         if (new_centroids[index] == past_centroids[index]) == false {
             return true;
@@ -234,14 +249,14 @@ fn centroids_are_different(past_centroids: &Vec<Point>, new_centroids: &Vec<Poin
 // de los puntos y tomar una decision en base a ello
 // Selecciona el cluster que menos aumento de violaciones de restricciones produce
 // En caso de que haya empate, se toma el cluster mas cercano al punto
-// TODO -- borrar las comprobaciones de seguridad
+// Debe haber al menos un cluster
 fn select_best_cluster(
-    current_cluster_indixes: &Vec<u32>,
+    current_cluster_indixes: &[u32],
     number_of_clusters: i32,
     constraints: &Constraints,
     current_point_index: u32,
     current_point: &Point,
-    centroids: &Vec<Point>,
+    centroids: &[Point],
 ) -> u32 {
     // Calculo las restricciones que se violan cuando asinamos al punto representado
     // por current_point_index a cada uno de los clusters
@@ -253,17 +268,7 @@ fn select_best_cluster(
     );
 
     // Calculo el valor minimo de violaciones que produce una asignacion de cluster
-    let min_value = match violated_constraints.iter().min() {
-        Some(value) => value,
-        None => {
-            eprint!("[Err] No se pudo encontrar el minimo del numero de violaciones que supone una asignacion de cluster");
-            eprintln!(
-                "El vector de restricciones violadas es: {:?}",
-                violated_constraints
-            );
-            exit(-1);
-        }
-    };
+    let min_value = violated_constraints.iter().min().expect("Debe haber al menos un cluster al que asignar el punto");
 
     // Calculo los clusters cuya asignacion produce el minimo numero de violaciones
     // Este vector guarda los indices de los ya mencionados clusters, por ejemplo:
@@ -302,7 +307,7 @@ fn select_best_cluster(
     }
 
     // Devuelvo el indice que da la minima distancia
-    return min_cluster_indixes[min_index as usize] as u32;
+    return min_cluster_indixes[min_index] as u32;
 }
 
 /// Calcula un vector con las restricciones que se viola al realizar cada una
@@ -310,53 +315,40 @@ fn select_best_cluster(
 /// posibles clusters
 /// Es decir, vector de violaciones consecuencia de asgnar el punto current_point_index
 /// al cluster i-esimo
+/// Solo recorremos los puntos con los que current_point_index tiene restricciones, usando las
+/// listas de adyacencia de Constraints. Ignoramos los puntos sin asignar (UNASSIGNED)
 fn get_violated_constraints_per_cluster_assignment(
-    current_cluster_indixes: &Vec<u32>,
+    current_cluster_indixes: &[u32],
     number_of_clusters: i32,
     constraints: &Constraints,
     current_point_index: u32,
 ) -> Vec<u32> {
     // Vector que construimos con las restricciones violadas
-    let mut violated_constraints = vec![];
-
-    for cluster_candidate in 0..number_of_clusters as u32 {
-        // Calculo el numero de restricciones violadas para este cluster en concreto
-        // Para ello, itero sobre los indices de los puntos y los clusters a los
-        // que estan asignados dichos puntos
-        let mut current_violations = 0;
-        for (point_index, point_cluster) in current_cluster_indixes.iter().enumerate() {
-            // Miramos que restriccion tenemos entre los dos puntos
-            match constraints.get_constraint(point_index as i32, current_point_index as i32) {
-                // Hay restriccion
-                // Tenemos que comprobar con otro match segun el tipo de restriccion que sea
-                Some(constraint) => {
-                    match constraint {
-                        // Sumamos uno si el candidato a cluster no coincide
-                        // con el cluster del punto
-                        ConstraintType::MustLink => {
-                            if *point_cluster != cluster_candidate {
-                                current_violations += 1;
-                            }
-                        }
-
-                        // Sumamos uno si el candidato a cluster coincide con
-                        // el cluster del punto
-                        ConstraintType::CannotLink => {
-                            if *point_cluster == cluster_candidate {
-                                current_violations += 1;
-                            }
-                        }
-                    }
-                }
-
-                // No hay restricciones entre los dos puntos asi que no tenemos que hacer
-                // comprobaciones
-                None => (),
+    let mut violated_constraints = vec![0; number_of_clusters as usize];
+
+    // Una restriccion MustLink se viola en todos los clusters candidatos salvo en el cluster
+    // del otro punto
+    for other_point in constraints.must_link_neighbours(current_point_index as usize) {
+        let point_cluster = current_cluster_indixes[*other_point as usize];
+        if point_cluster == UNASSIGNED {
+            continue;
+        }
+
+        for cluster_candidate in 0..number_of_clusters as u32 {
+            if point_cluster != cluster_candidate {
+                violated_constraints[cluster_candidate as usize] += 1;
             }
         }
+    }
+
+    // Una restriccion CannotLink solo se viola en el cluster del otro punto
+    for other_point in constraints.cannot_link_neighbours(current_point_index as usize) {
+        let point_cluster = current_cluster_indixes[*other_point as usize];
+        if point_cluster == UNASSIGNED {
+            continue;
+        }
 
-        // Asigno el numero al vector
-        violated_constraints.push(current_violations);
+        violated_constraints[point_cluster as usize] += 1;
     }
 
     return violated_constraints;
@@ -412,7 +404,7 @@ fn generate_random_centroids_from_data_points(
     let mut centroids = vec![];
 
     // Genero indices aleatorios de los que vamos a tomar los puntos y los mezclamos
-    let mut indexes: Vec<u32> = (0 as u32.. data_points.len() as u32).collect();
+    let mut indexes: Vec<u32> = (0..data_points.len() as u32).collect();
     indexes.shuffle(rng);
 
     for i in 0..number_of_clusters {
@@ -427,27 +419,20 @@ fn generate_random_centroids_from_data_points(
 /// A partir de una asignacion de clusters y un conjunto de datos, calcula los
 /// centroides correspondientes a dicha asignacion con dichos puntos
 fn calculate_new_centroids(
-    cluster_indixes: &Vec<u32>,
+    cluster_indixes: &[u32],
     data_points: &DataPoints,
     number_of_clusters: i32,
 ) -> Vec<Point> {
-    // Generamos un struct Solution para usar algunos de sus metodos
-    // Las restricciones no me interesan para estos metodos, por tanto las dejo vacias
-    let constraints = Constraints::new();
-    let tmp_solution = Solution::new(
-        cluster_indixes.clone(),
-        data_points,
-        &constraints,
-        number_of_clusters,
-    );
+    // Agrupamos los puntos segun el cluster al que pertenecen, recorriendo los puntos en orden
+    let mut points_per_cluster: Vec<Vec<&Point>> = vec![vec![]; number_of_clusters as usize];
+    for (index, cluster) in cluster_indixes.iter().enumerate() {
+        points_per_cluster[*cluster as usize].push(&data_points.get_points()[index]);
+    }
 
     let mut new_centroids = vec![];
-    for cluster in 0..number_of_clusters as u32 {
-        // Tomamos los puntos que pertenecen a este cluster
-        let cluster_points = tmp_solution.get_points_in_cluster(cluster);
-
+    for cluster_points in &points_per_cluster {
         // Añadimos el centroide de ese conjunto de puntos
-        new_centroids.push(Point::calculate_centroid(&cluster_points));
+        new_centroids.push(Point::calculate_centroid(cluster_points));
     }
 
     return new_centroids;
@@ -457,20 +442,20 @@ fn calculate_new_centroids(
 /// Una configuracion es invalida cuando hay un cluster sin puntos
 /// Es decir, cuando un elemento de {0, 1, ..., number_of_clusters} no aparece
 /// en ninguna posicion de cluster_indixes
-fn valid_cluster_configuration(cluster_indixes: &Vec<u32>, number_of_clusters: i32) -> bool {
+fn valid_cluster_configuration(cluster_indixes: &[u32], number_of_clusters: i32) -> bool {
     let cluster_without_point_indixes =
-        get_cluster_without_point_indixes(&cluster_indixes, number_of_clusters);
-    return cluster_without_point_indixes.len() == 0;
+        get_cluster_without_point_indixes(cluster_indixes, number_of_clusters);
+    return cluster_without_point_indixes.is_empty() == true;
 }
 
 /// Toma los indices de clusters que no tienen puntos asignados
 /// Por ejemplo, {3, 4} porque ambos clusters no tienen ni un solo punto asignado
 fn get_cluster_without_point_indixes(
-    cluster_indixes: &Vec<u32>,
+    cluster_indixes: &[u32],
     number_of_clusters: i32,
 ) -> Vec<i32> {
     // Generamos todos los clusters en un vector
-    let mut cluster_without_point_indixes: Vec<i32> = (0..number_of_clusters).into_iter().collect();
+    let mut cluster_without_point_indixes: Vec<i32> = (0..number_of_clusters).collect();
 
     // Marcamos los clusters con elementos con un -1
     for cluster in cluster_indixes {
@@ -493,14 +478,14 @@ fn get_cluster_without_point_indixes(
 fn assign_points_to_clusters(
     data_points: &DataPoints,
     constraints: &Constraints,
-    current_centroids: &Vec<Point>,
-    current_cluster_indixes: &Vec<u32>,
+    current_centroids: &[Point],
+    current_cluster_indixes: &[u32],
     number_of_clusters: i32,
     rng: &mut StdRng,
 ) -> Vec<u32> {
     // Realizamos una nueva asignacion de clusters
     // -1 para saber que puntos todavia no han sido asignados a un cluster
-    let mut new_cluster_indixes: Vec<i32> = vec![-1; data_points.len() as usize];
+    let mut new_cluster_indixes: Vec<i32> = vec![-1; data_points.len()];
 
     // Recorremos aleatoriamente los puntos para irlos asignando a cada cluster
     let mut point_indexes: Vec<u32> = (0..data_points.len() as u32).collect();
@@ -509,12 +494,12 @@ fn assign_points_to_clusters(
     for index in point_indexes {
         // Calculo el cluster al que asignamos el punto actual
         new_cluster_indixes[index as usize] = select_best_cluster(
-            &current_cluster_indixes,
+            current_cluster_indixes,
             number_of_clusters,
-            &constraints,
+            constraints,
             index,
             &data_points.get_points()[index as usize],
-            &current_centroids,
+            current_centroids,
         ) as i32;
     }
 
@@ -523,15 +508,166 @@ fn assign_points_to_clusters(
     return new_cluster_indixes.into_iter().map(|x| x as u32).collect();
 }
 
+/// Construccion greedy aleatorizada para GRASP
+///
+/// Sigue el esquema de copkmeans partiendo de centroides tomados de los datos, pero en vez de
+/// asignar cada punto a su mejor cluster, lo asignamos a un cluster aleatorio de la lista
+/// restringida de candidatos (ver select_cluster_from_rcl). Con alpha = 0 es el greedy original
+///
+/// Si una pasada deja clusters vacios, reparamos la asignacion en vez de reiniciar el algoritmo.
+/// Al ser aleatoria, la asignacion puede no estabilizarse nunca, asi que acotamos el numero de
+/// pasadas como en la version robusta
+pub fn randomized_greedy_solution(instance: &Arc<ProblemInstance>, alpha: f64, rng: &mut StdRng) -> Solution{
+    let data_points = instance.get_data_points();
+    let constraints = instance.get_constraints();
+    let number_of_clusters = instance.get_number_of_clusters();
+
+    let mut current_centroids = generate_random_centroids_from_data_points(number_of_clusters, data_points, rng);
+    let mut current_cluster_indixes: Vec<u32> = vec![0; data_points.len()];
+
+    let max_iterations = 50;
+    for _ in 0..max_iterations{
+        // Recorremos aleatoriamente los puntos, asignando cada uno a un cluster de la lista
+        // restringida de candidatos
+        let mut point_indexes: Vec<u32> = (0..data_points.len() as u32).collect();
+        point_indexes.shuffle(rng);
+
+        let mut new_cluster_indixes = vec![0; data_points.len()];
+        for index in point_indexes{
+            let costs = get_assignment_costs(
+                &current_cluster_indixes,
+                number_of_clusters,
+                constraints,
+                index,
+                &data_points.get_points()[index as usize],
+                &current_centroids,
+            );
+            new_cluster_indixes[index as usize] = select_cluster_from_rcl(&costs, alpha, rng);
+        }
+
+        // No podemos calcular el centroide de un cluster vacio, asi que reparamos la asignacion
+        if valid_cluster_configuration(&new_cluster_indixes, number_of_clusters) == false{
+            let mut repaired = Solution::new(new_cluster_indixes, instance);
+            repaired.repair_solution(rng);
+            new_cluster_indixes = repaired.get_cluster_indexes();
+        }
+
+        let new_centroids = calculate_new_centroids(&new_cluster_indixes, data_points, number_of_clusters);
+        let centroids_have_changed = centroids_are_different(&current_centroids, &new_centroids);
+
+        current_cluster_indixes = new_cluster_indixes;
+        current_centroids = new_centroids;
+
+        if centroids_have_changed == false{
+            break;
+        }
+    }
+
+    return Solution::new(current_cluster_indixes, instance);
+}
+
+/// Reinserta de forma greedy los puntos removed_points de la solucion, que debe ser valida
+///
+/// Primero desasignamos todos los puntos a reinsertar. Despues los recorremos en orden aleatorio,
+/// asignando cada uno con el criterio de select_best_cluster: el cluster que menos restricciones
+/// viola con los puntos ya asignados y, en caso de empate, el de centroide mas cercano. Los
+/// centroides son los de la solucion original. Si algun cluster queda vacio, reparamos la
+/// solucion devuelta
+pub fn reinsert_points(solution: &Solution, removed_points: &[usize], rng: &mut StdRng) -> Solution{
+    let instance = solution.get_instance();
+    let data_points = instance.get_data_points();
+    let number_of_clusters = instance.get_number_of_clusters();
+
+    let mut cluster_indixes = solution.get_cluster_indexes();
+    let centroids = calculate_new_centroids(&cluster_indixes, data_points, number_of_clusters);
+
+    for point_index in removed_points{
+        cluster_indixes[*point_index] = UNASSIGNED;
+    }
+
+    let mut insertion_order = removed_points.to_vec();
+    insertion_order.shuffle(rng);
+
+    for point_index in insertion_order{
+        cluster_indixes[point_index] = select_best_cluster(
+            &cluster_indixes,
+            number_of_clusters,
+            instance.get_constraints(),
+            point_index as u32,
+            &data_points.get_points()[point_index],
+            &centroids,
+        );
+    }
+
+    let mut new_solution = Solution::new(cluster_indixes, instance);
+    if new_solution.is_valid() == false{
+        new_solution.repair_solution(rng);
+    }
+
+    return new_solution;
+}
+
+/// Coste de asignar el punto a cada uno de los clusters, siguiendo el criterio de
+/// select_best_cluster: la parte entera es el numero de restricciones violadas, y la parte
+/// decimal la distancia al centroide dividida entre la maxima distancia a un centroide, de forma
+/// que la distancia solo desempata entre clusters que violan las mismas restricciones
+/// Ignoramos los puntos sin asignar (UNASSIGNED), asi que sirve para construir soluciones punto a
+/// punto
+pub fn get_assignment_costs(
+    current_cluster_indixes: &[u32],
+    number_of_clusters: i32,
+    constraints: &Constraints,
+    current_point_index: u32,
+    current_point: &Point,
+    centroids: &[Point],
+) -> Vec<f64> {
+    let violated_constraints = get_violated_constraints_per_cluster_assignment(
+        current_cluster_indixes,
+        number_of_clusters,
+        constraints,
+        current_point_index,
+    );
+
+    let distances: Vec<f64> = centroids.iter().map(|centroid| Point::distance(current_point, centroid)).collect();
+    let max_distance = distances.iter().cloned().fold(0.0, f64::max);
+
+    let mut costs = vec![];
+    for cluster in 0..number_of_clusters as usize{
+        let normalized_distance = if max_distance > 0.0 {distances[cluster] / max_distance} else {0.0};
+
+        // Escalamos la distancia para que la parte decimal nunca llegue a 1
+        costs.push(violated_constraints[cluster] as f64 + 0.99 * normalized_distance);
+    }
+
+    return costs;
+}
+
+/// Selecciona aleatoriamente un cluster de la lista restringida de candidatos, formada por los
+/// clusters cuyo coste no supera min + alpha * (max - min)
+/// Con alpha = 0 solo se consideran los clusters de coste minimo, y con alpha = 1 cualquiera
+fn select_cluster_from_rcl(costs: &[f64], alpha: f64, rng: &mut StdRng) -> u32{
+    let min_cost = costs.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_cost = costs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let threshold = min_cost + alpha * (max_cost - min_cost);
+
+    let restricted_candidate_list: Vec<u32> = (0..costs.len() as u32).filter(|&cluster| costs[cluster as usize] <= threshold).collect();
+    return *restricted_candidate_list.choose(rng).expect("La lista restringida de candidatos no puede estar vacia");
+}
+
 #[cfg(test)]
 mod tests{
-    use crate::copkmeans::centroids_are_different;
-    use crate::copkmeans::get_violated_constraints_per_cluster_assignment;
-    use crate::copkmeans::select_best_cluster;
+    use crate::algorithms::copkmeans::centroids_are_different;
+    use crate::algorithms::copkmeans::get_violated_constraints_per_cluster_assignment;
+    use crate::algorithms::copkmeans::select_best_cluster;
+    use crate::algorithms::copkmeans::select_cluster_from_rcl;
+    use crate::algorithms::copkmeans::UNASSIGNED;
     use crate::problem_datatypes::Point;
     use crate::problem_datatypes::Constraints;
     use crate::problem_datatypes::ConstraintType;
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     #[test]
     fn test_centroids_are_different(){
         let first_centroids = vec![
@@ -584,6 +720,7 @@ mod tests{
         let mut constraints = Constraints::new();
         constraints.add_constraint(0, 1, ConstraintType::MustLink);
         constraints.add_constraint(3, 4, ConstraintType::CannotLink);
+        constraints.build_adjacency(current_cluster_indixes.len());
 
         let calc_violated_constraints_per_cluster = get_violated_constraints_per_cluster_assignment(&current_cluster_indixes, number_of_clusters, &constraints, 1);
         let exp_violated_constraints_per_cluster = vec![1, 1, 0, 1];
@@ -592,6 +729,15 @@ mod tests{
         let calc_violated_constraints_per_cluster = get_violated_constraints_per_cluster_assignment(&current_cluster_indixes, number_of_clusters, &constraints, 3);
         let exp_violated_constraints_per_cluster = vec![0, 0, 0, 1];
         assert_eq!(calc_violated_constraints_per_cluster, exp_violated_constraints_per_cluster);
+
+        // Las restricciones con puntos sin asignar no se violan
+        let current_cluster_indixes = vec![2, UNASSIGNED, 1, 2, UNASSIGNED];
+        let calc_violated_constraints_per_cluster = get_violated_constraints_per_cluster_assignment(&current_cluster_indixes, number_of_clusters, &constraints, 0);
+        let exp_violated_constraints_per_cluster = vec![0, 0, 0, 0];
+        assert_eq!(calc_violated_constraints_per_cluster, exp_violated_constraints_per_cluster);
+
+        let calc_violated_constraints_per_cluster = get_violated_constraints_per_cluster_assignment(&current_cluster_indixes, number_of_clusters, &constraints, 3);
+        assert_eq!(calc_violated_constraints_per_cluster, exp_violated_constraints_per_cluster);
     }
 
     #[test]
@@ -600,6 +746,7 @@ mod tests{
         let number_of_clusters = 4;
         let mut constraints = Constraints::new();
         constraints.add_constraint(0, 1, ConstraintType::MustLink);
+        constraints.build_adjacency(current_cluster_indixes.len());
         let current_point_index = 0;
         let centroids = vec![Point::from_vec(vec![0.0]),Point::from_vec(vec![0.0]),Point::from_vec(vec![0.0]),Point::from_vec(vec![0.0]),Point::from_vec(vec![0.0])];
 
@@ -607,4 +754,27 @@ mod tests{
         let exp_best_cluster = 1;
         assert_eq!(calc_best_cluster, exp_best_cluster);
     }
+
+    #[test]
+    fn test_select_cluster_from_rcl(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let costs = vec![1.5, 0.2, 0.3, 2.0];
+
+        // Con alpha = 0 el greedy es determinista
+        for _ in 0..100{
+            assert_eq!(select_cluster_from_rcl(&costs, 0.0, &mut rng), 1);
+        }
+
+        // Con alpha = 0.1 el umbral es 0.38, asi que solo entran los clusters 1 y 2
+        for _ in 0..100{
+            let cluster = select_cluster_from_rcl(&costs, 0.1, &mut rng);
+            assert!(cluster == 1 || cluster == 2);
+        }
+
+        // Con alpha = 1 puede salir cualquier cluster
+        let selected: Vec<u32> = (0..100).map(|_| select_cluster_from_rcl(&costs, 1.0, &mut rng)).collect();
+        for cluster in 0..4{
+            assert!(selected.contains(&cluster));
+        }
+    }
 }
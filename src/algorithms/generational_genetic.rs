@@ -1,143 +1,216 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::problem_datatypes::population::Population;
-use crate::fitness_evaluation_result::FitnessEvaluationResult;
-use crate::utils;
-use crate::arg_parser::SearchType;
+use crate::diversity_evolution::DiversityMonitor;
+use crate::problem_datatypes::population::{Population, CrossoverOperator, SelectionOperator};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
 
 use rand::rngs::StdRng;
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Ejecuta y muestra los resultados de la busqueda genetica con modelo generacional
+/// Algoritmo genetico con modelo generacional y elitismo
 /// cross_uniform == true ==> usamos cruce uniforme
 /// cross_uniform == false ==> usamos cruce de segmento fijo
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, cross_uniform: bool, rng: &mut StdRng){
-    // Parametros del algoritmo
-    let max_fitness_evaluations = 100000;
-    let population_size = 50;
-    let crossover_probability = 0.7;
+/// El parametro crossover permite usar cualquier otro operador de cruce
+pub struct GenerationalGenetic{
+    cross_uniform: bool,
+}
 
-    // El tamaño de un gen sera el tamaño de la poblacion de datos a asignar a clusters
-    let gen_size = data_points.len();
-    let mutation_probability_per_gen = 0.1 / gen_size as f64;
-    let individuals_to_mutate = (mutation_probability_per_gen * gen_size as f64 * population_size as f64) as i32;
+impl GenerationalGenetic{
+    pub fn new(cross_uniform: bool) -> Self{
+        return Self{cross_uniform};
+    }
+}
 
-    // Comprobacion de seguridad. No se tiene en cuenta cuando usamos --release
-    debug_assert!(individuals_to_mutate == 5, "El numero de individuos deberia ser 5, pero tenemos {} individuos a mutar", individuals_to_mutate);
+impl Metaheuristic for GenerationalGenetic{
+    fn name(&self) -> &'static str{
+        if self.cross_uniform == true{
+            return "gguniform";
+        }
+        return "ggsegment";
+    }
 
-    let before = Instant::now();
-    let (solucion, fitness_evolution) = run(&data_points, &constraints, program_arguments.get_number_of_clusters(), max_fitness_evaluations, rng, population_size, crossover_probability, individuals_to_mutate, cross_uniform);
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Para la salida de resultados formateada
-    let search_type;
-    if cross_uniform == true{
-        search_type = SearchType::GenerationalGeneticUniform;
-    }else{
-        search_type = SearchType::GenerationalGeneticSegment;
+    fn description(&self) -> &'static str{
+        if self.cross_uniform == true{
+            return "Busqueda genetica, modelo generacional, cruce uniforme";
+        }
+        return "Busqueda genetica, modelo generacional, cruce de segmento fijo";
     }
 
-    // Mostramos los resultados
-    println!("==> Busqueda genetica, modelo generacional, cross_uniform: {}", cross_uniform);
-    println!("\t--> La distancia global instracluster de la solucion es: {}", solucion.global_cluster_mean_distance());
-    println!("\t--> El numero de restricciones violadas es: {}", solucion.infeasibility());
-    println!("\t--> El valor de fitness es: {}", solucion.fitness());
-    println!("\t--> El valor de lambda es: {}", solucion.get_lambda());
-    println!("\t--> Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("\t--> Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(search_type)));
-    println!("");
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion", Some("50")),
+            ParameterSpec::new("crossover_probability", "Probabilidad de cruce de cada pareja", Some("0.7")),
+            ParameterSpec::new("individuals_to_mutate", "Individuos mutados por generacion (por defecto, 0.1 * population_size)", None),
+            ParameterSpec::new("crossover", "Operador de cruce: uniform, segment, aligned_uniform, aligned_segment o group (por defecto, el del nombre del algoritmo)", None),
+            ParameterSpec::new("selection", "Operador de seleccion: binary_tournament, tournament, linear_rank, roulette o sus", Some("binary_tournament")),
+            ParameterSpec::new("tournament_size", "Candidatos de cada torneo en la seleccion tournament", Some("3")),
+            ParameterSpec::new("selection_pressure", "Presion selectiva, entre 1 y 2, de la seleccion linear_rank", Some("1.5")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion", Some("1")),
+            ParameterSpec::new("diversity_file", "Fichero csv en el que guardamos la diversidad de la poblacion en cada generacion (por defecto, no se guarda)", None),
+            ParameterSpec::new("restart_entropy", "Reiniciamos la poblacion cuando la entropia media de sus genes, entre 0 y 1, baja de este valor (por defecto, no se reinicia)", None),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let population_size = params.get::<i32>("population_size")?;
+        let crossover_probability = params.get::<f64>("crossover_probability")?;
+        let number_of_workers = params.get::<usize>("number_of_workers")?;
+        let default_crossover = if self.cross_uniform == true {CrossoverOperator::Uniform} else {CrossoverOperator::Segment};
+        let crossover = params.get_optional::<CrossoverOperator>("crossover")?.unwrap_or(default_crossover);
+        let selection = SelectionOperator::from_name(
+            &params.get::<String>("selection")?,
+            params.get::<usize>("tournament_size")?,
+            params.get::<f64>("selection_pressure")?
+        )?;
+
+        // Por defecto, mutamos con probabilidad 0.1 / gen_size cada gen de la poblacion. El tamaño
+        // de un gen sera el tamaño de la poblacion de datos a asignar a clusters
+        let gen_size = instance.number_of_points();
+        let mutation_probability_per_gen = 0.1 / gen_size as f64;
+        let individuals_to_mutate = params.get_optional::<i32>("individuals_to_mutate")?
+            .unwrap_or((mutation_probability_per_gen * gen_size as f64 * population_size as f64) as i32);
+
+        // Seguimiento de la diversidad de la poblacion
+        let diversity_file = params.get_optional::<String>("diversity_file")?;
+        let mut diversity_monitor = DiversityMonitor::new(diversity_file.is_some(), params.get_optional::<f64>("restart_entropy")?);
+
+        let result = run(instance, max_fitness_evaluations, rng, number_of_workers, population_size, crossover_probability, individuals_to_mutate, crossover, selection, &mut diversity_monitor);
+
+        if let Some(file_path) = diversity_file{
+            diversity_monitor.get_evolution().save_as_csv(&file_path)?;
+        }
+        return Ok(result);
+    }
+}
+
+/// Ejecuta la busqueda genetica consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness, y devuelve el resultado de la busqueda
+/// El seguimiento de la diversidad guarda las medidas de diversidad de cada generacion y, si asi
+/// se indica, reinicia la poblacion cuando se pierde la diversidad (ver DiversityMonitor)
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    population_size: i32,
+    crossover_probability: f64,
+    individuals_to_mutate: i32,
+    crossover: CrossoverOperator,
+    selection: SelectionOperator,
+    diversity_monitor: &mut DiversityMonitor
+    ) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, rng, number_of_workers, population_size, crossover_probability, individuals_to_mutate, crossover, selection, diversity_monitor);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
 /// Ejecuta la busqueda genetica y devuelve la solucion encontrada
-fn run<'a, 'b>(
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
-    max_fitness_evaluations: i32,
+#[allow(clippy::too_many_arguments)]
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
     rng: &mut StdRng,
+    number_of_workers: usize,
     population_size: i32,
     crossover_probability: f64,
     individuals_to_mutate: i32,
-    cross_uniform: bool // Si es false, significa que usamos cruce de segmento fijo
-                        // Si es true, significa que usamos cruce uniforme
+    crossover: CrossoverOperator,
+    selection: SelectionOperator,
+    diversity_monitor: &mut DiversityMonitor
     )
-    -> (Solution<'a, 'b>, FitnessEvolution){
+    -> (Solution, FitnessEvolution){
 
     // Llevamos la cuenta del fitness del mejor individuo en cada iteracion sobre la poblacion
     let mut fitness_evolution = FitnessEvolution::new();
 
     // Poblacion inicial aleatoria
-    let mut current_population = Population::new_random_population(data_points, constraints, number_of_clusters, population_size, rng);
-
-    // Realizamos las iteraciones pertinentes
-    let mut consumed_fitness_evaluations = 0;
-    while consumed_fitness_evaluations < max_fitness_evaluations{
-
-        let mut iteration_fitness_evaluations = 0;
-
-        // Generamos una nueva poblacion a partir de torneos binarios
-        // Como tamaño, tomamos toda la poblacion, porque esto es lo correspondiente al modelo
-        // estacionario
-        let selection_population_result = current_population.select_population_binary_tournament(population_size, rng);
-        let selection_population = selection_population_result.get_result();
-        iteration_fitness_evaluations += selection_population_result.get_iterations_consumed();
-        debug_assert!(selection_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
-
-        // A partir de la poblacion seleccionada, generamos una nueva poblacion a partir de los
-        // cruces de los elementos de esa poblacion. El cruce depende del tipo de cruce que estemos
-        // estableciendo
-        let crossed_population_result;
-        if cross_uniform == true{
-            crossed_population_result = selection_population.cross_population_uniform(crossover_probability, rng);
-        }else{
-            crossed_population_result = selection_population.cross_population_segment(crossover_probability, rng);
-        }
+    let mut current_population = Population::new_random_population(instance, population_size, rng);
 
-        let crossed_population = crossed_population_result.get_result();
-        iteration_fitness_evaluations += crossed_population_result.get_iterations_consumed();
-        debug_assert!(crossed_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
-
-        // A partir de la poblacion cruzada, mutamos para generar una ultima poblacion
-        // Esta operacion no consume iteraciones, por lo que no hacemos la suma
-        let mutated_population = crossed_population.mutate_population(individuals_to_mutate, rng);
-        debug_assert!(mutated_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
-
-        // En la poblacion nueva podemos estar perdiendo el mejor individuo de la poblacion
-        // original. Tenemos que comprobar que dicho individuo sobreviva, y en caso de que no lo
-        // haga, introducirlo en la nueva poblacion, en su poblacion original.
-        // Esta operacion solo hace comprobaciones sobre el vector de posiciones (para comprobar
-        // que ya tengamos la solucion en la poblacion) y por ello no consume iteraciones. De todas
-        // formas, dejamos la comprobacion por seguridad (tenemos que elegir al mejor individuo de
-        // la poblacion original. Esta poblacion deberia estar evaluada, pero por si acaso)
-        let final_population_result = mutated_population.preserve_best_past_parent(&current_population);
-        let final_population = final_population_result.get_result();
-        iteration_fitness_evaluations += final_population_result.get_iterations_consumed();
-
-        // Evaluamnos esta poblacion final. Esta operacion consume bastantes evaluaciones, porque
-        // llegamos aqui con una poblacion altamente modificada, cuyos fitness no se han evaluado.
-        // Otra gran parte de la poblacion, la que llega sin modificarse, no contribuye a estas
-        // evaluaciones
-        let evaluate_poblation_result = final_population.evaluate_all_individuals();
-        iteration_fitness_evaluations += evaluate_poblation_result.get_iterations_consumed();
-
-        // Realizamos el cambio de poblacion
-        current_population = final_population.clone();
-        debug_assert!(current_population.population_size() == population_size as usize, "La poblacion final tras la iteracion tiene {} elementos", current_population.population_size());
-
-        // Añadimos las evaluaciones de fitness consumidas en esta pasada
-        consumed_fitness_evaluations += iteration_fitness_evaluations as i32;
+    // Realizamos las iteraciones pertinentes mientras queden evaluaciones del fitness
+    // Si se agotan a mitad de una generacion, los individuos que no se han podido evaluar no se
+    // tienen en cuenta al escoger al mejor individuo
+    while evaluator.budget_left() == true{
+        current_population = next_generation(&current_population, evaluator, rng, number_of_workers, crossover_probability, individuals_to_mutate, crossover, selection);
 
         // Llevamos la cuenta del valor del fitness de la mejor solucion de la poblacion en esta
         // iteracion
-        let best_individual = current_population.get_best_individual().get_result().0;
+        let (best_individual, _) = current_population.get_best_individual(evaluator);
         let best_individual_fitness = best_individual.fitness();
         fitness_evolution.add_iteration(best_individual_fitness);
+
+        // Si la poblacion ha perdido la diversidad, la reiniciamos manteniendo al mejor individuo
+        if diversity_monitor.after_generation(&current_population, best_individual_fitness) == true{
+            current_population = current_population.random_restart(evaluator, rng);
+            current_population.evaluate_all_individuals(number_of_workers, evaluator);
+        }
     }
 
-    return (current_population.get_best_individual().get_result().0.clone(), fitness_evolution);
+    return (current_population.best_solution(evaluator, rng), fitness_evolution);
+}
+
+/// Genera la siguiente generacion del genetico generacional a partir de la poblacion actual:
+/// seleccion con el operador dado, cruce, mutacion y elitismo. La poblacion devuelta queda evaluada,
+/// salvo que se agoten las evaluaciones del fitness
+///
+/// La usan tanto el genetico generacional como el modelo de islas
+#[allow(clippy::too_many_arguments)]
+pub fn next_generation(
+    current_population: &Population,
+    evaluator: &Evaluator,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    crossover_probability: f64,
+    individuals_to_mutate: i32,
+    crossover: CrossoverOperator,
+    selection: SelectionOperator
+    ) -> Population{
+    let population_size = current_population.population_size();
+
+    // Generamos una nueva poblacion con el operador de seleccion
+    // Como tamaño, tomamos toda la poblacion, porque esto es lo correspondiente al modelo
+    // generacional
+    let selection_population = current_population.select_population(selection, population_size as i32, number_of_workers, evaluator, rng);
+    debug_assert!(selection_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
+
+    // A partir de la poblacion seleccionada, generamos una nueva poblacion a partir de los
+    // cruces de los elementos de esa poblacion. El cruce depende del tipo de cruce que estemos
+    // estableciendo
+    let crossed_population = selection_population.cross_population(crossover, crossover_probability, rng);
+    debug_assert!(crossed_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
+
+    // A partir de la poblacion cruzada, mutamos para generar una ultima poblacion
+    // Esta operacion no consume iteraciones, por lo que no hacemos la suma
+    let mutated_population = crossed_population.mutate_population(individuals_to_mutate, rng);
+    debug_assert!(mutated_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
+
+    // En la poblacion nueva podemos estar perdiendo el mejor individuo de la poblacion
+    // original. Tenemos que comprobar que dicho individuo sobreviva, y en caso de que no lo
+    // haga, introducirlo en la nueva poblacion, en su poblacion original.
+    // Esta operacion solo hace comprobaciones sobre el vector de posiciones (para comprobar
+    // que ya tengamos la solucion en la poblacion) y por ello no consume iteraciones. De todas
+    // formas, dejamos la comprobacion por seguridad (tenemos que elegir al mejor individuo de
+    // la poblacion original. Esta poblacion deberia estar evaluada, pero por si acaso)
+    let final_population = mutated_population.preserve_best_past_parent(current_population, evaluator);
+
+    // Evaluamnos esta poblacion final. Esta operacion consume bastantes evaluaciones, porque
+    // llegamos aqui con una poblacion altamente modificada, cuyos fitness no se han evaluado.
+    // Otra gran parte de la poblacion, la que llega sin modificarse, no contribuye a estas
+    // evaluaciones
+    final_population.evaluate_all_individuals(number_of_workers, evaluator);
+
+    debug_assert!(final_population.population_size() == population_size, "La poblacion final tras la iteracion tiene {} elementos", final_population.population_size());
+    return final_population;
 }
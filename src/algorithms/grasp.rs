@@ -0,0 +1,90 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::{copkmeans, local_search};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::ImproveOnly;
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// GRASP: en cada iteracion construimos una solucion con el greedy de copkmeans aleatorizado y la
+/// mejoramos con busqueda local
+pub struct Grasp;
+
+impl Metaheuristic for Grasp{
+    fn name(&self) -> &'static str{
+        return "grasp";
+    }
+
+    fn description(&self) -> &'static str{
+        return "GRASP con greedy copkmeans aleatorizado y busqueda local";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("alpha", "Tamaño de la lista restringida de candidatos (0 es el greedy, 1 es aleatorio)", Some("0.3")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let alpha = params.get::<f64>("alpha")?;
+
+        return Ok(run(instance, max_fitness_evaluations, alpha, rng));
+    }
+}
+
+/// Ejecuta GRASP consumiendo como mucho max_fitness_evaluations evaluaciones del fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, alpha: f64, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, alpha, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta GRASP descontando las evaluaciones del fitness del evaluador dado
+/// Iteramos construccion y busqueda local hasta agotar las evaluaciones, quedandonos con la mejor
+/// solucion encontrada. La construccion no consume evaluaciones del fitness
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, alpha: f64, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+    let mut best: Option<(Solution, f64)> = None;
+
+    while evaluator.budget_left() == true{
+        // Fase de construccion
+        let greedy_solution = copkmeans::randomized_greedy_solution(instance, alpha, rng);
+
+        // Fase de mejora
+        let (local_solution, _) = local_search::run_from_init_sol(evaluator, &greedy_solution, &mut ImproveOnly, rng);
+        let local_fitness = match evaluator.fitness(&local_solution){
+            Some(fitness) => fitness,
+            None => break,
+        };
+
+        let improves = match &best{
+            Some((_, best_fitness)) => local_fitness < *best_fitness,
+            None => true,
+        };
+        if improves == true{
+            best = Some((local_solution, local_fitness));
+        }
+
+        // Guardamos el fitness de la mejor solucion tras cada iteracion
+        fitness_evolution.add_iteration(best.as_ref().unwrap().1);
+    }
+
+    match best{
+        Some((solution, _)) => return (solution, fitness_evolution),
+
+        // No ha habido evaluaciones ni para evaluar una solucion. Devolvemos la construccion greedy
+        None => return (copkmeans::randomized_greedy_solution(instance, alpha, rng), fitness_evolution),
+    }
+}
@@ -0,0 +1,97 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::algorithms::random_neighbour_search::{self, AcceptanceRule};
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Gran diluvio (record_to_record == false) o viaje de record a record (record_to_record == true),
+/// partiendo de una solucion aleatoria
+pub struct GreatDeluge{
+    record_to_record: bool,
+}
+
+impl GreatDeluge{
+    pub fn new(record_to_record: bool) -> Self{
+        return Self{record_to_record};
+    }
+}
+
+impl Metaheuristic for GreatDeluge{
+    fn name(&self) -> &'static str{
+        if self.record_to_record == true{
+            return "record_to_record";
+        }
+        return "great_deluge";
+    }
+
+    fn description(&self) -> &'static str{
+        if self.record_to_record == true{
+            return "Viaje de record a record";
+        }
+        return "Gran diluvio";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        if self.record_to_record == true{
+            return vec![
+                ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+                ParameterSpec::new("deviation", "Empeoramiento permitido respecto a la mejor solucion, como fraccion de su fitness", Some("0.01")),
+            ];
+        }
+
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("final_level", "Nivel del agua al agotar las evaluaciones, como fraccion del fitness de la solucion inicial", Some("0")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+
+        let level = if self.record_to_record == true{
+            params.get::<f64>("deviation")?
+        }else{
+            params.get::<f64>("final_level")?
+        };
+
+        return Ok(run(instance, max_fitness_evaluations, level, self.record_to_record, rng));
+    }
+}
+
+/// Ejecuta el gran diluvio o el viaje de record a record partiendo de una solucion aleatoria,
+/// consumiendo como mucho max_fitness_evaluations evaluaciones del fitness
+///
+/// Si record_to_record == false, level es el nivel final del agua, como fraccion del fitness de la
+/// solucion inicial. Si record_to_record == true, level es el empeoramiento permitido respecto a
+/// la mejor solucion, como fraccion de su fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, level: f64, record_to_record: bool, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, level, record_to_record, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta el gran diluvio o el viaje de record a record partiendo de una solucion aleatoria,
+/// descontando las evaluaciones del fitness del evaluador dado
+///
+/// Un vecino aleatorio se acepta si no empeora la solucion actual o si su fitness no supera el
+/// nivel del agua. level y record_to_record tienen el mismo significado que en run
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, level: f64, record_to_record: bool, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let acceptance = if record_to_record == true{
+        AcceptanceRule::RecordToRecord{deviation: level}
+    }else{
+        AcceptanceRule::Deluge{final_level: level}
+    };
+
+    return random_neighbour_search::run_with_evaluator(instance, evaluator, acceptance, rng);
+}
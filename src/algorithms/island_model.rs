@@ -0,0 +1,409 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::problem_datatypes::population::{Population, MemeticType, CrossoverOperator, SelectionOperator, ReplacementStrategy};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::{generational_genetic, steady_genetic, memetic};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::str::FromStr;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Modelo de islas: varias poblaciones evolucionan por separado, cada una con su propio genetico
+/// o memetico, e intercambian sus mejores individuos cada cierto numero de generaciones
+pub struct IslandModel;
+
+impl Metaheuristic for IslandModel{
+    fn name(&self) -> &'static str{
+        return "island_model";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Modelo de islas con migracion entre geneticos y memeticos";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness, compartidas por todas las islas", Some("100000")),
+            ParameterSpec::new("islands", "Algoritmo de cada isla, separados por comas: gguniform, ggsegment, gsuniform, gssegment, memeall, memerandom o memeelitist", Some("gguniform,gguniform,gguniform,gguniform")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion de cada isla", Some("25")),
+            ParameterSpec::new("crossover_probability", "Probabilidad de cruce de cada pareja en las islas generacionales", Some("0.7")),
+            ParameterSpec::new("individuals_to_mutate", "Individuos mutados por generacion en las islas generacionales (por defecto, 0.1 * population_size)", None),
+            ParameterSpec::new("mutation_probability_per_gen", "Probabilidad de mutar cada gen de los hijos en las islas estacionarias (por defecto, 0.1 / numero de puntos)", None),
+            ParameterSpec::new("max_fails", "Fallos permitidos en la busqueda local suave de las islas memeticas (por defecto, 0.1 * numero de puntos)", None),
+            ParameterSpec::new("migration_interval", "Generaciones entre dos migraciones", Some("10")),
+            ParameterSpec::new("migrants", "Mejores individuos que envia cada isla en cada migracion", Some("1")),
+            ParameterSpec::new("topology", "Islas a las que migran los individuos: ring, fully_connected o random", Some("ring")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion de cada isla", Some("1")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let islands = parse_islands(&params.get::<String>("islands")?)?;
+        let population_size = params.get::<i32>("population_size")?;
+
+        // Mismos valores por defecto que en los geneticos y memeticos
+        let gen_size = instance.number_of_points();
+        let island_params = IslandParameters{
+            population_size,
+            crossover_probability: params.get::<f64>("crossover_probability")?,
+            individuals_to_mutate: params.get_optional::<i32>("individuals_to_mutate")?.unwrap_or((0.1 * population_size as f64) as i32),
+            mutation_probability_per_gen: params.get_optional::<f64>("mutation_probability_per_gen")?.unwrap_or(0.1 / gen_size as f64),
+            max_fails: params.get_optional::<i32>("max_fails")?.unwrap_or((0.1 * gen_size as f64) as i32),
+            migration_interval: params.get::<u32>("migration_interval")?.max(1),
+            migrants: params.get::<usize>("migrants")?,
+            topology: params.get::<Topology>("topology")?,
+            number_of_workers: params.get::<usize>("number_of_workers")?,
+        };
+
+        return Ok(run(instance, max_fitness_evaluations, &islands, island_params, rng));
+    }
+}
+
+/// Algoritmo con el que evoluciona cada isla. Se nombran igual que los algoritmos del registro
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IslandType{
+    /// Genetico generacional con elitismo
+    Generational{crossover: CrossoverOperator},
+
+    /// Genetico estacionario
+    Steady{crossover: CrossoverOperator},
+
+    /// Memetico generacional con cruce uniforme
+    Memetic(MemeticType),
+}
+
+impl FromStr for IslandType{
+    type Err = SimpleError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        match name{
+            "gguniform" => return Ok(IslandType::Generational{crossover: CrossoverOperator::Uniform}),
+            "ggsegment" => return Ok(IslandType::Generational{crossover: CrossoverOperator::Segment}),
+            "gsuniform" => return Ok(IslandType::Steady{crossover: CrossoverOperator::Uniform}),
+            "gssegment" => return Ok(IslandType::Steady{crossover: CrossoverOperator::Segment}),
+            "memeall" => return Ok(IslandType::Memetic(MemeticType::All)),
+            "memerandom" => return Ok(IslandType::Memetic(MemeticType::Random)),
+            "memeelitist" => return Ok(IslandType::Memetic(MemeticType::Elitist)),
+            _ => return Err(SimpleError::new(format!("el tipo de isla {} no existe", name))),
+        }
+    }
+}
+
+/// Islas a las que envia sus mejores individuos cada isla en una migracion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology{
+    /// La isla i envia a la isla i + 1, y la ultima a la primera
+    Ring,
+
+    /// Cada isla envia a todas las demas
+    FullyConnected,
+
+    /// Cada isla envia a otra isla escogida aleatoriamente en cada migracion
+    Random,
+}
+
+impl FromStr for Topology{
+    type Err = SimpleError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        match name{
+            "ring" => return Ok(Topology::Ring),
+            "fully_connected" => return Ok(Topology::FullyConnected),
+            "random" => return Ok(Topology::Random),
+            _ => return Err(SimpleError::new(format!("la topologia {} no existe", name))),
+        }
+    }
+}
+
+/// Parametros del modelo de islas. Los de los algoritmos se comparten entre todas las islas, y
+/// cada isla usa los que le corresponden segun su tipo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IslandParameters{
+    /// Tamaño de la poblacion de cada isla
+    pub population_size: i32,
+
+    /// Probabilidad de cruce en las islas generacionales y memeticas
+    pub crossover_probability: f64,
+
+    /// Individuos mutados por generacion en las islas generacionales y memeticas
+    pub individuals_to_mutate: i32,
+
+    /// Probabilidad de mutar cada gen en las islas estacionarias
+    pub mutation_probability_per_gen: f64,
+
+    /// Fallos permitidos en la busqueda local suave de las islas memeticas
+    pub max_fails: i32,
+
+    /// Generaciones entre dos migraciones
+    pub migration_interval: u32,
+
+    /// Mejores individuos que envia cada isla en cada migracion
+    pub migrants: usize,
+
+    pub topology: Topology,
+
+    /// Hilos con los que se evalua la poblacion de cada isla
+    pub number_of_workers: usize,
+}
+
+/// Convierte la lista de islas separadas por comas, por ejemplo "gguniform,memeelitist"
+pub fn parse_islands(description: &str) -> Result<Vec<IslandType>, Box<dyn Error>>{
+    let mut islands = vec![];
+    for name in description.split(','){
+        islands.push(name.trim().parse::<IslandType>()?);
+    }
+
+    return Ok(islands);
+}
+
+/// Ejecuta el modelo de islas consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness entre todas las islas
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, islands: &[IslandType], island_params: IslandParameters, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, islands, island_params, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta el modelo de islas descontando las evaluaciones del fitness del evaluador dado, que
+/// comparten todas las islas
+///
+/// En cada generacion, cada isla avanza una generacion de su algoritmo, por turnos. Para que todas
+/// las islas generen el mismo numero de hijos, una generacion de una isla estacionaria son
+/// population_size / 2 iteraciones del estacionario. Cada migration_interval generaciones, cada
+/// isla envia copias de sus mejores individuos a las islas que indica la topologia, donde
+/// sustituyen a los peores individuos si son mejores que ellos. En la evolucion del fitness
+/// guardamos el fitness del mejor individuo de todas las islas en cada generacion
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    islands: &[IslandType],
+    island_params: IslandParameters,
+    rng: &mut StdRng
+) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let mut populations: Vec<Population> = islands.iter()
+        .map(|_| Population::new_random_population(instance, island_params.population_size, rng))
+        .collect();
+    for population in populations.iter(){
+        population.evaluate_all_individuals(island_params.number_of_workers, evaluator);
+    }
+
+    let mut current_generation = 0;
+    let mut generations_since_migration = 0;
+    while evaluator.budget_left() == true{
+        for (population, island_type) in populations.iter_mut().zip(islands.iter()){
+            *population = next_generation(population, *island_type, current_generation, island_params, evaluator, rng);
+        }
+        current_generation += 1;
+        generations_since_migration += 1;
+
+        if generations_since_migration >= island_params.migration_interval{
+            generations_since_migration = 0;
+            migrate(&mut populations, island_params.migrants, island_params.topology, evaluator, rng);
+        }
+
+        if let Some((_, best_fitness)) = best_individual(&populations){
+            fitness_evolution.add_iteration(best_fitness);
+        }
+    }
+
+    match best_individual(&populations){
+        Some((solution, _)) => return (solution.clone(), fitness_evolution),
+
+        // No hemos podido evaluar ni un individuo
+        None => {
+            let mut solution = Solution::generate_random_solution(instance, rng);
+            if solution.is_valid() == false{
+                solution.repair_solution(rng);
+            }
+            return (solution, fitness_evolution);
+        }
+    }
+}
+
+/// Avanza una generacion de la isla, con el algoritmo que indica su tipo
+fn next_generation(
+    population: &Population,
+    island_type: IslandType,
+    current_generation: i32,
+    island_params: IslandParameters,
+    evaluator: &Evaluator,
+    rng: &mut StdRng
+) -> Population{
+    match island_type{
+        IslandType::Generational{crossover} => {
+            return generational_genetic::next_generation(
+                population,
+                evaluator,
+                rng,
+                island_params.number_of_workers,
+                island_params.crossover_probability,
+                island_params.individuals_to_mutate,
+                crossover,
+                SelectionOperator::Tournament(2)
+            );
+        },
+
+        IslandType::Steady{crossover} => {
+            let mut new_population = population.clone();
+            for _ in 0..(island_params.population_size / 2).max(1){
+                if evaluator.budget_left() == false{
+                    break;
+                }
+                new_population = steady_genetic::next_generation(
+                    &new_population,
+                    evaluator,
+                    rng,
+                    island_params.number_of_workers,
+                    island_params.mutation_probability_per_gen,
+                    crossover,
+                    SelectionOperator::Tournament(2),
+                    ReplacementStrategy::Worst
+                );
+            }
+            return new_population;
+        },
+
+        IslandType::Memetic(memetic_type) => {
+            return memetic::next_generation(
+                population,
+                evaluator,
+                rng,
+                island_params.number_of_workers,
+                island_params.individuals_to_mutate,
+                island_params.crossover_probability,
+                island_params.max_fails,
+                memetic_type,
+                CrossoverOperator::Uniform,
+                current_generation
+            );
+        },
+    }
+}
+
+/// Cada isla envia copias de sus migrants mejores individuos evaluados a las islas que indica la
+/// topologia. Los emigrantes se escogen antes de recibir a ninguno, de forma que el resultado no
+/// depende del orden de las islas. Cada inmigrante sustituye al peor individuo de la isla de
+/// destino si es mejor que el
+fn migrate(populations: &mut [Population], migrants: usize, topology: Topology, evaluator: &Evaluator, rng: &mut StdRng){
+    let number_of_islands = populations.len();
+    if number_of_islands < 2{
+        return;
+    }
+
+    let emigrants: Vec<Vec<Solution>> = populations.iter().map(|population| best_individuals(population, migrants)).collect();
+
+    for (source, source_emigrants) in emigrants.iter().enumerate(){
+        let destinations: Vec<usize> = match topology{
+            Topology::Ring => vec![(source + 1) % number_of_islands],
+            Topology::FullyConnected => (0..number_of_islands).filter(|island| *island != source).collect(),
+            Topology::Random => vec![(source + rng.gen_range(1..number_of_islands)) % number_of_islands],
+        };
+
+        for destination in destinations{
+            for emigrant in source_emigrants.iter(){
+                let worst_index = populations[destination].get_index_worst_individual(evaluator);
+                let replaces = match evaluator.fitness(populations[destination].get_individual(worst_index)){
+                    Some(worst_fitness) => emigrant.fitness() < worst_fitness,
+                    None => true,
+                };
+
+                if replaces == true{
+                    populations[destination].set_individual(worst_index, emigrant.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Copias de los number_of_individuals mejores individuos evaluados de la poblacion
+fn best_individuals(population: &Population, number_of_individuals: usize) -> Vec<Solution>{
+    let mut evaluated: Vec<&Solution> = (0..population.population_size())
+        .map(|index| population.get_individual(index))
+        .filter(|individual| individual.is_fitness_cached() == true)
+        .collect();
+    evaluated.sort_by(|first, second| first.fitness().partial_cmp(&second.fitness()).unwrap());
+
+    return evaluated.into_iter().take(number_of_individuals).cloned().collect();
+}
+
+/// Mejor individuo evaluado de todas las islas, junto a su fitness
+fn best_individual(populations: &[Population]) -> Option<(&Solution, f64)>{
+    let mut best: Option<(&Solution, f64)> = None;
+
+    for population in populations.iter(){
+        for index in 0..population.population_size(){
+            let individual = population.get_individual(index);
+            if individual.is_fitness_cached() == false{
+                continue;
+            }
+
+            let improves = match best{
+                Some((_, best_fitness)) => individual.fitness() < best_fitness,
+                None => true,
+            };
+            if improves == true{
+                best = Some((individual, individual.fitness()));
+            }
+        }
+    }
+
+    return best;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::island_model::{parse_islands, migrate, best_individuals, IslandType, Topology};
+    use crate::problem_datatypes::population::{Population, MemeticType, CrossoverOperator};
+    use crate::problem_datatypes::ProblemInstance;
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_parse_islands(){
+        let islands = parse_islands("gguniform, gssegment,memeelitist").unwrap();
+        assert_eq!(islands, vec![
+            IslandType::Generational{crossover: CrossoverOperator::Uniform},
+            IslandType::Steady{crossover: CrossoverOperator::Segment},
+            IslandType::Memetic(MemeticType::Elitist),
+        ]);
+        assert!(parse_islands("gguniform,no_existe").is_err());
+    }
+
+    #[test]
+    // Tras migrar en anillo, el mejor individuo de cada isla esta tambien en la siguiente isla
+    fn test_ring_migration(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let evaluator = Evaluator::new(100000);
+
+        let mut populations: Vec<Population> = (0..3).map(|_| Population::new_random_population(&instance, 10, &mut rng)).collect();
+        for population in populations.iter(){
+            population.evaluate_all_individuals(1, &evaluator);
+        }
+        let best_before: Vec<Vec<u32>> = populations.iter().map(|population| best_individuals(population, 1)[0].get_cluster_indexes()).collect();
+
+        migrate(&mut populations, 1, Topology::Ring, &evaluator, &mut rng);
+
+        for (source, best) in best_before.iter().enumerate(){
+            let destination = &populations[(source + 1) % 3];
+            let contains_best = (0..destination.population_size()).any(|index| destination.get_individual(index).get_cluster_indexes() == *best);
+            assert!(contains_best == true);
+        }
+    }
+}
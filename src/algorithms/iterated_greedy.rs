@@ -0,0 +1,244 @@
+use crate::problem_datatypes::{Solution, ProblemInstance, Point};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::copkmeans;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::{AcceptanceCriterion, Decision, parse_acceptance_criterion};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::str::FromStr;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Greedy iterado (busqueda en vecindarios grandes): en cada iteracion quitamos un subconjunto de
+/// puntos de la solucion actual y los reinsertamos con el criterio greedy de copkmeans
+pub struct IteratedGreedy;
+
+impl Metaheuristic for IteratedGreedy{
+    fn name(&self) -> &'static str{
+        return "iterated_greedy";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Greedy iterado con destruccion y reconstruccion";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("destroy_size", "Puntos que se quitan en cada iteracion (por defecto, 0.1 * numero de puntos)", None),
+            ParameterSpec::new("destroy", "Puntos que se quitan: random, worst (mas lejanos a su centroide) o violating (que violan restricciones)", Some("random")),
+            ParameterSpec::new("acceptance", "Criterio de aceptacion de las soluciones reconstruidas", Some("improve_or_equal")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let destroy_size = params.get_optional::<usize>("destroy_size")?.unwrap_or((0.1 * instance.number_of_points() as f64) as usize);
+        let destroy = params.get::<DestroyStrategy>("destroy")?;
+        let mut acceptance = parse_acceptance_criterion(&params.get::<String>("acceptance")?)?;
+
+        return Ok(run(instance, max_fitness_evaluations, destroy_size, destroy, acceptance.as_mut(), rng));
+    }
+}
+
+/// Forma de elegir los puntos que quitamos de la solucion en la fase de destruccion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DestroyStrategy{
+    /// Puntos aleatorios
+    Random,
+
+    /// Puntos de entre los mas alejados del centroide de su cluster, que son los que mas aportan
+    /// a la distancia intracluster
+    Worst,
+
+    /// Puntos que participan en alguna restriccion violada, en orden aleatorio. Si no hay
+    /// suficientes, completamos con puntos aleatorios
+    Violating,
+}
+
+impl FromStr for DestroyStrategy{
+    type Err = SimpleError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        match name{
+            "random" => return Ok(DestroyStrategy::Random),
+            "worst" => return Ok(DestroyStrategy::Worst),
+            "violating" => return Ok(DestroyStrategy::Violating),
+            _ => return Err(SimpleError::new(format!("la estrategia de destruccion {} no existe", name))),
+        }
+    }
+}
+
+/// Ejecuta el greedy iterado consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    destroy_size: usize,
+    destroy: DestroyStrategy,
+    acceptance: &mut dyn AcceptanceCriterion,
+    rng: &mut StdRng
+) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, destroy_size, destroy, acceptance, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta el greedy iterado descontando las evaluaciones del fitness del evaluador dado
+///
+/// Partimos de la solucion greedy de copkmeans. En cada iteracion destruimos y reconstruimos la
+/// solucion actual, y el criterio de aceptacion decide si la solucion reconstruida pasa a ser la
+/// actual. Cada solucion reconstruida consume una evaluacion del fitness
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    destroy_size: usize,
+    destroy: DestroyStrategy,
+    acceptance: &mut dyn AcceptanceCriterion,
+    rng: &mut StdRng
+) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let mut current_solution = copkmeans::randomized_greedy_solution(instance, 0.0, rng);
+    let mut current_fitness = match evaluator.fitness(&current_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (current_solution, fitness_evolution),
+    };
+    fitness_evolution.add_iteration(current_fitness);
+
+    let mut best_solution = current_solution.clone();
+    let mut best_fitness = current_fitness;
+
+    while evaluator.budget_left() == true{
+        // Destruccion y reconstruccion
+        let removed_points = select_points_to_remove(&current_solution, destroy_size, destroy, rng);
+        let new_solution = copkmeans::reinsert_points(&current_solution, &removed_points, rng);
+
+        let new_fitness = match evaluator.fitness(&new_solution){
+            Some(fitness) => fitness,
+            None => break,
+        };
+
+        if new_fitness < best_fitness{
+            best_solution = new_solution.clone();
+            best_fitness = new_fitness;
+        }
+
+        match acceptance.decide(current_fitness, new_fitness, rng){
+            Decision::Accept => {
+                current_solution = new_solution;
+                current_fitness = new_fitness;
+            },
+            Decision::Reject => (),
+            Decision::Restart => {
+                let restart_solution = copkmeans::randomized_greedy_solution(instance, 0.0, rng);
+                current_fitness = match evaluator.fitness(&restart_solution){
+                    Some(fitness) => fitness,
+                    None => break,
+                };
+                current_solution = restart_solution;
+            },
+        }
+
+        fitness_evolution.add_iteration(current_fitness);
+    }
+
+    return (best_solution, fitness_evolution);
+}
+
+/// Selecciona los indices de los destroy_size puntos que quitamos de la solucion, sin repetidos
+fn select_points_to_remove(solution: &Solution, destroy_size: usize, destroy: DestroyStrategy, rng: &mut StdRng) -> Vec<usize>{
+    let number_of_points = solution.get_data_points().len();
+    let destroy_size = destroy_size.min(number_of_points);
+
+    let mut point_indexes: Vec<usize> = (0..number_of_points).collect();
+    point_indexes.shuffle(rng);
+
+    match destroy{
+        DestroyStrategy::Random => (),
+
+        DestroyStrategy::Worst => {
+            // Distancia de cada punto al centroide de su cluster
+            let cluster_indexes = solution.get_cluster_indexes();
+            let centroids: Vec<Point> = (0..solution.get_number_of_clusters() as u32)
+                .map(|cluster| Point::calculate_centroid(&solution.get_points_in_cluster(cluster)))
+                .collect();
+            let points = solution.get_data_points().get_points();
+            let distances: Vec<f64> = (0..number_of_points)
+                .map(|index| Point::distance(&points[index], &centroids[cluster_indexes[index] as usize]))
+                .collect();
+
+            // Elegimos aleatoriamente entre los 2 * destroy_size puntos mas lejanos. Si siempre
+            // quitasemos los mismos puntos, la reconstruccion los devolveria a los mismos clusters
+            point_indexes.sort_by(|first, second| distances[*second].partial_cmp(&distances[*first]).unwrap());
+            point_indexes.truncate(2 * destroy_size);
+            point_indexes.shuffle(rng);
+        },
+
+        DestroyStrategy::Violating => {
+            // Primero los que violan alguna restriccion. Ordenacion estable, asi que cada grupo
+            // queda en orden aleatorio
+            point_indexes.sort_by_key(|index| solution.violated_constraints_of_point(*index) == 0);
+        },
+    }
+
+    point_indexes.truncate(destroy_size);
+    return point_indexes;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::iterated_greedy::{select_points_to_remove, DestroyStrategy};
+    use crate::algorithms::copkmeans;
+    use crate::problem_datatypes::{ProblemInstance, Solution};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    // La destruccion quita destroy_size puntos distintos, y la reconstruccion da una solucion
+    // valida que solo cambia los puntos quitados
+    fn test_destroy_and_repair(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let mut solution = Solution::generate_random_solution(&instance, &mut rng);
+        solution.repair_solution(&mut rng);
+
+        for destroy in [DestroyStrategy::Random, DestroyStrategy::Worst, DestroyStrategy::Violating]{
+            let removed_points = select_points_to_remove(&solution, 8, destroy, &mut rng);
+            let unique_points: HashSet<usize> = removed_points.iter().cloned().collect();
+            assert_eq!(removed_points.len(), 8);
+            assert_eq!(unique_points.len(), 8);
+
+            let new_solution = copkmeans::reinsert_points(&solution, &removed_points, &mut rng);
+            assert!(new_solution.is_valid() == true);
+
+            let old_clusters = solution.get_cluster_indexes();
+            let new_clusters = new_solution.get_cluster_indexes();
+            for index in 0..old_clusters.len(){
+                if unique_points.contains(&index) == false{
+                    assert_eq!(old_clusters[index], new_clusters[index]);
+                }
+            }
+        }
+
+        // Si la solucion viola restricciones, la destruccion violating quita primero esos puntos
+        let removed_points = select_points_to_remove(&solution, 1, DestroyStrategy::Violating, &mut rng);
+        if solution.infeasibility() > 0{
+            assert!(solution.violated_constraints_of_point(removed_points[0]) > 0);
+        }
+    }
+}
@@ -1,102 +1,192 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::utils;
-use crate::arg_parser::SearchType;
 use crate::algorithms::local_search;
 use crate::algorithms::simulated_annealing;
+use crate::algorithms::simulated_annealing::CoolingSchedule;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::{AcceptanceCriterion, Decision, ImproveOnly, Metropolis, parse_acceptance_criterion};
 
 use rand::rngs::StdRng;
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Ejecuta y muestra los resultados de la busqueda
-/// Esto para no incluir todo este codigo en el cuerpo de la funcion main
-/// basic indica si usamos busqueda local (true) o enfriamiento simulado (false) entre repeticiones
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, basic: bool, rng: &mut StdRng){
-    // Numero maximo de iteraciones para la busqueda local y numero de repeticiones
-    // Es menos que en otros algoritmos, porque estamos usando repeticiones
-    let max_fitness_evaluations = 10000;
-    let number_of_repetitions = 10;
+/// Busqueda local reiterada: en cada repeticion mutamos fuertemente la solucion actual y le
+/// aplicamos busqueda local (basic == true) o enfriamiento simulado (basic == false). El criterio
+/// de aceptacion decide si el nuevo optimo local pasa a ser la solucion actual
+pub struct IterativeLocalSearch{
+    basic: bool,
+}
+
+impl IterativeLocalSearch{
+    pub fn new(basic: bool) -> Self{
+        return Self{basic};
+    }
+}
+
+impl Metaheuristic for IterativeLocalSearch{
+    fn name(&self) -> &'static str{
+        if self.basic == true{
+            return "iterative_local_search";
+        }
+        return "iterative_local_search_annealing";
+    }
 
-    // Tamaño del segmento de mutacion fuerte que consideramos
-    let mutation_segment_size: usize = (0.1 * data_points.len() as f32) as usize;
+    fn description(&self) -> &'static str{
+        if self.basic == true{
+            return "Busqueda local iterativa con busqueda local";
+        }
+        return "Busqueda local iterativa con enfriamiento simulado";
+    }
 
-    // Comprobacion de seguridad
-    debug_assert!(
-        max_fitness_evaluations * number_of_repetitions == 100000,
-        "No estamos usando el numero adecuado de evaluaciones del fitness o lanzamientos de local search"
-    );
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        // El maximo de evaluaciones es el total, como en el resto de algoritmos, y se reparte a
+        // partes iguales entre las repeticiones
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness, repartidas entre las repeticiones", Some("100000")),
+            ParameterSpec::new("number_of_repetitions", "Numero de repeticiones", Some("10")),
+            ParameterSpec::new("mutation_segment_size", "Tamaño del segmento de la mutacion fuerte (por defecto, 0.1 * numero de puntos)", None),
+            ParameterSpec::new("acceptance", "Criterio de aceptacion de los nuevos optimos locales (por ejemplo improve, random_walk o restart:<fallos>)", Some("improve")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<i32>("max_fitness_evaluations")?;
+        let number_of_repetitions = params.get::<i32>("number_of_repetitions")?;
+        let mutation_segment_size = params.get_optional::<usize>("mutation_segment_size")?.unwrap_or((0.1 * instance.number_of_points() as f32) as usize);
+        let mut acceptance = parse_acceptance_criterion(&params.get::<String>("acceptance")?)?;
+
+        return Ok(run(instance, max_fitness_evaluations, number_of_repetitions, self.basic, mutation_segment_size, acceptance.as_mut(), rng));
+    }
+}
+
+/// Lanzamos la busqueda iterativa, con number_of_repetitions repeticiones que se reparten
+/// max_fitness_evaluations evaluaciones del fitness
+/// basic indica si usamos busqueda local (true) o enfriamiento simulado (false) entre repeticiones
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: i32, number_of_repetitions: i32, basic: bool, mutation_segment_size: usize, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations as u32);
 
     let before = Instant::now();
-    let (solucion_local, fitness_evolution) = run(&data_points, &constraints, program_arguments.get_number_of_clusters(), max_fitness_evaluations, number_of_repetitions, basic, mutation_segment_size, rng);
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Mostramos los resultados
-    println!("==> Busqueda local iterativa, basic: {}", basic);
-    println!("La distancia global instracluster de la solucion es: {}", solucion_local.global_cluster_mean_distance());
-    println!("El numero de restricciones violadas es: {}", solucion_local.infeasibility());
-    println!("El valor de fitness es: {}", solucion_local.fitness());
-    println!("El valor de lambda es: {}", solucion_local.get_lambda());
-    println!("Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(SearchType::LocalSearch)));
-    println!("");
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, number_of_repetitions, basic, mutation_segment_size, acceptance, rng);
+    let duration = before.elapsed();
 
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
 /// Lanzamos la busqueda iterativa
-fn run<'a, 'b>(data_points: &'a DataPoints, constraints: &'b Constraints, number_of_clusters: i32, max_fitness_evaluations: i32, number_of_repetitions: i32, basic: bool, mutation_segment_size: usize, rng: &mut StdRng) -> (Solution<'a, 'b>, FitnessEvolution){
+/// Cada repeticion dispone de la parte que le toca de las evaluaciones que le quedan al evaluador
+/// al empezar, sin superar nunca el maximo de evaluaciones del evaluador
+///
+/// El criterio de aceptacion compara el nuevo optimo local con la solucion actual. Con improve
+/// la solucion actual es siempre la mejor, con random_walk siempre nos movemos al nuevo optimo, y
+/// con restart:<fallos> volvemos a una solucion aleatoria tras ese numero de fallos consecutivos.
+/// En cualquier caso, devolvemos la mejor solucion encontrada
+#[allow(non_snake_case)]  // M es la notacion de las transparencias
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, number_of_repetitions: i32, basic: bool, mutation_segment_size: usize, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> (Solution, FitnessEvolution){
     // Llevamos la cuenta de la evolucion del fintess
     let mut fitness_evolution = FitnessEvolution::new();
 
+    // Evaluaciones de las que dispone cada repeticion
+    let repetition_evaluations = evaluator.remaining() / number_of_repetitions.max(1) as u32;
+
     // Generamos una solucion inicial aleatoria
-    // Current solution sera la mejor solucion hasta el momento
-    let mut current_solution = Solution::generate_random_solution(data_points, constraints, number_of_clusters, rng);
-    fitness_evolution.add_iteration(current_solution.fitness()); // Por ser solo una evaluacion no tenemos en
-                                                                 // cuenta esto en el maximo de evaluaciones
+    let mut current_solution = Solution::generate_random_solution(instance, rng);
+    let mut current_fitness = match evaluator.fitness(&current_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial. Devolvemos la solucion
+        // inicial reparada, sin evaluar
+        None => {
+            if current_solution.is_valid() == false{
+                current_solution.repair_solution(rng);
+            }
+            return (current_solution, fitness_evolution);
+        }
+    };
+    fitness_evolution.add_iteration(current_fitness);
+
+    // Mejor solucion encontrada, que no tiene por que ser la actual
+    let mut best_solution = current_solution.clone();
+    let mut best_fitness = current_fitness;
 
     // Realizamos las repeticiones dadas
     for _ in 0..number_of_repetitions{
 
-        // Mutamos fuertemente la mejor solucion encontrada hasta el momento
-        // Notar que esta mejor solucion no se modifica en el .hard_mutated
+        // Evaluaciones de las que dispone esta repeticion
+        let repetition_evaluator = evaluator.sub_evaluator(repetition_evaluations);
+
+        // Mutamos fuertemente la solucion actual
+        // Notar que la solucion actual no se modifica en el .hard_mutated
         let mut new_solution = current_solution.hard_mutated(mutation_segment_size, rng);
 
         // Aplicamos busqueda local o enfriamiento simulado a esta solucion mutada fuertemente
         if basic == true{
-            let (local_solution, _) = local_search::run_from_init_sol(max_fitness_evaluations, &new_solution, rng);
+            let (local_solution, _) = local_search::run_from_init_sol(&repetition_evaluator, &new_solution, &mut ImproveOnly, rng);
             new_solution = local_solution;
         }else{
             // Establecemos los parametros para aplicar enfriamiento simulado
             let mu = 0.3;
             let final_tmp = 0.001;
-            let max_neighbours: i32 = (10.0 * data_points.len() as f64) as i32;
+            let max_neighbours: i32 = (10.0 * instance.number_of_points() as f64) as i32;
             let max_successes: i32 = (0.1 * max_neighbours as f64) as i32;
-            let M: f64 = max_fitness_evaluations as f64 / max_neighbours as f64;
-            let initial_tmp: f64 = (mu * current_solution.fitness()) / (-mu.ln());
+            let M: f64 = repetition_evaluations as f64 / max_neighbours as f64;
+            let initial_tmp: f64 = (mu * current_fitness) / (-mu.ln());
 
             // Aplicamos enfriamiento simulado
-            let (annealing_solution, _) = simulated_annealing::run(
-                max_fitness_evaluations,
+            let (annealing_solution, _) = simulated_annealing::run_from_init_sol(
+                &repetition_evaluator,
                 &current_solution,
                 initial_tmp,
                 final_tmp,
                 M,
                 max_neighbours,
                 max_successes,
+                CoolingSchedule::CauchyModified,
+                &mut Metropolis::new(initial_tmp),
                 rng
             );
             new_solution = annealing_solution;
         }
 
-        // Comprobamos si esta solucion es mejor que la que ya teniamos
-        if new_solution.fitness() < current_solution.fitness(){
-            current_solution = new_solution;
+        // Sumamos las evaluaciones consumidas en esta repeticion
+        evaluator.absorb(&repetition_evaluator);
+
+        // Si no quedan evaluaciones ni para evaluar el nuevo optimo local, hemos terminado
+        let new_fitness = match evaluator.fitness(&new_solution){
+            Some(fitness) => fitness,
+            None => break,
+        };
+
+        if new_fitness < best_fitness{
+            best_solution = new_solution.clone();
+            best_fitness = new_fitness;
+        }
+
+        // Decidimos si el nuevo optimo local pasa a ser la solucion actual
+        match acceptance.decide(current_fitness, new_fitness, rng){
+            Decision::Accept => {
+                current_solution = new_solution;
+                current_fitness = new_fitness;
+            },
+            Decision::Reject => (),
+            Decision::Restart => {
+                let mut restart_solution = Solution::generate_random_solution(instance, rng);
+                if restart_solution.is_valid() == false{
+                    restart_solution.repair_solution(rng);
+                }
+
+                current_fitness = match evaluator.fitness(&restart_solution){
+                    Some(fitness) => fitness,
+                    None => break,
+                };
+                current_solution = restart_solution;
+            },
         }
     }
 
-    return (current_solution, fitness_evolution);
+    return (best_solution, fitness_evolution);
 }
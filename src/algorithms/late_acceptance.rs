@@ -0,0 +1,61 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::algorithms::random_neighbour_search::{self, AcceptanceRule};
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Late Acceptance Hill Climbing, partiendo de una solucion aleatoria
+pub struct LateAcceptance;
+
+impl Metaheuristic for LateAcceptance{
+    fn name(&self) -> &'static str{
+        return "late_acceptance";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Late Acceptance Hill Climbing";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("history_length", "Numero de iteraciones que recuerda el historial de fitness", Some("50")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let history_length = params.get::<usize>("history_length")?;
+
+        return Ok(run(instance, max_fitness_evaluations, history_length, rng));
+    }
+}
+
+/// Ejecuta Late Acceptance Hill Climbing partiendo de una solucion aleatoria, consumiendo como
+/// mucho max_fitness_evaluations evaluaciones del fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, history_length: usize, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, history_length, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta Late Acceptance Hill Climbing partiendo de una solucion aleatoria, descontando las
+/// evaluaciones del fitness del evaluador dado
+///
+/// Guardamos el fitness de la solucion actual en las ultimas history_length iteraciones. Un
+/// vecino aleatorio se acepta si no empeora la solucion actual o la solucion que teniamos hace
+/// history_length iteraciones. Con history_length = 1 es una busqueda local que acepta empates
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, history_length: usize, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    return random_neighbour_search::run_with_evaluator(instance, evaluator, AcceptanceRule::LateAcceptance{history_length}, rng);
+}
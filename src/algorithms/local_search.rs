@@ -1,94 +1,129 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::utils;
-use crate::arg_parser::SearchType;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::{AcceptanceCriterion, Decision, parse_acceptance_criterion};
 
 use rand::rngs::StdRng;
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Ejecuta y muestra los resultados de la busqueda
-/// Esto para no incluir todo este codigo en el cuerpo de la funcion main
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, rng: &mut StdRng){
-    // Numero maximo de iteraciones para la busqueda local
-    let max_fitness_evaluations = 100000;
+/// Busqueda local del primer mejor, partiendo de una solucion aleatoria
+/// Cambiando el criterio de aceptacion, nos movemos al primer vecino que acepte el criterio
+pub struct LocalSearch;
+
+impl Metaheuristic for LocalSearch{
+    fn name(&self) -> &'static str{
+        return "local_search";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Busqueda local";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("acceptance", "Criterio de aceptacion de los vecinos", Some("improve")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let mut acceptance = parse_acceptance_criterion(&params.get::<String>("acceptance")?)?;
+
+        return Ok(run(instance, max_fitness_evaluations, acceptance.as_mut(), rng));
+    }
+}
+
+/// Ejecuta la metaheuristica de busqueda local, consumiendo como mucho max_fitness_evaluations
+/// evaluaciones del fitness
+/// Parte de una solucion inicial aleatoria
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
 
     let before = Instant::now();
-    let (solucion_local, fitness_evolution) = run(&data_points, &constraints, program_arguments.get_number_of_clusters(), max_fitness_evaluations, rng);
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Mostramos los resultados
-    println!("==> Busqueda local");
-    println!("La distancia global instracluster de la solucion es: {}", solucion_local.global_cluster_mean_distance());
-    println!("El numero de restricciones violadas es: {}", solucion_local.infeasibility());
-    println!("El valor de fitness es: {}", solucion_local.fitness());
-    println!("El valor de lambda es: {}", solucion_local.get_lambda());
-    println!("Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(SearchType::LocalSearch)));
-    println!("");
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, acceptance, rng);
+    let duration = before.elapsed();
 
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
 /// Ejecuta la metaheuristica de busqueda local y devuelve la solucion encontrada
-/// Parte de una solucion inicial aleatoria
-pub fn run<'a, 'b>(data_points: &'a DataPoints, constraints: &'b Constraints, number_of_clusters: i32, max_fitness_evaluations: i32, rng: &mut StdRng) -> (Solution<'a, 'b>, FitnessEvolution){
+/// Parte de una solucion inicial aleatoria, y descuenta las evaluaciones del fitness del evaluador
+/// dado, por si la busqueda local forma parte de otro algoritmo
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> (Solution, FitnessEvolution){
     // Partimos de una solucion inicial aleatoria
-    let init_sol = Solution::generate_random_solution(data_points, constraints, number_of_clusters, rng);
+    let init_sol = Solution::generate_random_solution(instance, rng);
 
     // Usamos la funcion que corre la busqueda local cuando se le da la solucion inicial
-    return run_from_init_sol(max_fitness_evaluations, &init_sol, rng);
+    return run_from_init_sol(evaluator, &init_sol, acceptance, rng);
 }
 
 /// Lanza la busqueda local.
 /// Se necesita como argumento una solucion inicial.
 /// Usar run si se quiere solucion inicial aleatoria
-pub fn run_from_init_sol<'a, 'b>(max_fitness_evaluations: i32, init_sol: &Solution<'a, 'b>, rng: &mut StdRng) -> (Solution<'a, 'b>, FitnessEvolution){
+///
+/// Paramos cuando el criterio de aceptacion no acepta ningun vecino o cuando se agotan las
+/// evaluaciones, y devolvemos la mejor solucion encontrada. Si el criterio pide reiniciar,
+/// continuamos desde una solucion aleatoria
+pub fn run_from_init_sol(evaluator: &Evaluator, init_sol: &Solution, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> (Solution, FitnessEvolution){
     // Cuenta de como avanza la evolucion del fitness a traves de las iteraciones
     let mut fitness_evolution = FitnessEvolution::new();
 
     // Partimos de una solucion inicial dada por parametro
+    // Si no quedan evaluaciones ni para evaluarla, la devolvemos sin mas
     let mut current_solution = init_sol.clone();
-    fitness_evolution.add_iteration(current_solution.fitness());
+    let mut best_fitness = match evaluator.fitness(&current_solution){
+        Some(fitness) => fitness,
+        None => return (current_solution, fitness_evolution),
+    };
+    fitness_evolution.add_iteration(best_fitness);
+
+    // Con criterios que aceptan empeoramientos, la solucion actual puede no ser la mejor
+    let mut best_solution = current_solution.clone();
 
     // Realizamos las iteraciones pertinentes mientras no hayamos consumido todas las evaluaciones
     // sobre el fitness
-    let mut fitness_evaluations_consumed = 0;
-    while fitness_evaluations_consumed < max_fitness_evaluations{
-
-        // Las evaluaciones de fitness que se consumen en esta iteracion
-        let mut current_fitness_consumed = 0;
+    while evaluator.budget_left() == true{
 
-        // Tomamos el vecino y tenemos en cuenta las evaluaciones del fitness consumidas
-        let evaluations_left = max_fitness_evaluations - fitness_evaluations_consumed;
-        let find_new_solution_result = current_solution.get_neighbour(evaluations_left, rng);
-        let new_solution = find_new_solution_result.get_result();
-        current_fitness_consumed += find_new_solution_result.get_iterations_consumed();
+        // Tomamos el vecino. El evaluador lleva la cuenta de las evaluaciones del fitness
+        let new_solution = current_solution.get_neighbour(evaluator, acceptance, rng);
 
         let new_solution = match new_solution{
-            Some(sol) => sol,
+            Some((sol, Decision::Accept)) => sol,
+
+            // Reiniciamos desde una solucion aleatoria, que evaluamos en la siguiente iteracion
+            Some((_, _)) => {
+                current_solution = Solution::generate_random_solution(current_solution.get_instance(), rng);
+                if current_solution.is_valid() == false{
+                    current_solution.repair_solution(rng);
+                }
+                continue;
+            },
 
-            // No hemos encontrado ningun vecino mejor, asi que paramos de iterar
-            // Ademas mostramos la informacion de las iteraciones que nos hemos ahorrado
+            // No hemos encontrado ningun vecino que se acepte, asi que paramos de iterar
             None => {
                 break;
             },
         };
 
-        // Hacemos el cambio de solucion y guardamos la mejora del fitness
+        // Hacemos el cambio de solucion y guardamos la evolucion del fitness
         // Este valor del fitness ya ha sido calculado en la busqueda del vecinadario, y por tanto,
         // no consume evaluaciones del fitness
-        current_solution = new_solution.clone();
+        debug_assert!(new_solution.is_fitness_cached() == true, "El vecino generado debe tener el valor del fitness cacheado");
+        current_solution = new_solution;
         fitness_evolution.add_iteration(current_solution.fitness());
-        debug_assert!(current_solution.is_fitness_cached() == true, "El vecino generado debe tener el valor del fitness cacheado");
 
-        // Añadimos todas las evaluaciones que se hayan consumido en la busqueda
-        fitness_evaluations_consumed += current_fitness_consumed as i32;
+        if current_solution.fitness() < best_fitness{
+            best_fitness = current_solution.fitness();
+            best_solution = current_solution.clone();
+        }
     }
 
-    return (current_solution, fitness_evolution);
+    return (best_solution, fitness_evolution);
 }
-
@@ -1,168 +1,228 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::problem_datatypes::population::Population;
-use crate::fitness_evaluation_result::FitnessEvaluationResult;
-use crate::arg_parser::SearchType;
-use crate::utils;
+use crate::diversity_evolution::DiversityMonitor;
+use crate::problem_datatypes::population::{Population, MemeticType, CrossoverOperator, SelectionOperator, ReplacementStrategy};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::algorithms::steady_genetic;
 
 use rand::rngs::StdRng;
+use simple_error::SimpleError;
+use std::error::Error;
 use std::time::Instant;
-use std::process::exit;
-
-/// Ejecuta y muestra los resultados de la busqueda genetica con modelo estacionario
-/// cross_uniform == true ==> usamos cruce uniforme
-/// cross_uniform == false ==> usamos cruce de segmento fijo
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, memetic_type: SearchType, rng: &mut StdRng){
-    // Parametros del algoritmo
-    // Son los mismos parametros para los tres tipos de algoritmo memetico
-    let max_fitness_evaluations = 100000;
-    let population_size = 50;
-    let crossover_probability = 0.7;
-    let max_fails = (0.1 * data_points.len() as f64) as i32;
-
-    // El tamaño de un gen sera el tamaño de la poblacion de datos a asignar a clusters
-    let gen_size = data_points.len();
-    let mutation_probability_per_gen = 0.1 / gen_size as f64;
-
-    // Numero de genes que vamos a mutar
-    let individuals_to_mutate = (mutation_probability_per_gen * gen_size as f64 * population_size as f64) as i32;
+use std::sync::Arc;
+
+/// Algoritmo memetico: genetico, por defecto generacional y con cruce uniforme, que, cada diez
+/// generaciones, aplica una busqueda local suave a los individuos escogidos segun memetic_type
+/// El parametro model permite usar el modelo estacionario, con cualquier estrategia de reemplazo
+pub struct Memetic{
+    memetic_type: MemeticType,
+}
+
+impl Memetic{
+    pub fn new(memetic_type: MemeticType) -> Self{
+        return Self{memetic_type};
+    }
+}
+
+impl Metaheuristic for Memetic{
+    fn name(&self) -> &'static str{
+        match self.memetic_type{
+            MemeticType::All => return "memeall",
+            MemeticType::Random => return "memerandom",
+            MemeticType::Elitist => return "memeelitist",
+        }
+    }
+
+    fn description(&self) -> &'static str{
+        match self.memetic_type{
+            MemeticType::All => return "Busqueda memetica, busqueda local suave sobre toda la poblacion",
+            MemeticType::Random => return "Busqueda memetica, busqueda local suave sobre individuos aleatorios",
+            MemeticType::Elitist => return "Busqueda memetica, busqueda local suave sobre los mejores individuos",
+        }
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        // Son los mismos parametros para los tres tipos de algoritmo memetico
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion", Some("50")),
+            ParameterSpec::new("crossover_probability", "Probabilidad de cruce de cada pareja en el modelo generational (en el modelo steady se cruzan siempre)", Some("0.7")),
+            ParameterSpec::new("individuals_to_mutate", "Individuos mutados por generacion en el modelo generational (por defecto, 0.1 * population_size)", None),
+            ParameterSpec::new("max_fails", "Fallos permitidos en la busqueda local suave (por defecto, 0.1 * numero de puntos)", None),
+            ParameterSpec::new("crossover", "Operador de cruce: uniform, segment, aligned_uniform, aligned_segment o group", Some("uniform")),
+            ParameterSpec::new("model", "Modelo del genetico: generational o steady", Some("generational")),
+            ParameterSpec::new("mutation_probability_per_gen", "Probabilidad de mutar cada gen de los hijos en el modelo steady (por defecto, 0.1 / numero de puntos)", None),
+            ParameterSpec::new("replacement", "Estrategia de reemplazo del modelo steady: worst, random, oldest, crowding, restricted_tournament o worst_without_duplicates", Some("worst")),
+            ParameterSpec::new("replacement_window", "Individuos con los que se compara cada hijo en el reemplazo restricted_tournament", Some("5")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion y se lanzan las busquedas locales suaves", Some("1")),
+            ParameterSpec::new("diversity_file", "Fichero csv en el que guardamos la diversidad de la poblacion en cada generacion, o cada population_size / 2 iteraciones en el modelo steady (por defecto, no se guarda)", None),
+            ParameterSpec::new("restart_entropy", "Reiniciamos la poblacion cuando la entropia media de sus genes, entre 0 y 1, baja de este valor (por defecto, no se reinicia)", None),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let population_size = params.get::<i32>("population_size")?;
+        let crossover_probability = params.get::<f64>("crossover_probability")?;
+        let number_of_workers = params.get::<usize>("number_of_workers")?;
+        let crossover = params.get::<CrossoverOperator>("crossover")?;
+        let max_fails = params.get_optional::<i32>("max_fails")?.unwrap_or((0.1 * instance.number_of_points() as f64) as i32);
+
+        // Por defecto, mutamos con probabilidad 0.1 / gen_size cada gen de la poblacion. El tamaño
+        // de un gen sera el tamaño de la poblacion de datos a asignar a clusters
+        let gen_size = instance.number_of_points();
+        let mutation_probability_per_gen = 0.1 / gen_size as f64;
+        let model = match params.get::<String>("model")?.as_str(){
+            "generational" => {
+                let individuals_to_mutate = params.get_optional::<i32>("individuals_to_mutate")?
+                    .unwrap_or((mutation_probability_per_gen * gen_size as f64 * population_size as f64) as i32);
+                GeneticModel::Generational{individuals_to_mutate}
+            },
+            "steady" => {
+                let mutation_probability_per_gen = params.get_optional::<f64>("mutation_probability_per_gen")?.unwrap_or(mutation_probability_per_gen);
+                let replacement = ReplacementStrategy::from_name(&params.get::<String>("replacement")?, params.get::<usize>("replacement_window")?)?;
+                GeneticModel::Steady{mutation_probability_per_gen, replacement}
+            },
+            model => return Err(Box::new(SimpleError::new(format!("el modelo {} no existe", model)))),
+        };
+
+        // Seguimiento de la diversidad de la poblacion
+        let diversity_file = params.get_optional::<String>("diversity_file")?;
+        let mut diversity_monitor = DiversityMonitor::new(diversity_file.is_some(), params.get_optional::<f64>("restart_entropy")?);
+
+        let result = run(
+            instance,
+            max_fitness_evaluations,
+            rng,
+            number_of_workers,
+            population_size,
+            model,
+            crossover_probability,
+            max_fails,
+            self.memetic_type,
+            crossover,
+            &mut diversity_monitor
+        );
+
+        if let Some(file_path) = diversity_file{
+            diversity_monitor.get_evolution().save_as_csv(&file_path)?;
+        }
+        return Ok(result);
+    }
+}
+
+/// Modelo del algoritmo genetico sobre el que aplicamos la busqueda local suave
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeneticModel{
+    /// Genetico generacional, en el que mutamos individuals_to_mutate individuos por generacion
+    Generational{individuals_to_mutate: i32},
+
+    /// Genetico estacionario, en el que mutamos cada gen de los hijos con probabilidad
+    /// mutation_probability_per_gen, y los hijos entran en la poblacion segun replacement
+    Steady{mutation_probability_per_gen: f64, replacement: ReplacementStrategy},
+}
+
+/// Ejecuta la busqueda memetica consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness, y devuelve el resultado de la busqueda
+/// El seguimiento de la diversidad guarda las medidas de diversidad de cada generacion y, si asi
+/// se indica, reinicia la poblacion cuando se pierde la diversidad (ver DiversityMonitor)
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    population_size: i32,
+    model: GeneticModel,
+    crossover_probability: f64,
+    max_fails: i32,
+    memetic_type: MemeticType,
+    crossover: CrossoverOperator,
+    diversity_monitor: &mut DiversityMonitor
+    ) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
 
     let before = Instant::now();
-    let (solucion, fitness_evolution) = run_generational(
-        &data_points,
-        &constraints,
-        program_arguments.get_number_of_clusters(),
-        max_fitness_evaluations,
-        rng,
-        population_size,
-        individuals_to_mutate,
-        crossover_probability,
-        max_fails,
-        memetic_type
-    );
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Mostramos los resultados
-    println!("==> Busqueda memetica, tipo memetico: {:?}", memetic_type);
-    println!("\t--> La distancia global instracluster de la solucion es: {}", solucion.global_cluster_mean_distance());
-    println!("\t--> El numero de restricciones violadas es: {}", solucion.infeasibility());
-    println!("\t--> El valor de fitness es: {}", solucion.fitness());
-    println!("\t--> El valor de lambda es: {}", solucion.get_lambda());
-    println!("\t--> Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("\t--> Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(memetic_type)));
-    println!("");
+    let (solution, fitness_evolution) = match model{
+        GeneticModel::Generational{individuals_to_mutate} => run_generational(
+            instance,
+            &evaluator,
+            rng,
+            number_of_workers,
+            population_size,
+            individuals_to_mutate,
+            crossover_probability,
+            max_fails,
+            memetic_type,
+            crossover,
+            diversity_monitor
+        ),
+        GeneticModel::Steady{mutation_probability_per_gen, replacement} => run_steady(
+            instance,
+            &evaluator,
+            rng,
+            number_of_workers,
+            population_size,
+            mutation_probability_per_gen,
+            max_fails,
+            memetic_type,
+            crossover,
+            replacement,
+            diversity_monitor
+        ),
+    };
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
 /// Ejecuta la busqueda genetica y devuelve la solucion encontrada
-/// Usamos busqueda genetica generacional con cruce uniforme
+/// Usamos busqueda genetica generacional con el operador de cruce dado
 // Al final uso memetico con
-fn run_generational<'a, 'b>(
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
-    max_fitness_evaluations: i32,
+#[allow(clippy::too_many_arguments)]
+fn run_generational(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
     rng: &mut StdRng,
+    number_of_workers: usize,
     population_size: i32,
     individuals_to_mutate: i32,
     crossover_probability: f64,
     max_fails: i32,
-    memetic_type: SearchType
+    memetic_type: MemeticType,
+    crossover: CrossoverOperator,
+    diversity_monitor: &mut DiversityMonitor
     )
-    -> (Solution<'a, 'b>, FitnessEvolution){
-
-    // Comprobamos que el tipo de busqueda, dado por memetic_type, sea correcto. Esto porque el
-    // enumerado puede llevar valores que no correspondan a memetico
-    match memetic_type{
-        // Opciones validas
-        SearchType::MemeticAll => (),
-        SearchType::MemeticRandom => (),
-        SearchType::MemeticElitist => (),
-
-        // Hemos alcanzado una opcion que no es valida
-        _ => {
-            println!("[Err] Tipo de busqueda memetica no correcta");
-            println!("El tipo de busqueda memetica obtenida fue: {:?}", memetic_type);
-            exit(-1);
-        }
-    }
+    -> (Solution, FitnessEvolution){
 
     // Llevamos la cuenta del fitness del mejor individuo en cada iteracion sobre la poblacion
     let mut fitness_evolution = FitnessEvolution::new();
 
     // Poblacion inicial aleatoria
-    let mut current_population = Population::new_random_population(data_points, constraints, number_of_clusters, population_size, rng);
+    let mut current_population = Population::new_random_population(instance, population_size, rng);
 
     // Realizamos las iteraciones pertinentes
-    let mut consumed_fitness_evaluations = 0;
     let mut current_generation = 0;
 
-    while consumed_fitness_evaluations < max_fitness_evaluations{
-        let mut iteration_fitness_evaluations = 0;
-
-        // Generamos una nueva poblacion a partir de torneos binarios
-        // Como tamaño, tomamos toda la poblacion, porque esto es lo correspondiente al modelo
-        // estacionario
-        let selection_population_result = current_population.select_population_binary_tournament(population_size, rng);
-        let selection_population = selection_population_result.get_result();
-        iteration_fitness_evaluations += selection_population_result.get_iterations_consumed();
-        debug_assert!(selection_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
-
-        // A partir de la poblacion seleccionada, generamos una nueva poblacion a partir de los
-        // cruces de los elementos de esa poblacion.
-        let crossed_population_result = selection_population.cross_population_uniform(crossover_probability, rng);
-        let crossed_population = crossed_population_result.get_result();
-        iteration_fitness_evaluations += crossed_population_result.get_iterations_consumed();
-        debug_assert!(crossed_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
-
-        // A partir de la poblacion cruzada, mutamos para generar una ultima poblacion
-        // Esta operacion no consume iteraciones, por lo que no hacemos la suma
-        let mutated_population = crossed_population.mutate_population(individuals_to_mutate, rng);
-        debug_assert!(mutated_population.population_size() == population_size as usize, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
-
-        // En la poblacion nueva podemos estar perdiendo el mejor individuo de la poblacion
-        // original. Tenemos que comprobar que dicho individuo sobreviva, y en caso de que no lo
-        // haga, introducirlo en la nueva poblacion, en su poblacion original.
-        // Esta operacion solo hace comprobaciones sobre el vector de posiciones (para comprobar
-        // que ya tengamos la solucion en la poblacion) y por ello no consume iteraciones. De todas
-        // formas, dejamos la comprobacion por seguridad (tenemos que elegir al mejor individuo de
-        // la poblacion original. Esta poblacion deberia estar evaluada, pero por si acaso)
-        let final_population_result = mutated_population.preserve_best_past_parent(&current_population);
-        let final_population = final_population_result.get_result();
-        iteration_fitness_evaluations += final_population_result.get_iterations_consumed();
-
-        // Evaluamos esta poblacion final. Esta operacion consume bastantes evaluaciones, porque
-        // llegamos aqui con una poblacion altamente modificada, cuyos fitness no se han evaluado.
-        // Otra gran parte de la poblacion, la que llega sin modificarse, no contribuye a estas
-        // evaluaciones
-        let evaluate_poblation_result = final_population.evaluate_all_individuals();
-        iteration_fitness_evaluations += evaluate_poblation_result.get_iterations_consumed();
-
-        // Cada diez iteraciones, aplicamos la busqueda local suave segun el criterio que indica
-        // memetic_type. Llevamos las cuentas de las evaluaciones adicionales que consume esta
-        // busqueda local suave
-        let soft_local_search_pop;
-        if current_generation % 10 == 0{
-            let soft_local_search_pop_result = final_population.soft_local_search(memetic_type, max_fails, rng);
-            soft_local_search_pop = soft_local_search_pop_result.get_result().clone();
-            iteration_fitness_evaluations += soft_local_search_pop_result.get_iterations_consumed();
-        }else{
-            soft_local_search_pop = final_population.clone();
-        }
-
-        // Realizamos el cambio de poblacion
-        // Hacemos una comprobacion de seguridad sobne la poblacion, tras las competicion de los
-        // dos nuevos individuos
-        current_population = soft_local_search_pop.clone();
+    while evaluator.budget_left() == true{
+        current_population = next_generation(
+            &current_population,
+            evaluator,
+            rng,
+            number_of_workers,
+            individuals_to_mutate,
+            crossover_probability,
+            max_fails,
+            memetic_type,
+            crossover,
+            current_generation
+        );
         debug_assert!(current_population.population_size() == population_size as usize, "La poblacion final tras la iteracion tiene {} elementos", current_population.population_size());
 
-        // Añadimos las evaluaciones de fitness consumidas en esta pasada
-        consumed_fitness_evaluations += iteration_fitness_evaluations as i32;
-
         // Hemos creado una nueva generacion
         current_generation += 1;
 
@@ -171,153 +231,231 @@ fn run_generational<'a, 'b>(
         // pasadas estamos evaluando toda la poblacion. Ademas, hacemos una evaluacion sobre toda
         // la poblacion extra por seguridad (de nuevo, esa comprobacion de seguridad no consume
         // evaluaciones adicionales)
-        let best_individual = current_population.get_best_individual().get_result().0;
+        let (best_individual, _) = current_population.get_best_individual(evaluator);
         let best_individual_fitness = best_individual.fitness();
         fitness_evolution.add_iteration(best_individual_fitness);
+
+        // Si la poblacion ha perdido la diversidad, la reiniciamos manteniendo al mejor individuo
+        if diversity_monitor.after_generation(&current_population, best_individual_fitness) == true{
+            current_population = current_population.random_restart(evaluator, rng);
+            current_population.evaluate_all_individuals(number_of_workers, evaluator);
+        }
     }
 
-    return (current_population.get_best_individual().get_result().0.clone(), fitness_evolution);
+    return (current_population.best_solution(evaluator, rng), fitness_evolution);
 }
 
+/// Genera la siguiente generacion del memetico generacional con el operador de cruce dado a
+/// partir de la poblacion actual. Si current_generation es multiplo de diez, aplicamos ademas la busqueda local
+/// suave segun memetic_type. La poblacion devuelta queda evaluada, salvo que se agoten las
+/// evaluaciones del fitness
+///
+/// La usan tanto el memetico como el modelo de islas
+#[allow(clippy::too_many_arguments)]
+pub fn next_generation(
+    current_population: &Population,
+    evaluator: &Evaluator,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    individuals_to_mutate: i32,
+    crossover_probability: f64,
+    max_fails: i32,
+    memetic_type: MemeticType,
+    crossover: CrossoverOperator,
+    current_generation: i32
+    ) -> Population{
+    let population_size = current_population.population_size();
+
+    // Generamos una nueva poblacion a partir de torneos binarios
+    // Como tamaño, tomamos toda la poblacion, porque esto es lo correspondiente al modelo
+    // estacionario
+    let selection_population = current_population.select_population_binary_tournament(population_size as i32, number_of_workers, evaluator, rng);
+    debug_assert!(selection_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
+
+    // A partir de la poblacion seleccionada, generamos una nueva poblacion a partir de los
+    // cruces de los elementos de esa poblacion.
+    let crossed_population = selection_population.cross_population(crossover, crossover_probability, rng);
+    debug_assert!(crossed_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
+
+    // A partir de la poblacion cruzada, mutamos para generar una ultima poblacion
+    // Esta operacion no consume iteraciones, por lo que no hacemos la suma
+    let mutated_population = crossed_population.mutate_population(individuals_to_mutate, rng);
+    debug_assert!(mutated_population.population_size() == population_size, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
+
+    // En la poblacion nueva podemos estar perdiendo el mejor individuo de la poblacion
+    // original. Tenemos que comprobar que dicho individuo sobreviva, y en caso de que no lo
+    // haga, introducirlo en la nueva poblacion, en su poblacion original.
+    // Esta operacion solo hace comprobaciones sobre el vector de posiciones (para comprobar
+    // que ya tengamos la solucion en la poblacion) y por ello no consume iteraciones. De todas
+    // formas, dejamos la comprobacion por seguridad (tenemos que elegir al mejor individuo de
+    // la poblacion original. Esta poblacion deberia estar evaluada, pero por si acaso)
+    let final_population = mutated_population.preserve_best_past_parent(current_population, evaluator);
+
+    // Evaluamos esta poblacion final. Esta operacion consume bastantes evaluaciones, porque
+    // llegamos aqui con una poblacion altamente modificada, cuyos fitness no se han evaluado.
+    // Otra gran parte de la poblacion, la que llega sin modificarse, no contribuye a estas
+    // evaluaciones
+    final_population.evaluate_all_individuals(number_of_workers, evaluator);
+
+    // Cada diez iteraciones, aplicamos la busqueda local suave segun el criterio que indica
+    // memetic_type. Las evaluaciones adicionales que consume esta busqueda local suave se
+    // descuentan del evaluador
+    if current_generation % 10 == 0{
+        return soft_local_search(final_population, memetic_type, max_fails, number_of_workers, evaluator, rng);
+    }
+
+    return final_population;
+}
+
+/// Aplica la busqueda local suave segun memetic_type a una poblacion evaluada
+///
+/// La busqueda local suave deja sin evaluar a los individuos que modifica. Si agota las
+/// evaluaciones del fitness, puede dejar a toda la poblacion sin evaluar, y entonces no sabriamos
+/// cual es su mejor individuo. En ese caso devolvemos la poblacion de partida
+fn soft_local_search(
+    population: Population,
+    memetic_type: MemeticType,
+    max_fails: i32,
+    number_of_workers: usize,
+    evaluator: &Evaluator,
+    rng: &mut StdRng
+    ) -> Population{
+    let searched_population = population.soft_local_search(memetic_type, max_fails, number_of_workers, evaluator, rng);
+
+    if evaluator.budget_left() == false && searched_population.all_population_is_not_cached() == true{
+        return population;
+    }
+    return searched_population;
+}
 
 /// Ejecuta la busqueda genetica y devuelve la solucion encontrada
-/// Usando algoritmo genetico estacionario con cruce uniforme
-fn run_steady<'a, 'b>(
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
-    max_fitness_evaluations: i32,
+/// Usamos busqueda genetica estacionaria con el operador de cruce dado, seleccionando a los
+/// padres por torneo binario, y los hijos entran en la poblacion segun la estrategia de reemplazo
+/// dada. Cada diez iteraciones aplicamos la busqueda local suave segun memetic_type
+/// El seguimiento de la diversidad se hace cada population_size / 2 iteraciones, como en el
+/// genetico estacionario
+#[allow(clippy::too_many_arguments)]
+fn run_steady(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
     rng: &mut StdRng,
+    number_of_workers: usize,
     population_size: i32,
     mutation_probability_per_gen: f64,
-    crossover_probability: f64,
     max_fails: i32,
-    memetic_type: SearchType
+    memetic_type: MemeticType,
+    crossover: CrossoverOperator,
+    replacement: ReplacementStrategy,
+    diversity_monitor: &mut DiversityMonitor
     )
-    -> (Solution<'a, 'b>, FitnessEvolution){
-
-    // Comprobamos que el tipo de busqueda, dado por memetic_type, sea correcto. Esto porque el
-    // enumerado puede llevar valores que no correspondan a memetico
-    match memetic_type{
-        // Opciones validas
-        SearchType::MemeticAll => (),
-        SearchType::MemeticRandom => (),
-        SearchType::MemeticElitist => (),
-
-        // Hemos alcanzado una opcion que no es valida
-        _ => {
-            println!("[Err] Tipo de busqueda memetica no correcta");
-            println!("El tipo de busqueda memetica obtenida fue: {:?}", memetic_type);
-            exit(-1);
-        }
-    }
+    -> (Solution, FitnessEvolution){
 
     // Llevamos la cuenta del fitness del mejor individuo en cada iteracion sobre la poblacion
     let mut fitness_evolution = FitnessEvolution::new();
 
-    // Poblacion inicial aleatoria
-    let mut current_population = Population::new_random_population(data_points, constraints, number_of_clusters, population_size, rng);
+    // Poblacion inicial aleatoria. La evaluamos entera, pues cada iteracion parte de una
+    // poblacion evaluada y no todas las estrategias de reemplazo evaluan a toda la poblacion
+    let mut current_population = Population::new_random_population(instance, population_size, rng);
+    current_population.evaluate_all_individuals(number_of_workers, evaluator);
+
+    // Iteraciones que forman una generacion, para el seguimiento de la diversidad
+    let iterations_per_generation = (population_size / 2).max(1);
+    let mut iterations_in_generation = 0;
 
     // Realizamos las iteraciones pertinentes
-    let mut consumed_fitness_evaluations = 0;
     let mut current_generation = 0;
-    while consumed_fitness_evaluations < max_fitness_evaluations{
-
-        // Las evaluaciones del fitness que se consumen en este ciclo
-        let mut iteration_fitness_evaluations = 0;
-
-        // Tomamos dos individuos de la poblacion por torneo binario
-        // Si la poblacion anterior no esta evaluada, puede consumir hasta cuatro evaluaciones del
-        // fitness (2 x 2 candidatos compitiendo)
-        let selection_population_result = current_population.select_population_binary_tournament(2, rng);
-        let selection_population = selection_population_result.get_result();
-        iteration_fitness_evaluations += selection_population_result.get_iterations_consumed();
-        debug_assert!(selection_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
-        debug_assert!(
-            selection_population_result.get_iterations_consumed() <= 4,
-            "En la seleccion de dos individos por torneo binario debemos consumir, como mucho, 4 evaluaciones. Hemos consumido {} evaluaciones",
-            selection_population_result.get_iterations_consumed()
-        );
-
-        // Cruzamos los dos individuos que hemos tomado de la poblacion, generando otros dos
-        // individuos. Esto no deberia provocar evaluaciones del fitness
-        let crossover_probability = 1.00; // Cruzamos forzosamente a los individuos
-        let crossed_population_result = selection_population.cross_population_uniform(crossover_probability, rng);
-        let crossed_population = crossed_population_result.get_result();
-        iteration_fitness_evaluations += crossed_population_result.get_iterations_consumed();
-        debug_assert!(crossed_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
-        debug_assert!(
-            crossed_population_result.get_iterations_consumed() == 0,
-            "El cruce no deberia consumir iteraciones, hemos consumidos {} iteraciones",
-            crossed_population_result.get_iterations_consumed()
-        );
-        debug_assert!(crossed_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
-
-        // A partir de los dos hijos cruzados, mutamos en caso de que se escoja aleatoriamente hacerlo
-        // Esta operacion no consume iteraciones, por lo que no hacemos la suma
-        let mutated_population = crossed_population.mutate_population_given_prob(mutation_probability_per_gen, rng);
-        debug_assert!(mutated_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
-        debug_assert!(mutated_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
-
-        // Los dos hijos, cruzados y en algunos casos mutados, compiten contra los peores elementos
-        // de la poblacion original para pasar a ser parte de ella
-        // Esta operacion consume evaluaciones del fitness. Salvo en la primera iteracion, deberia
-        // consumir como mucho dos evaluaciones (en los dos nuevos individuos que entran a competir)
-        let final_population_result = current_population.compete_with_new_individuals(&mutated_population);
-        let final_population = final_population_result.get_result();
-        iteration_fitness_evaluations += final_population_result.get_iterations_consumed();
-        debug_assert!(
-            final_population_result.get_iterations_consumed() >= 2,
-            "La competicion de los dos nuevos individuos contra toda la poblacion original debe provocar al menos dos evaluaciones del fitness, hemos obtenido {} evaluaciones",
-            final_population_result.get_iterations_consumed()
-        );
-
-        // Evaluamnos esta poblacion final. No deberia consumir ninguna iteracion, pues en la
-        // competicion de los dos nuevos individuos, ya deberiamos tener a toda la poblacion
-        // evaluada. Hacemos esto por seguridad, pues al tener todos los fitness cacheados, no
-        // deberia consumir demasiado tiempo
-        let evaluate_poblation_result = final_population.evaluate_all_individuals();
-        iteration_fitness_evaluations += evaluate_poblation_result.get_iterations_consumed();
-        debug_assert!(
-            evaluate_poblation_result.get_iterations_consumed() == 0,
-            "La poblacion deberia estar evaluada tras la competicion, pero estamos consumiendo {} evaluaciones",
-            evaluate_poblation_result.get_iterations_consumed()
+    while evaluator.budget_left() == true{
+        // Iteracion del genetico estacionario: dos hijos que entran en la poblacion segun la
+        // estrategia de reemplazo. La poblacion devuelta queda evaluada
+        let final_population = steady_genetic::next_generation(
+            &current_population,
+            evaluator,
+            rng,
+            number_of_workers,
+            mutation_probability_per_gen,
+            crossover,
+            SelectionOperator::Tournament(2),
+            replacement
         );
 
         // Cada diez iteraciones, aplicamos la busqueda local suave segun el criterio que indica
-        // memetic_type. Llevamos las cuentas de las evaluaciones adicionales que consume esta
-        // busqueda local suave
-        let soft_local_search_pop;
+        // memetic_type. Las evaluaciones adicionales que consume esta busqueda local suave se
+        // descuentan del evaluador
         if current_generation % 10 == 0{
-            let soft_local_search_pop_result = final_population.soft_local_search(memetic_type, max_fails, rng);
-            soft_local_search_pop = soft_local_search_pop_result.get_result().clone();
-            iteration_fitness_evaluations += soft_local_search_pop_result.get_iterations_consumed();
+            current_population = soft_local_search(final_population, memetic_type, max_fails, number_of_workers, evaluator, rng);
         }else{
-            soft_local_search_pop = final_population.clone();
+            current_population = final_population;
         }
-
-        // Realizamos el cambio de poblacion
-        // Hacemos una comprobacion de seguridad sobre la poblacion, tras las competicion de los
-        // dos nuevos individuos
-        current_population = soft_local_search_pop.clone();
         debug_assert!(current_population.population_size() == population_size as usize, "La poblacion final tras la iteracion tiene {} elementos", current_population.population_size());
 
-        // Añadimos las evaluaciones de fitness consumidas en esta pasada
-        consumed_fitness_evaluations += iteration_fitness_evaluations as i32;
-
         // Hemos creado una nueva generacion
         current_generation += 1;
 
         // Llevamos la cuenta del valor del fitness de la mejor solucion de la poblacion en esta
         // iteracion. No deberia consumir evaluaciones del fitness porque ya en operaciones
-        // pasadas estamos evaluando toda la poblacion. Ademas, hacemos una evaluacion sobre toda
-        // la poblacion extra por seguridad (de nuevo, esa comprobacion de seguridad no consume
-        // evaluaciones adicionales)
-        let best_individual = current_population.get_best_individual().get_result().0;
+        // pasadas estamos evaluando toda la poblacion
+        let (best_individual, _) = current_population.get_best_individual(evaluator);
         let best_individual_fitness = best_individual.fitness();
         fitness_evolution.add_iteration(best_individual_fitness);
+
+        // Si la poblacion ha perdido la diversidad, la reiniciamos manteniendo al mejor individuo
+        iterations_in_generation += 1;
+        if iterations_in_generation >= iterations_per_generation{
+            iterations_in_generation = 0;
+
+            if diversity_monitor.after_generation(&current_population, best_individual_fitness) == true{
+                current_population = current_population.random_restart(evaluator, rng);
+                current_population.evaluate_all_individuals(number_of_workers, evaluator);
+            }
+        }
     }
 
-    return (current_population.get_best_individual().get_result().0.clone(), fitness_evolution);
+    return (current_population.best_solution(evaluator, rng), fitness_evolution);
 }
 
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::memetic::Memetic;
+    use crate::problem_datatypes::population::MemeticType;
+    use crate::problem_datatypes::ProblemInstance;
+    use crate::metaheuristic::{Metaheuristic, Parameters};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Parametros del memetico con pocas evaluaciones, para que los tests sean rapidos
+    fn params(model: &str, replacement: &str, crossover: &str) -> Parameters{
+        let mut params = Parameters::new();
+        params.insert("max_fitness_evaluations", "2000");
+        params.insert("population_size", "10");
+        params.insert("model", model);
+        params.insert("replacement", replacement);
+        params.insert("crossover", crossover);
+        return params;
+    }
+
+    #[test]
+    // El modelo estacionario respeta el presupuesto con cualquier estrategia de reemplazo, y
+    // registra el fitness en cada iteracion de dos hijos, por lo que tiene muchas mas iteraciones
+    // que el generacional. Ademas, usa el operador de cruce indicado
+    fn test_steady_model(){
+        let instance = ProblemInstance::random_instance(&mut StdRng::seed_from_u64(123456789));
+        let memetic = Memetic::new(MemeticType::Elitist);
+
+        let generational = memetic.run(&instance, &params("generational", "worst", "uniform"), &mut StdRng::seed_from_u64(42)).unwrap();
+        let generational_iterations = generational.get_fitness_evolution().get_fitness_at_iteration().len();
+
+        for replacement in ["crowding", "restricted_tournament", "worst_without_duplicates"]{
+            let steady = memetic.run(&instance, &params("steady", replacement, "aligned_segment"), &mut StdRng::seed_from_u64(42)).unwrap();
+            assert!(steady.get_solution().is_valid() == true);
+            assert!(steady.get_fitness_evaluations() <= 2000);
+            assert!(steady.get_fitness_evolution().get_fitness_at_iteration().len() > 2 * generational_iterations);
+        }
+
+        let uniform = memetic.run(&instance, &params("steady", "crowding", "uniform"), &mut StdRng::seed_from_u64(42)).unwrap();
+        let group = memetic.run(&instance, &params("steady", "crowding", "group"), &mut StdRng::seed_from_u64(42)).unwrap();
+        assert_ne!(uniform.get_fitness_evolution().get_fitness_at_iteration(), group.get_fitness_evolution().get_fitness_at_iteration());
+
+        assert!(memetic.run(&instance, &params("no_existe", "worst", "uniform"), &mut StdRng::seed_from_u64(42)).is_err());
+    }
+}
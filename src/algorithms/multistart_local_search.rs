@@ -1,86 +1,269 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
-use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
+use crate::problem_datatypes::ProblemInstance;
 use crate::utils;
-use crate::arg_parser::SearchType;
-use crate::algorithms::local_search;
+use crate::algorithms::{local_search, path_relinking};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::ImproveOnly;
 
 use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
+
+/// Busqueda multiarranque basica: varias busquedas locales independientes desde soluciones
+/// aleatorias, de las que nos quedamos con la mejor
+pub struct MultiStartLocalSearch;
+
+impl MultiStartLocalSearch{
+    /// Toma los parametros de la busqueda: evaluaciones totales, numero de arranques, numero de
+    /// hilos y si aplicamos path relinking al terminar
+    fn read_parameters(&self, params: &Parameters) -> Result<(i32, i32, usize, bool), Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<i32>("max_fitness_evaluations")?;
+        let number_of_local_searchs = params.get::<i32>("number_of_local_searchs")?;
+        let number_of_workers = params.get::<usize>("number_of_workers")?;
+        let path_relinking = params.get::<bool>("path_relinking")?;
+
+        return Ok((max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking));
+    }
+}
+
+impl Metaheuristic for MultiStartLocalSearch{
+    fn name(&self) -> &'static str{
+        return "multistartlocalsearch";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Busqueda local multiarranque basica";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        // El maximo de evaluaciones es el total, como en el resto de algoritmos, y se reparte a
+        // partes iguales entre los arranques
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness, repartidas entre los arranques", Some("100000")),
+            ParameterSpec::new("number_of_local_searchs", "Numero de arranques", Some("10")),
+            ParameterSpec::new("number_of_workers", "Hilos entre los que se reparten los arranques", Some("1")),
+            ParameterSpec::new("path_relinking", "Reencadenar los optimos de los arranques con el mejor, usando las evaluaciones sobrantes", Some("false")),
+        ];
+    }
 
-/// Ejecuta y muestra los resultados de la busqueda
-/// Esto para no incluir todo este codigo en el cuerpo de la funcion main
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, rng: &mut StdRng){
-    // Numero maximo de iteraciones para la busqueda local <- Menos que en la busqueda local
-    // clasica, porque estamos repitiendo varias veces la busqueda
-    let max_fitness_evaluations = 10000;
-
-    // Numero de veces que lanzamos la busqueda local
-    let number_of_local_searchs = 10;
-
-    // Comprobacion de seguridad -> Debemos estar usando en total el mismo numero de evaluaciones
-    // del fitness que el resto de algoritmos
-    debug_assert!(
-        max_fitness_evaluations * number_of_local_searchs == 100000,
-        "No estamos usando el numero adecuado de evaluaciones del fitness o lanzamientos de local search"
-    );
-
-    // Lanzamos 10 veces la busqueda local, guardando las soluciones
-    // Tambien guardaremos las evoluciones del fitness, para poder guardarlas en un archivo al
-    // finalizar
-    let mut solutions = vec![];
-    let mut fitness_evolutions = vec![];
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let (max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking) = self.read_parameters(params)?;
+        return Ok(run(instance, max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking, rng));
+    }
+
+    /// Ademas del mejor arranque, mostramos y guardamos los resultados de cada uno de los arranques
+    fn run_and_show_results(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<(), Box<dyn Error>>{
+        let (max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking) = self.read_parameters(params)?;
+        let (best_result, solutions, fitness_evolutions, best_index) = run_all_starts(instance, max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking, rng);
+        metaheuristic::show_results(self.name(), self.description(), &best_result);
+
+        // Mostramos y guardamos tambien los resultados de cada uno de los arranques
+        println!("Mejor arranque: {}", best_index);
+        let file_name = utils::generate_file_name(self.name());
+        for (index, (solution, fitness_evolution)) in solutions.iter().zip(fitness_evolutions.iter()).enumerate(){
+            // Un arranque sin evaluaciones no llega a evaluar su solucion
+            let fitness = if solution.is_fitness_cached() == true {solution.fitness().to_string()} else {String::from("sin evaluar")};
+            println!("\t--> Arranque {}: fitness {}, salvado del fitness: {:?}",
+                index,
+                fitness,
+                fitness_evolution.save_as_numpy_file(&format!("{}--start{}", file_name, index))
+            );
+        }
+        println!();
+
+        return Ok(());
+    }
+}
+
+/// Lanza number_of_local_searchs busquedas locales independientes, que se reparten
+/// max_fitness_evaluations evaluaciones del fitness, y devuelve el resultado del mejor arranque
+/// Si path_relinking == true, reencadenamos los optimos de los arranques con el mejor de ellos
+/// (ver relink_starts)
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: i32,
+    number_of_local_searchs: i32,
+    number_of_workers: usize,
+    path_relinking: bool,
+    rng: &mut StdRng
+) -> RunResult{
+    let (best_result, _, _, _) = run_all_starts(instance, max_fitness_evaluations, number_of_local_searchs, number_of_workers, path_relinking, rng);
+    return best_result;
+}
+
+/// Igual que run, pero devuelve ademas las soluciones y las evoluciones del fitness de cada
+/// arranque, y el indice del mejor arranque
+fn run_all_starts(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: i32,
+    number_of_local_searchs: i32,
+    number_of_workers: usize,
+    path_relinking: bool,
+    rng: &mut StdRng
+) -> (RunResult, Vec<Solution>, Vec<FitnessEvolution>, usize){
+    let evaluator = Evaluator::new(max_fitness_evaluations as u32);
 
     // Llevamos la cuenta del tiempo empleado en todo el proceso
     let before = Instant::now();
-    for i in 0..number_of_local_searchs{
-        let (solucion_local, fitness_evolution) = local_search::run(&data_points, &constraints, program_arguments.get_number_of_clusters(), max_fitness_evaluations, rng);
-        solutions.insert(i as usize, solucion_local);
-        fitness_evolutions.insert(i as usize, fitness_evolution);
+    let (solutions, fitness_evolutions) = run_with_evaluator(instance, &evaluator, number_of_local_searchs, number_of_workers, rng);
+
+    // Nos quedamos con el mejor arranque y su evolucion del fitness
+    let best_index = select_best_solution(&evaluator, &solutions);
+    let mut best_solution = solutions[best_index].clone();
+    let mut best_fit_ev = fitness_evolutions[best_index].clone();
+    if path_relinking == true{
+        relink_starts(&evaluator, &solutions, &mut best_solution, &mut best_fit_ev);
+    }
+    let duration = before.elapsed();
+
+    let best_result = RunResult::new(best_solution, evaluator.consumed(), duration, best_fit_ev);
+    return (best_result, solutions, fitness_evolutions, best_index);
+}
+
+/// Lanza number_of_local_searchs busquedas locales independientes, repartidas entre
+/// number_of_workers hilos
+///
+/// Las evaluaciones que le quedan al evaluador se reparten a partes iguales entre los arranques
+/// antes de lanzar las busquedas. Las que sobran de la division se dan, de una en una, a los
+/// primeros arranques. Al terminar, las evaluaciones consumidas por cada arranque se suman al
+/// evaluador
+///
+/// Cada arranque usa su propio generador de numeros aleatorios, cuya semilla se saca del
+/// generador maestro antes de lanzar las busquedas. Asi cada arranque no depende del orden en que
+/// se ejecuten los demas, y la misma semilla da el mismo resultado con cualquier numero de hilos
+///
+/// Devuelve las soluciones y las evoluciones del fitness de cada arranque, en orden de arranque
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    number_of_local_searchs: i32,
+    number_of_workers: usize,
+    rng: &mut StdRng
+) -> (Vec<Solution>, Vec<FitnessEvolution>){
+    // Semillas y evaluadores de cada uno de los arranques
+    let number_of_starts = number_of_local_searchs.max(1) as u32;
+    let start_evaluations = evaluator.remaining() / number_of_starts;
+    let extra_evaluations = evaluator.remaining() % number_of_starts;
+    let mut starts = vec![];
+    for start in 0..number_of_starts{
+        let evaluations = start_evaluations + if start < extra_evaluations {1} else {0};
+        starts.push((rng.gen::<u64>(), Evaluator::new(evaluations)));
     }
 
-    // Las 10 repeticiones de local search han parado, cronometramos este instante
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Escogemos el indice de la solucion con mejor fitness
-    // Tomando el indice podemos tomar el fitness_evolution de la mejor solucion facilmente
-    let best_index = select_best_solution(&solutions);
-
-    // Recuperamos la mejor solucion y la mejor evolucion del fitness
-    let best_solution = &solutions[best_index];
-    let best_fit_ev = &fitness_evolutions[best_index];
-
-    // Mostramos los resultados
-    println!("==> Busqueda local multiarranque basica");
-    println!("La distancia global instracluster de la solucion es: {}", best_solution.global_cluster_mean_distance());
-    println!("El numero de restricciones violadas es: {}", best_solution.infeasibility());
-    println!("El valor de fitness es: {}", best_solution.fitness());
-    println!("El valor de lambda es: {}", best_solution.get_lambda());
-    println!("Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("Salvado del fitness: {:?}", best_fit_ev.save_as_numpy_file(&utils::generate_file_name(SearchType::MultiStartLocalSearch)));
-    println!("");
+    // Lanzamos las busquedas locales
+    let results = utils::parallel_map(&starts, number_of_workers, |(seed, start_evaluator)| {
+        let mut start_rng = StdRng::seed_from_u64(*seed);
+        return local_search::run_with_evaluator(instance, start_evaluator, &mut ImproveOnly, &mut start_rng);
+    });
+
+    for (_, start_evaluator) in starts.iter(){
+        evaluator.absorb(start_evaluator);
+    }
+
+    return results.into_iter().unzip();
+}
+
+/// Postoptimizacion con path relinking: reencadenamos los optimos de todos los arranques con el
+/// mejor de ellos, usando las evaluaciones que no hayan consumido los arranques. Si encontramos
+/// una solucion mejor, sustituye a best_solution y añadimos su fitness a la evolucion del fitness
+/// Los arranques que no han llegado a evaluar su solucion no se reencadenan
+fn relink_starts(evaluator: &Evaluator, solutions: &[Solution], best_solution: &mut Solution, fitness_evolution: &mut FitnessEvolution){
+    let evaluated_solutions: Vec<Solution> = solutions.iter().filter(|solution| solution.is_fitness_cached() == true).cloned().collect();
+
+    if let Some((relinked, relinked_fitness)) = path_relinking::relink_with_best(evaluator, &evaluated_solutions){
+        let improves = match evaluator.fitness(best_solution){
+            Some(best_fitness) => relinked_fitness < best_fitness,
+            None => true,
+        };
+
+        if improves == true{
+            *best_solution = relinked;
+            fitness_evolution.add_iteration(relinked_fitness);
+        }
+    }
 }
 
 /// Dado un vector de soluciones, devuelve el indice de la solucion con menor fitness (la mejor
 /// solucion para nuestro problema de minimizar el fitness)
+/// Las soluciones que el evaluador no puede evaluar se saltan. Si no se puede evaluar ninguna,
+/// devolvemos la primera
 /// El vector de soluciones debe tener al menos una solucion
-fn select_best_solution(solutions: &Vec<Solution>) -> usize{
+fn select_best_solution(evaluator: &Evaluator, solutions: &[Solution]) -> usize{
     // Comprobacion adicional de seguridad
-    debug_assert!(solutions.len() > 0, "El vector de soluciones debe tener al menos un elemento");
+    debug_assert!(solutions.is_empty() == false, "El vector de soluciones debe tener al menos un elemento");
+
+    let mut best: Option<(usize, f64)> = None;
+
+    for (index, solution) in solutions.iter().enumerate(){
+        let fitness = match evaluator.fitness(solution){
+            Some(fitness) => fitness,
+            None => continue,
+        };
 
-    let mut best_index = 0;
-    let mut best_fitness = solutions[best_index].fitness();
+        let improves = match best{
+            Some((_, best_fitness)) => fitness < best_fitness,
+            None => true,
+        };
+        if improves == true{
+            best = Some((index, fitness));
+        }
+    }
+
+    return match best{
+        Some((best_index, _)) => best_index,
+        None => 0,
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::multistart_local_search::{run, run_with_evaluator};
+    use crate::problem_datatypes::ProblemInstance;
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // La misma semilla debe dar los mismos arranques independientemente del numero de hilos
+    fn test_same_seed_same_result_with_any_number_of_workers(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
 
-    for index in 0..solutions.len(){
-        if solutions[index].fitness() < best_fitness{
-            best_fitness = solutions[index].fitness();
-            best_index = index;
+        let mut results = vec![];
+        for number_of_workers in [1, 2, 4, 7]{
+            let mut rng = StdRng::seed_from_u64(42);
+            let evaluator = Evaluator::new(3000);
+            let (solutions, fitness_evolutions) = run_with_evaluator(&instance, &evaluator, 6, number_of_workers, &mut rng);
+
+            let solutions: Vec<Vec<u32>> = solutions.iter().map(|solution| solution.get_cluster_indexes()).collect();
+            let fitness_evolutions: Vec<Vec<f64>> = fitness_evolutions.iter().map(|fit_ev| fit_ev.get_fitness_at_iteration()).collect();
+            results.push((solutions, fitness_evolutions));
+        }
+
+        for result in results.iter().skip(1){
+            assert_eq!(*result, results[0]);
         }
     }
 
-    return best_index;
+    #[test]
+    // Con menos evaluaciones que arranques, algunos arranques no llegan a evaluar su solucion. Nos
+    // quedamos con un arranque evaluado sin pasarnos del maximo de evaluaciones
+    fn test_starts_without_evaluations_are_skipped(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+
+        for path_relinking in [false, true]{
+            let result = run(&instance, 3, 6, 2, path_relinking, &mut rng);
+            assert!(result.get_fitness_evaluations() <= 3);
+            assert!(result.get_solution().is_fitness_cached() == true);
+        }
+    }
 }
@@ -0,0 +1,406 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::problem_datatypes::population::{Population, CrossoverOperator};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::utils;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// NSGA-II: algoritmo genetico multiobjetivo que minimiza por separado la distancia media
+/// intracluster y el numero de restricciones violadas, sin fijar lambda
+///
+/// Devuelve el frente de Pareto de la poblacion final. Como solucion del resultado tomamos la del
+/// frente con mejor fitness (con el lambda de la instancia), para poder compararla con el resto
+/// de algoritmos
+pub struct Nsga2;
+
+impl Nsga2{
+    /// Toma los parametros del algoritmo, salvo el fichero en el que guardamos el frente
+    fn read_parameters(&self, params: &Parameters) -> Result<(u32, Nsga2Parameters), Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let population_size = params.get::<i32>("population_size")?;
+
+        // Por defecto mutamos, como en los geneticos, 0.1 * population_size individuos por
+        // generacion
+        let individuals_to_mutate = params.get_optional::<i32>("individuals_to_mutate")?
+            .unwrap_or((0.1 * population_size as f64) as i32);
+
+        let nsga2_params = Nsga2Parameters{
+            population_size,
+            crossover_probability: params.get::<f64>("crossover_probability")?,
+            individuals_to_mutate,
+            crossover: params.get::<CrossoverOperator>("crossover")?,
+            number_of_workers: params.get::<usize>("number_of_workers")?,
+        };
+
+        return Ok((max_fitness_evaluations, nsga2_params));
+    }
+}
+
+impl Metaheuristic for Nsga2{
+    fn name(&self) -> &'static str{
+        return "nsga2";
+    }
+
+    fn description(&self) -> &'static str{
+        return "NSGA-II, minimizando distancia intracluster y restricciones violadas";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion", Some("50")),
+            ParameterSpec::new("crossover_probability", "Probabilidad de cruce de cada pareja", Some("0.7")),
+            ParameterSpec::new("individuals_to_mutate", "Individuos mutados por generacion (por defecto, 0.1 * population_size)", None),
+            ParameterSpec::new("crossover", "Operador de cruce: uniform, segment, aligned_uniform, aligned_segment o group", Some("uniform")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion", Some("1")),
+            ParameterSpec::new("pareto_front_file", "Fichero csv en el que guardamos el frente de Pareto (por defecto, junto a la evolucion del fitness)", None),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let (max_fitness_evaluations, nsga2_params) = self.read_parameters(params)?;
+        let (result, _) = run(instance, max_fitness_evaluations, nsga2_params, rng);
+        return Ok(result);
+    }
+
+    /// Ademas del resultado, guardamos el frente de Pareto en un fichero csv
+    fn run_and_show_results(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<(), Box<dyn Error>>{
+        let (max_fitness_evaluations, nsga2_params) = self.read_parameters(params)?;
+        let file_path = match params.get_optional::<String>("pareto_front_file")?{
+            Some(file_path) => file_path,
+            None => format!("{}--pareto_front.csv", utils::generate_file_name(self.name())),
+        };
+
+        let (result, pareto_front) = run(instance, max_fitness_evaluations, nsga2_params, rng);
+        metaheuristic::show_results(self.name(), self.description(), &result);
+
+        println!("Soluciones en el frente de Pareto: {}", pareto_front.len());
+        match save_pareto_front_as_csv(&pareto_front, &file_path){
+            Ok(()) => println!("Frente de Pareto guardado en: {}", file_path),
+            Err(err) => {
+                eprintln!("[Err] No se pudo guardar el frente de Pareto en {}", file_path);
+                eprintln!("Codigo de error: {}", err);
+            }
+        }
+        println!();
+
+        return Ok(());
+    }
+}
+
+/// Parametros de NSGA-II
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Nsga2Parameters{
+    pub population_size: i32,
+
+    /// Probabilidad de cruce de cada pareja
+    pub crossover_probability: f64,
+
+    /// Individuos mutados por generacion
+    pub individuals_to_mutate: i32,
+
+    /// Operador de cruce
+    pub crossover: CrossoverOperator,
+
+    /// Hilos con los que se evalua la poblacion
+    pub number_of_workers: usize,
+}
+
+/// Ejecuta NSGA-II consumiendo como mucho max_fitness_evaluations evaluaciones del fitness
+/// Devuelve el resultado con la solucion del frente de mejor fitness, y el frente de Pareto
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, nsga2_params: Nsga2Parameters, rng: &mut StdRng) -> (RunResult, Vec<Solution>){
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (pareto_front, fitness_evolution) = run_with_evaluator(instance, &evaluator, nsga2_params, rng);
+    let duration = before.elapsed();
+
+    let best_solution = pareto_front.iter()
+        .min_by(|first, second| first.fitness().partial_cmp(&second.fitness()).unwrap())
+        .expect("El frente de Pareto no puede estar vacio")
+        .clone();
+
+    return (RunResult::new(best_solution, evaluator.consumed(), duration, fitness_evolution), pareto_front);
+}
+
+/// Ejecuta NSGA-II descontando las evaluaciones del fitness del evaluador dado, y devuelve el
+/// frente de Pareto de la poblacion final, sin soluciones repetidas
+///
+/// Los objetivos son la distancia media intracluster y el numero de restricciones violadas, que
+/// obtenemos al evaluar el fitness de cada individuo. En cada generacion seleccionamos por torneo
+/// binario con el operador de comparacion por rango y distancia de crowding, cruzamos y mutamos
+/// con los operadores de los geneticos, y escogemos la siguiente poblacion de entre padres e hijos
+/// por frentes de no dominancia. En la evolucion del fitness guardamos el mejor fitness (con el
+/// lambda de la instancia) de la poblacion en cada generacion
+///
+/// Si no quedan evaluaciones ni para evaluar un individuo, el frente contiene una unica solucion
+/// aleatoria, que queda sin evaluar
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, nsga2_params: Nsga2Parameters, rng: &mut StdRng) -> (Vec<Solution>, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    // Poblacion inicial aleatoria, de la que descartamos los individuos que no se han podido evaluar
+    let initial_population = Population::new_random_population(instance, nsga2_params.population_size, rng);
+    initial_population.evaluate_all_individuals(nsga2_params.number_of_workers, evaluator);
+    let mut population: Vec<Solution> = initial_population.into_individuals()
+        .into_iter()
+        .filter(|individual| individual.is_fitness_cached() == true)
+        .collect();
+
+    if population.is_empty(){
+        let mut solution = Solution::generate_random_solution(instance, rng);
+        if solution.is_valid() == false{
+            solution.repair_solution(rng);
+        }
+        return (vec![solution], fitness_evolution);
+    }
+    fitness_evolution.add_iteration(best_fitness(&population));
+
+    while evaluator.budget_left() == true{
+        // Seleccion por torneo binario con el operador de comparacion de crowding
+        let objectives: Vec<(f64, f64)> = population.iter().map(objectives_of).collect();
+        let (ranks, crowding) = rank_and_crowding(&objectives);
+        let mut selected = vec![];
+        for _ in 0..nsga2_params.population_size{
+            let first = rng.gen_range(0..population.len());
+            let second = rng.gen_range(0..population.len());
+            let winner = if crowded_comparison(first, second, &ranks, &crowding) == true {first} else {second};
+            selected.push(population[winner].clone());
+        }
+
+        // Cruce y mutacion con los operadores de los geneticos
+        let selected = Population::from_individuals(selected);
+        let crossed = selected.cross_population(nsga2_params.crossover, nsga2_params.crossover_probability, rng);
+        let offspring = crossed.mutate_population(nsga2_params.individuals_to_mutate, rng);
+
+        // Los hijos que no se han podido evaluar no entran en la poblacion
+        offspring.evaluate_all_individuals(nsga2_params.number_of_workers, evaluator);
+        let mut union = population;
+        union.extend(offspring.into_individuals().into_iter().filter(|individual| individual.is_fitness_cached() == true));
+
+        population = select_next_population(union, nsga2_params.population_size as usize);
+        fitness_evolution.add_iteration(best_fitness(&population));
+    }
+
+    return (pareto_front(population), fitness_evolution);
+}
+
+/// Guarda el frente de Pareto en un fichero csv, ordenado por numero de restricciones violadas
+/// Cada fila tiene la distancia media intracluster, las restricciones violadas, el fitness y la
+/// asignacion de clusters, separada por espacios. Las soluciones deben estar evaluadas
+pub fn save_pareto_front_as_csv(pareto_front: &[Solution], file_path: &str) -> Result<(), Box<dyn Error>>{
+    let mut sorted_front: Vec<&Solution> = pareto_front.iter().collect();
+    sorted_front.sort_by(|first, second| objectives_of(first).partial_cmp(&objectives_of(second)).unwrap());
+
+    let mut writer = csv::Writer::from_path(file_path)?;
+    writer.write_record(["global_cluster_mean_distance", "infeasibility", "fitness", "cluster_indexes"])?;
+    for solution in sorted_front{
+        let cluster_indexes: Vec<String> = solution.get_cluster_indexes().iter().map(|cluster| cluster.to_string()).collect();
+        writer.write_record(&[
+            solution.global_cluster_mean_distance().to_string(),
+            solution.infeasibility().to_string(),
+            solution.fitness().to_string(),
+            cluster_indexes.join(" "),
+        ])?;
+    }
+    writer.flush()?;
+
+    return Ok(());
+}
+
+/// Objetivos de una solucion ya evaluada: (distancia media intracluster, restricciones violadas)
+/// Al estar evaluada, las estadisticas por cluster estan calculadas y no hay que recorrer los datos
+fn objectives_of(solution: &Solution) -> (f64, f64){
+    return (solution.global_cluster_mean_distance(), solution.infeasibility() as f64);
+}
+
+/// Mejor fitness, con el lambda de la instancia, de una poblacion evaluada
+fn best_fitness(population: &[Solution]) -> f64{
+    return population.iter().map(|individual| individual.fitness()).fold(f64::INFINITY, f64::min);
+}
+
+/// Comprueba si first domina a second: no es peor en ningun objetivo y es mejor en alguno
+fn dominates(first: (f64, f64), second: (f64, f64)) -> bool{
+    let not_worse = first.0 <= second.0 && first.1 <= second.1;
+    let better = first.0 < second.0 || first.1 < second.1;
+    return not_worse && better;
+}
+
+/// Ordenacion rapida por no dominancia. Devuelve los frentes, empezando por el de las soluciones
+/// no dominadas, con los indices de las soluciones de cada uno
+fn non_dominated_sort(objectives: &[(f64, f64)]) -> Vec<Vec<usize>>{
+    // Soluciones que domina cada solucion, y numero de soluciones que la dominan
+    let mut dominated_by: Vec<Vec<usize>> = vec![vec![]; objectives.len()];
+    let mut domination_count = vec![0; objectives.len()];
+
+    for first in 0..objectives.len(){
+        for second in 0..objectives.len(){
+            if dominates(objectives[first], objectives[second]) == true{
+                dominated_by[first].push(second);
+            }else if dominates(objectives[second], objectives[first]) == true{
+                domination_count[first] += 1;
+            }
+        }
+    }
+
+    let mut fronts = vec![];
+    let mut current_front: Vec<usize> = (0..objectives.len()).filter(|index| domination_count[*index] == 0).collect();
+    while current_front.is_empty() == false{
+        let mut next_front = vec![];
+        for index in current_front.iter(){
+            for dominated in dominated_by[*index].iter(){
+                domination_count[*dominated] -= 1;
+                if domination_count[*dominated] == 0{
+                    next_front.push(*dominated);
+                }
+            }
+        }
+
+        fronts.push(current_front);
+        current_front = next_front;
+    }
+
+    return fronts;
+}
+
+/// Distancia de crowding de cada solucion del frente, en el mismo orden que front. Los extremos
+/// de cada objetivo tienen distancia infinita, para conservarlos siempre
+fn crowding_distance(objectives: &[(f64, f64)], front: &[usize]) -> Vec<f64>{
+    let mut distances = vec![0.0; front.len()];
+
+    for objective in 0..2{
+        // Valor del objetivo de la solucion en la posicion dada del frente
+        let value = |position: usize| -> f64{
+            let objectives = objectives[front[position]];
+            if objective == 0 {objectives.0} else {objectives.1}
+        };
+
+        // Posiciones del frente ordenadas por el objetivo
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|first, second| value(*first).partial_cmp(&value(*second)).unwrap());
+
+        let min_value = value(order[0]);
+        let max_value = value(order[front.len() - 1]);
+        distances[order[0]] = f64::INFINITY;
+        distances[order[front.len() - 1]] = f64::INFINITY;
+
+        if max_value - min_value <= 0.0{
+            continue;
+        }
+
+        for position in 1..front.len().saturating_sub(1){
+            distances[order[position]] += (value(order[position + 1]) - value(order[position - 1])) / (max_value - min_value);
+        }
+    }
+
+    return distances;
+}
+
+/// Rango (indice del frente) y distancia de crowding de cada solucion
+fn rank_and_crowding(objectives: &[(f64, f64)]) -> (Vec<usize>, Vec<f64>){
+    let mut ranks = vec![0; objectives.len()];
+    let mut crowding = vec![0.0; objectives.len()];
+
+    for (rank, front) in non_dominated_sort(objectives).iter().enumerate(){
+        let front_crowding = crowding_distance(objectives, front);
+        for (position, index) in front.iter().enumerate(){
+            ranks[*index] = rank;
+            crowding[*index] = front_crowding[position];
+        }
+    }
+
+    return (ranks, crowding);
+}
+
+/// Operador de comparacion de crowding: gana la solucion de menor rango y, a igual rango, la de
+/// mayor distancia de crowding. Devuelve true si gana first
+fn crowded_comparison(first: usize, second: usize, ranks: &[usize], crowding: &[f64]) -> bool{
+    if ranks[first] != ranks[second]{
+        return ranks[first] < ranks[second];
+    }
+    return crowding[first] >= crowding[second];
+}
+
+/// Escoge population_size soluciones de union por frentes de no dominancia. El ultimo frente que
+/// cabe solo en parte se completa con las soluciones de mayor distancia de crowding
+fn select_next_population(union: Vec<Solution>, population_size: usize) -> Vec<Solution>{
+    let objectives: Vec<(f64, f64)> = union.iter().map(objectives_of).collect();
+
+    let mut selected_indexes = vec![];
+    for front in non_dominated_sort(&objectives){
+        if selected_indexes.len() + front.len() <= population_size{
+            selected_indexes.extend(front);
+            continue;
+        }
+
+        let crowding = crowding_distance(&objectives, &front);
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|first, second| crowding[*second].partial_cmp(&crowding[*first]).unwrap());
+        for position in order.into_iter().take(population_size - selected_indexes.len()){
+            selected_indexes.push(front[position]);
+        }
+        break;
+    }
+
+    let mut union: Vec<Option<Solution>> = union.into_iter().map(Some).collect();
+    return selected_indexes.into_iter().map(|index| union[index].take().unwrap()).collect();
+}
+
+/// Frente de Pareto de una poblacion evaluada, sin soluciones repetidas (las que representan la
+/// misma agrupacion, aunque tengan distintas etiquetas de clusters)
+fn pareto_front(population: Vec<Solution>) -> Vec<Solution>{
+    let objectives: Vec<(f64, f64)> = population.iter().map(objectives_of).collect();
+    let first_front = non_dominated_sort(&objectives).into_iter().next().unwrap_or_default();
+
+    let mut front: Vec<Solution> = vec![];
+    for index in first_front{
+        if front.contains(&population[index]) == false{
+            front.push(population[index].clone());
+        }
+    }
+
+    return front;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::nsga2::{non_dominated_sort, crowding_distance};
+
+    #[test]
+    fn test_non_dominated_sort_and_crowding(){
+        let objectives = vec![
+            (1.0, 4.0),     // Frente 0
+            (2.0, 2.0),     // Frente 0
+            (4.0, 1.0),     // Frente 0
+            (3.0, 3.0),     // Frente 1, dominada por (2, 2)
+            (4.0, 4.0),     // Frente 2, dominada por (3, 3)
+            (2.0, 2.0),     // Frente 0, igual que (2, 2), que no la domina
+        ];
+
+        let mut fronts = non_dominated_sort(&objectives);
+        for front in fronts.iter_mut(){
+            front.sort();
+        }
+        assert_eq!(fronts, vec![vec![0, 1, 2, 5], vec![3], vec![4]]);
+
+        // Los extremos tienen distancia infinita, y las soluciones interiores la suma de las
+        // distancias normalizadas entre sus vecinas en cada objetivo
+        let front = vec![0, 1, 2];
+        let distances = crowding_distance(&objectives, &front);
+        assert_eq!(distances[0], f64::INFINITY);
+        assert_eq!(distances[2], f64::INFINITY);
+        assert!((distances[1] - 2.0).abs() < 0.0001);
+
+        // Frentes de una y dos soluciones
+        assert_eq!(crowding_distance(&objectives, &[3]), vec![f64::INFINITY]);
+        assert_eq!(crowding_distance(&objectives, &[0, 3]), vec![f64::INFINITY, f64::INFINITY]);
+    }
+}
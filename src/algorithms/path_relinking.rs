@@ -0,0 +1,153 @@
+use crate::problem_datatypes::Solution;
+use crate::evaluator::Evaluator;
+
+/// Reencadenamiento de trayectorias (path relinking) entre dos soluciones de elite
+///
+/// Partiendo de initial, nos acercamos a guiding reasignando un punto cada vez. En cada paso
+/// evaluamos todos los puntos en los que todavia difieren, moviendo cada uno al cluster que tiene
+/// en guiding, y nos quedamos con el movimiento de mejor fitness aunque empeore. Antes de empezar
+/// renombramos los clusters de guiding para que coincidan con los de initial, de forma que no
+/// recorramos puntos que solo difieren en la etiqueta del cluster
+///
+/// No hacemos movimientos que dejen un cluster vacio, por lo que todas las soluciones del camino
+/// son validas. Termina al llegar a guiding, al no quedar movimientos validos o al agotar las
+/// evaluaciones. Devuelve la mejor solucion intermedia del camino, sin contar los extremos, o None
+/// si no se ha podido evaluar ninguna
+pub fn relink(evaluator: &Evaluator, initial: &Solution, guiding: &Solution) -> Option<(Solution, f64)>{
+    let guiding = guiding.relabeled_like(initial);
+    let guiding_indexes = guiding.get_cluster_indexes();
+
+    let mut current = initial.clone();
+    let mut current_indexes = current.get_cluster_indexes();
+    let mut best: Option<(Solution, f64)> = None;
+
+    // Tamaño de cada cluster, para no hacer movimientos que dejen clusters vacios
+    let mut cluster_sizes = vec![0; initial.get_number_of_clusters() as usize];
+    for cluster in current_indexes.iter(){
+        cluster_sizes[*cluster as usize] += 1;
+    }
+
+    loop{
+        let mut best_move: Option<(usize, Solution, f64)> = None;
+
+        for point_index in 0..current_indexes.len(){
+            if current_indexes[point_index] == guiding_indexes[point_index]{
+                continue;
+            }
+            if cluster_sizes[current_indexes[point_index] as usize] <= 1{
+                continue;
+            }
+
+            let mut candidate = current.clone();
+            candidate.move_point(point_index, guiding_indexes[point_index]);
+            let candidate_fitness = match evaluator.fitness(&candidate){
+                Some(fitness) => fitness,
+                None => break,
+            };
+
+            let improves = match &best_move{
+                Some((_, _, best_move_fitness)) => candidate_fitness < *best_move_fitness,
+                None => true,
+            };
+            if improves == true{
+                best_move = Some((point_index, candidate, candidate_fitness));
+            }
+        }
+
+        let (point_index, candidate, candidate_fitness) = match best_move{
+            Some(best_move) => best_move,
+
+            // Hemos llegado a guiding, no quedan movimientos validos o no quedan evaluaciones
+            None => break,
+        };
+
+        // No contamos guiding como solucion intermedia
+        if candidate == guiding{
+            break;
+        }
+
+        cluster_sizes[current_indexes[point_index] as usize] -= 1;
+        cluster_sizes[guiding_indexes[point_index] as usize] += 1;
+        current_indexes[point_index] = guiding_indexes[point_index];
+        current = candidate;
+
+        let improves = match &best{
+            Some((_, best_fitness)) => candidate_fitness < *best_fitness,
+            None => true,
+        };
+        if improves == true{
+            best = Some((current.clone(), candidate_fitness));
+        }
+
+        if evaluator.budget_left() == false{
+            break;
+        }
+    }
+
+    return best;
+}
+
+/// Reencadena cada solucion de elite con la mejor de ellas (la de menor fitness), tomando la
+/// mejor como solucion guia. Devuelve la mejor solucion intermedia encontrada en todos los
+/// caminos, o None si no se ha encontrado ninguna
+///
+/// Pensado como postoptimizacion de los algoritmos que terminan con varias soluciones de elite,
+/// como la busqueda multiarranque. Las soluciones deben estar evaluadas
+pub fn relink_with_best(evaluator: &Evaluator, elite_solutions: &[Solution]) -> Option<(Solution, f64)>{
+    let guiding = elite_solutions.iter().min_by(|first, second| first.fitness().partial_cmp(&second.fitness()).unwrap())?;
+    let mut best: Option<(Solution, f64)> = None;
+
+    for initial in elite_solutions.iter(){
+        if evaluator.budget_left() == false{
+            break;
+        }
+
+        let (solution, fitness) = match relink(evaluator, initial, guiding){
+            Some(relinked) => relinked,
+            None => continue,
+        };
+
+        let improves = match &best{
+            Some((_, best_fitness)) => fitness < *best_fitness,
+            None => true,
+        };
+        if improves == true{
+            best = Some((solution, fitness));
+        }
+    }
+
+    return best;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::path_relinking::relink;
+    use crate::problem_datatypes::{ProblemInstance, Solution};
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // Las soluciones del camino son validas y estan a distancia estrictamente menor de guiding
+    // que initial. Con evaluaciones de sobra el camino se recorre entero, y consume como mucho
+    // d * (d + 1) / 2 evaluaciones, siendo d la distancia entre los extremos
+    fn test_relink_between_two_solutions(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+
+        let mut initial = Solution::generate_random_solution(&instance, &mut rng);
+        initial.repair_solution(&mut rng);
+        let guiding = initial.shaken(10, &mut rng);
+        let distance = initial.partition_distance(&guiding);
+
+        let evaluator = Evaluator::new(100000);
+        let (relinked, fitness) = relink(&evaluator, &initial, &guiding).expect("El camino tiene soluciones intermedias");
+
+        assert!(relinked.is_valid() == true);
+        assert_eq!(relinked.fitness(), fitness);
+        assert!(relinked.partition_distance(&guiding) < distance);
+        assert!(relinked.partition_distance(&initial) > 0);
+        assert!(evaluator.consumed() as usize <= distance * (distance + 1) / 2);
+    }
+}
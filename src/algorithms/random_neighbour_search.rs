@@ -0,0 +1,231 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+
+use rand::rngs::StdRng;
+use std::sync::Arc;
+
+/// Regla con la que las busquedas sobre vecinos aleatorios (late acceptance, aceptacion por
+/// umbral, gran diluvio y viaje de record a record) deciden si aceptan un vecino que empeora la
+/// solucion actual. Los vecinos que no empeoran se aceptan siempre
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AcceptanceRule{
+    /// Acepta si el vecino no empeora la solucion que teniamos hace history_length iteraciones
+    LateAcceptance{history_length: usize},
+
+    /// Acepta si el vecino no empeora la solucion actual en mas del umbral. El umbral empieza en
+    /// initial_threshold veces el fitness de la solucion inicial y desciende linealmente hasta
+    /// cero segun consumimos las evaluaciones
+    Threshold{initial_threshold: f64},
+
+    /// Acepta si el fitness del vecino no supera el nivel del agua, que empieza en el fitness de
+    /// la solucion inicial y desciende linealmente hasta final_level veces dicho fitness segun
+    /// consumimos las evaluaciones
+    Deluge{final_level: f64},
+
+    /// Acepta si el fitness del vecino no supera el de la mejor solucion encontrada mas
+    /// deviation veces dicho fitness
+    RecordToRecord{deviation: f64},
+}
+
+/// Estado de la regla de aceptacion a lo largo de la busqueda
+struct AcceptanceState{
+    rule: AcceptanceRule,
+
+    /// Fitness de la solucion inicial, del que parten el umbral y el nivel del agua
+    initial_fitness: f64,
+
+    /// Historial de fitness de late acceptance, inicializado con el fitness inicial
+    history: Vec<f64>,
+    iteration: usize,
+}
+
+impl AcceptanceState{
+    fn new(rule: AcceptanceRule, initial_fitness: f64) -> Self{
+        let history_length = match rule{
+            AcceptanceRule::LateAcceptance{history_length} => history_length.max(1),
+            _ => 0,
+        };
+
+        return Self{rule, initial_fitness, history: vec![initial_fitness; history_length], iteration: 0};
+    }
+
+    /// Decide si aceptamos el vecino. progress es la fraccion de las evaluaciones disponibles
+    /// al empezar que ya hemos consumido
+    fn accepts(&self, neighbour_fitness: f64, current_fitness: f64, best_fitness: f64, progress: f64) -> bool{
+        if neighbour_fitness <= current_fitness{
+            return true;
+        }
+
+        match self.rule{
+            AcceptanceRule::LateAcceptance{..} => return neighbour_fitness <= self.history[self.iteration % self.history.len()],
+            AcceptanceRule::Threshold{initial_threshold} => {
+                return neighbour_fitness - current_fitness < threshold(initial_threshold * self.initial_fitness, progress);
+            },
+            AcceptanceRule::Deluge{final_level} => return neighbour_fitness <= deluge_level(self.initial_fitness, final_level, progress),
+            AcceptanceRule::RecordToRecord{deviation} => return neighbour_fitness <= record_to_record_level(best_fitness, deviation),
+        }
+    }
+
+    /// Cierra una iteracion, con el fitness de la solucion actual tras decidir
+    fn end_iteration(&mut self, current_fitness: f64){
+        if self.history.is_empty() == false{
+            let history_index = self.iteration % self.history.len();
+            self.history[history_index] = current_fitness;
+        }
+        self.iteration += 1;
+    }
+}
+
+/// Umbral de la aceptacion por umbral tras consumir la fraccion progress de las evaluaciones
+fn threshold(start_threshold: f64, progress: f64) -> f64{
+    return start_threshold * (1.0 - progress).max(0.0);
+}
+
+/// Nivel del agua del gran diluvio tras consumir la fraccion progress de las evaluaciones
+fn deluge_level(initial_fitness: f64, final_level: f64, progress: f64) -> f64{
+    return initial_fitness - progress * (initial_fitness - final_level * initial_fitness);
+}
+
+/// Nivel del agua del viaje de record a record
+fn record_to_record_level(best_fitness: f64, deviation: f64) -> f64{
+    return best_fitness + deviation * best_fitness;
+}
+
+/// Busqueda por trayectorias sobre vecinos aleatorios partiendo de una solucion aleatoria,
+/// descontando las evaluaciones del fitness del evaluador dado
+///
+/// En cada iteracion generamos un vecino aleatorio, que cambia un unico punto de cluster, y lo
+/// aceptamos segun la regla dada. Devolvemos la mejor solucion encontrada, y en la evolucion del
+/// fitness guardamos el fitness de cada solucion aceptada
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, rule: AcceptanceRule, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    // La solucion aleatoria puede dejar clusters vacios, asi que la reparamos
+    let mut current_solution = Solution::generate_random_solution(instance, rng);
+    if current_solution.is_valid() == false{
+        current_solution.repair_solution(rng);
+    }
+
+    let mut current_fitness = match evaluator.fitness(&current_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (current_solution, fitness_evolution),
+    };
+    fitness_evolution.add_iteration(current_fitness);
+
+    let mut best_solution = current_solution.clone();
+    let mut best_fitness = current_fitness;
+
+    // Evaluaciones de las que disponemos para hacer descender el umbral o el nivel del agua
+    let mut acceptance = AcceptanceState::new(rule, current_fitness);
+    let available_evaluations = evaluator.remaining() as f64;
+    let consumed_at_start = evaluator.consumed();
+
+    while evaluator.budget_left() == true{
+        let neighbour = current_solution.one_random_neighbour(rng);
+        let neighbour_fitness = match evaluator.fitness(&neighbour){
+            Some(fitness) => fitness,
+            None => break,
+        };
+
+        let progress = (evaluator.consumed() - consumed_at_start) as f64 / available_evaluations;
+        if acceptance.accepts(neighbour_fitness, current_fitness, best_fitness, progress) == true{
+            current_solution = neighbour;
+            current_fitness = neighbour_fitness;
+            fitness_evolution.add_iteration(current_fitness);
+
+            if current_fitness < best_fitness{
+                best_solution = current_solution.clone();
+                best_fitness = current_fitness;
+            }
+        }
+
+        acceptance.end_iteration(current_fitness);
+    }
+
+    return (best_solution, fitness_evolution);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::random_neighbour_search::{AcceptanceRule, AcceptanceState, threshold, deluge_level, record_to_record_level, run_with_evaluator};
+    use crate::problem_datatypes::ProblemInstance;
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // Con historial de longitud 1, late acceptance compara con la solucion actual, asi que es una
+    // busqueda local que acepta empates: el fitness de las soluciones aceptadas nunca sube
+    fn test_late_acceptance_with_unit_history_is_improve_or_equal(){
+        let mut acceptance = AcceptanceState::new(AcceptanceRule::LateAcceptance{history_length: 1}, 1.0);
+        assert!(acceptance.accepts(1.0, 1.0, 1.0, 0.0) == true);
+        assert!(acceptance.accepts(1.1, 1.0, 1.0, 0.0) == false);
+        acceptance.end_iteration(0.8);
+        assert!(acceptance.accepts(0.9, 0.8, 0.8, 0.0) == false);
+        assert!(acceptance.accepts(0.8, 0.8, 0.8, 0.0) == true);
+
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let evaluator = Evaluator::new(2000);
+        let (_, fitness_evolution) = run_with_evaluator(&instance, &evaluator, AcceptanceRule::LateAcceptance{history_length: 1}, &mut rng);
+        let accepted = fitness_evolution.get_fitness_at_iteration();
+        assert!(accepted.windows(2).all(|pair| pair[1] <= pair[0]));
+    }
+
+    #[test]
+    // Con historial de longitud 3, comparamos con el fitness de hace tres iteraciones
+    fn test_late_acceptance_history_indexing(){
+        let mut acceptance = AcceptanceState::new(AcceptanceRule::LateAcceptance{history_length: 3}, 1.0);
+        for current_fitness in [0.9, 0.8, 0.7]{
+            acceptance.end_iteration(current_fitness);
+        }
+
+        // La iteracion 3 compara con la 0, en la que la solucion actual tenia fitness 0.9
+        assert!(acceptance.accepts(0.9, 0.7, 0.7, 0.0) == true);
+        assert!(acceptance.accepts(0.95, 0.7, 0.7, 0.0) == false);
+        acceptance.end_iteration(0.7);
+
+        // La iteracion 4 compara con la 1
+        assert!(acceptance.accepts(0.8, 0.7, 0.7, 0.0) == true);
+        assert!(acceptance.accepts(0.85, 0.7, 0.7, 0.0) == false);
+    }
+
+    #[test]
+    // El umbral desciende linealmente hasta 0 al agotar las evaluaciones, y entonces solo se
+    // aceptan vecinos que no empeoran
+    fn test_threshold_falls_to_zero(){
+        assert_eq!(threshold(0.1, 0.0), 0.1);
+        assert_eq!(threshold(0.1, 0.5), 0.05);
+        assert_eq!(threshold(0.1, 1.0), 0.0);
+        assert_eq!(threshold(0.1, 1.5), 0.0);
+
+        let acceptance = AcceptanceState::new(AcceptanceRule::Threshold{initial_threshold: 0.1}, 1.0);
+        assert!(acceptance.accepts(1.05, 1.0, 1.0, 0.0) == true);
+        assert!(acceptance.accepts(1.05, 1.0, 1.0, 0.6) == false);
+        assert!(acceptance.accepts(1.0 + 1e-9, 1.0, 1.0, 1.0) == false);
+        assert!(acceptance.accepts(1.0, 1.0, 1.0, 1.0) == true);
+    }
+
+    #[test]
+    // El nivel del gran diluvio baja del fitness inicial a final_level veces dicho fitness, y el
+    // del viaje de record a record sigue a la mejor solucion
+    fn test_water_levels(){
+        assert_eq!(deluge_level(2.0, 0.5, 0.0), 2.0);
+        assert_eq!(deluge_level(2.0, 0.5, 0.5), 1.5);
+        assert_eq!(deluge_level(2.0, 0.5, 1.0), 1.0);
+        assert_eq!(record_to_record_level(2.0, 0.1), 2.2);
+
+        let deluge = AcceptanceState::new(AcceptanceRule::Deluge{final_level: 0.5}, 2.0);
+        assert!(deluge.accepts(1.8, 1.2, 1.2, 0.0) == true);
+        assert!(deluge.accepts(1.8, 1.2, 1.2, 0.5) == false);
+
+        let record_to_record = AcceptanceState::new(AcceptanceRule::RecordToRecord{deviation: 0.1}, 2.0);
+        assert!(record_to_record.accepts(1.05, 1.0, 1.0, 0.0) == true);
+        assert!(record_to_record.accepts(1.15, 1.0, 1.0, 0.0) == false);
+        assert!(record_to_record.accepts(1.15, 1.0, 1.1, 0.0) == true);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::local_search;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::ImproveOnly;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Busqueda dispersa: combinamos las soluciones de un conjunto de referencia pequeño, formado por
+/// soluciones buenas y soluciones diversas, y mejoramos las combinaciones con busqueda local
+pub struct ScatterSearch;
+
+impl Metaheuristic for ScatterSearch{
+    fn name(&self) -> &'static str{
+        return "scatter_search";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Busqueda dispersa";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("reference_set_size", "Soluciones del conjunto de referencia, la mitad de calidad y la mitad diversas", Some("10")),
+            ParameterSpec::new("diverse_set_size", "Soluciones aleatorias entre las que escogemos el conjunto de referencia", Some("30")),
+            ParameterSpec::new("local_search_evaluations", "Maximo de evaluaciones de cada busqueda local de mejora", Some("1000")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let reference_set_size = params.get::<usize>("reference_set_size")?;
+        let diverse_set_size = params.get::<usize>("diverse_set_size")?;
+        let local_search_evaluations = params.get::<u32>("local_search_evaluations")?;
+
+        return Ok(run(instance, max_fitness_evaluations, reference_set_size, diverse_set_size, local_search_evaluations, rng));
+    }
+}
+
+/// Ejecuta la busqueda dispersa consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    reference_set_size: usize,
+    diverse_set_size: usize,
+    local_search_evaluations: u32,
+    rng: &mut StdRng
+) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, reference_set_size, diverse_set_size, local_search_evaluations, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta la busqueda dispersa descontando las evaluaciones del fitness del evaluador dado
+///
+/// En cada iteracion combinamos todas las parejas del conjunto de referencia en las que hay al
+/// menos una solucion que ha entrado en la iteracion anterior, y mejoramos cada combinacion con
+/// busqueda local. Una combinacion entra en el conjunto de referencia si mejora a la peor
+/// solucion y no esta ya en el conjunto. Cuando ninguna combinacion entra, regeneramos la mitad
+/// diversa del conjunto de referencia. En la evolucion del fitness guardamos el fitness de la
+/// mejor solucion del conjunto de referencia tras cada iteracion
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    reference_set_size: usize,
+    diverse_set_size: usize,
+    local_search_evaluations: u32,
+    rng: &mut StdRng
+) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let diverse_set = generate_diverse_set(instance, evaluator, diverse_set_size.max(reference_set_size), local_search_evaluations, rng);
+    let mut reference_set = build_reference_set(vec![], diverse_set, reference_set_size);
+
+    // Ni siquiera hemos podido evaluar una solucion
+    if reference_set.is_empty(){
+        let mut solution = Solution::generate_random_solution(instance, rng);
+        if solution.is_valid() == false{
+            solution.repair_solution(rng);
+        }
+        return (solution, fitness_evolution);
+    }
+
+    // Soluciones del conjunto de referencia que han entrado en la ultima iteracion
+    let mut new_solutions = vec![true; reference_set.len()];
+
+    while evaluator.budget_left() == true{
+        // Generacion de subconjuntos: las parejas con alguna solucion nueva. Copiamos las
+        // soluciones porque el conjunto de referencia cambia segun entran las combinaciones
+        let mut pairs = vec![];
+        for first in 0..reference_set.len(){
+            for second in (first + 1)..reference_set.len(){
+                if new_solutions[first] == true || new_solutions[second] == true{
+                    pairs.push((reference_set[first].clone(), reference_set[second].clone()));
+                }
+            }
+        }
+        new_solutions = vec![false; reference_set.len()];
+
+        for ((first, first_fitness), (second, second_fitness)) in pairs{
+            let combined = combine(&first, first_fitness, &second, second_fitness, rng);
+            let (improved, improved_fitness) = match improve(evaluator, &combined, local_search_evaluations, rng){
+                Some(improved) => improved,
+                None => break,
+            };
+
+            // Actualizacion del conjunto de referencia
+            let worst_index = (0..reference_set.len())
+                .max_by(|first, second| reference_set[*first].1.partial_cmp(&reference_set[*second].1).unwrap())
+                .unwrap();
+            let is_duplicated = reference_set.iter().any(|(solution, _)| *solution == improved);
+            if improved_fitness < reference_set[worst_index].1 && is_duplicated == false{
+                reference_set[worst_index] = (improved, improved_fitness);
+                new_solutions[worst_index] = true;
+            }
+        }
+
+        let best_fitness = reference_set.iter().map(|(_, fitness)| *fitness).fold(f64::INFINITY, f64::min);
+        fitness_evolution.add_iteration(best_fitness);
+
+        // Si no ha entrado ninguna solucion, mantenemos la mitad de calidad y regeneramos el resto
+        if new_solutions.contains(&true) == false{
+            reference_set.sort_by(|first, second| first.1.partial_cmp(&second.1).unwrap());
+            // Siempre mantenemos al menos la mejor solucion
+            reference_set.truncate((reference_set_size / 2).max(1));
+
+            let diverse_set = generate_diverse_set(instance, evaluator, diverse_set_size, local_search_evaluations, rng);
+            let kept_solutions = reference_set.len();
+            reference_set = build_reference_set(reference_set, diverse_set, reference_set_size);
+
+            new_solutions = vec![false; reference_set.len()];
+            for new in new_solutions.iter_mut().skip(kept_solutions){
+                *new = true;
+            }
+        }
+    }
+
+    let (best_solution, _) = reference_set.into_iter()
+        .min_by(|first, second| first.1.partial_cmp(&second.1).unwrap())
+        .unwrap();
+    return (best_solution, fitness_evolution);
+}
+
+/// Genera hasta diverse_set_size soluciones aleatorias mejoradas con busqueda local. Devuelve
+/// menos si se agotan las evaluaciones
+fn generate_diverse_set(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
+    diverse_set_size: usize,
+    local_search_evaluations: u32,
+    rng: &mut StdRng
+) -> Vec<(Solution, f64)>{
+    let mut diverse_set = vec![];
+
+    for _ in 0..diverse_set_size{
+        let mut solution = Solution::generate_random_solution(instance, rng);
+        if solution.is_valid() == false{
+            solution.repair_solution(rng);
+        }
+
+        match improve(evaluator, &solution, local_search_evaluations, rng){
+            Some(improved) => diverse_set.push(improved),
+            None => break,
+        }
+    }
+
+    return diverse_set;
+}
+
+/// Completa el conjunto de referencia hasta reference_set_size soluciones con las del conjunto
+/// diverso: primero hasta la mitad con las de mejor fitness, y despues con las mas alejadas del
+/// conjunto de referencia (las que maximizan la minima distancia a sus soluciones). No añade
+/// soluciones que ya esten en el conjunto de referencia
+fn build_reference_set(mut reference_set: Vec<(Solution, f64)>, mut diverse_set: Vec<(Solution, f64)>, reference_set_size: usize) -> Vec<(Solution, f64)>{
+    let min_distance = |reference_set: &Vec<(Solution, f64)>, solution: &Solution| -> usize{
+        return reference_set.iter().map(|(other, _)| other.partition_distance(solution)).min().unwrap_or(usize::MAX);
+    };
+
+    // Soluciones de calidad
+    diverse_set.sort_by(|first, second| first.1.partial_cmp(&second.1).unwrap());
+    let mut remaining = vec![];
+    for candidate in diverse_set{
+        if reference_set.len() < reference_set_size / 2 && min_distance(&reference_set, &candidate.0) > 0{
+            reference_set.push(candidate);
+        }else{
+            remaining.push(candidate);
+        }
+    }
+
+    // Soluciones diversas
+    while reference_set.len() < reference_set_size && remaining.is_empty() == false{
+        let distances: Vec<usize> = remaining.iter().map(|(solution, _)| min_distance(&reference_set, solution)).collect();
+        let farthest = (0..remaining.len()).max_by_key(|index| distances[*index]).unwrap();
+        if distances[farthest] == 0{
+            break;
+        }
+
+        reference_set.push(remaining.swap_remove(farthest));
+    }
+
+    return reference_set;
+}
+
+/// Metodo de combinacion que tiene en cuenta las etiquetas de los clusters. Renombramos los
+/// clusters de second para que coincidan con los de first, y en cada punto en el que difieren
+/// tomamos la asignacion de uno de los dos padres con probabilidad proporcional a la calidad del
+/// padre (inversamente proporcional a su fitness). Reparamos la solucion si queda algun cluster
+/// vacio
+fn combine(first: &Solution, first_fitness: f64, second: &Solution, second_fitness: f64, rng: &mut StdRng) -> Solution{
+    let second_indexes = second.relabeled_like(first).get_cluster_indexes();
+    let mut cluster_indexes = first.get_cluster_indexes();
+
+    let total_fitness = first_fitness + second_fitness;
+    let first_probability = if total_fitness > 0.0 {second_fitness / total_fitness} else {0.5};
+
+    for index in 0..cluster_indexes.len(){
+        if cluster_indexes[index] != second_indexes[index] && rng.gen::<f64>() >= first_probability{
+            cluster_indexes[index] = second_indexes[index];
+        }
+    }
+
+    let mut combined = Solution::new(cluster_indexes, first.get_instance());
+    if combined.is_valid() == false{
+        combined.repair_solution(rng);
+    }
+
+    return combined;
+}
+
+/// Metodo de mejora: busqueda local con como mucho local_search_evaluations evaluaciones del
+/// fitness. Devuelve None si no quedan evaluaciones para evaluar la solucion
+fn improve(evaluator: &Evaluator, solution: &Solution, local_search_evaluations: u32, rng: &mut StdRng) -> Option<(Solution, f64)>{
+    let local_evaluator = evaluator.sub_evaluator(local_search_evaluations);
+    let (improved, _) = local_search::run_from_init_sol(&local_evaluator, solution, &mut ImproveOnly, rng);
+    evaluator.absorb(&local_evaluator);
+
+    if improved.is_fitness_cached() == false{
+        return None;
+    }
+    let improved_fitness = improved.fitness();
+
+    return Some((improved, improved_fitness));
+}
@@ -1,94 +1,168 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::arg_parser::SearchType;
-use crate::utils;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::{AcceptanceCriterion, Decision, parse_acceptance_criterion};
 
 use rand::rngs::StdRng;
-use rand::Rng;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::str::FromStr;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Ejecuta y muestra los resultados de la busqueda
-/// Esto para no incluir todo este codigo en el cuerpo de la funcion main
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, rng: &mut StdRng){
-    // Parametros iniciales del algoritmo
-    let max_fitness_evaluations = 100000;
-    let mu = 0.3;
-    let final_tmp = 0.001;
-    let max_neighbours: i32 = (10.0 * data_points.len() as f64) as i32;
-    let max_successes: i32 = (0.1 * max_neighbours as f64) as i32;
+/// Enfriamiento simulado partiendo de una solucion aleatoria. Por defecto, usamos el esquema de
+/// enfriamiento de Cauchy modificado y el criterio de aceptacion de Metropolis
+pub struct SimulatedAnnealing;
+
+impl Metaheuristic for SimulatedAnnealing{
+    fn name(&self) -> &'static str{
+        return "simulated_annealing";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Enfriamiento simulado";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("mu", "Fija la temperatura inicial a partir del fitness de la solucion inicial", Some("0.3")),
+            ParameterSpec::new("final_tmp", "Temperatura final", Some("0.001")),
+            ParameterSpec::new("max_neighbours", "Vecinos generados por enfriamiento (por defecto, 10 * numero de puntos)", None),
+            ParameterSpec::new("max_successes", "Vecinos aceptados por enfriamiento (por defecto, 0.1 * max_neighbours)", None),
+            ParameterSpec::new("cooling_schedule", "Esquema de enfriamiento: cauchy, geometric, linear, logarithmic o reheating", Some("cauchy")),
+            ParameterSpec::new("acceptance", "Criterio de aceptacion de los vecinos, que recibe la temperatura actual", Some("metropolis")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let mu = params.get::<f64>("mu")?;
+        let final_tmp = params.get::<f64>("final_tmp")?;
+        let max_neighbours = params.get_optional::<i32>("max_neighbours")?.unwrap_or((10.0 * instance.number_of_points() as f64) as i32);
+        let max_successes = params.get_optional::<i32>("max_successes")?.unwrap_or((0.1 * max_neighbours as f64) as i32);
+        let cooling_schedule = params.get::<CoolingSchedule>("cooling_schedule")?;
+        let mut acceptance = parse_acceptance_criterion(&params.get::<String>("acceptance")?)?;
+
+        return Ok(run(instance, max_fitness_evaluations, mu, final_tmp, max_neighbours, max_successes, cooling_schedule, acceptance.as_mut(), rng));
+    }
+}
+
+/// Ejecuta el enfriamiento simulado partiendo de una solucion inicial aleatoria, consumiendo como
+/// mucho max_fitness_evaluations evaluaciones del fitness
+/// La temperatura inicial se calcula a partir del fitness de la solucion inicial y de mu
+#[allow(clippy::too_many_arguments)]
+#[allow(non_snake_case)]  // M es la notacion de las transparencias
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    mu: f64,
+    final_tmp: f64,
+    max_neighbours: i32,
+    max_successes: i32,
+    cooling_schedule: CoolingSchedule,
+    acceptance: &mut dyn AcceptanceCriterion,
+    rng: &mut StdRng
+) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
     let M: f64 = max_fitness_evaluations as f64 / max_neighbours as f64;
 
+    let before = Instant::now();
+
     // Solucion inicial aleatoria. La generamos aqui, porque es necesaria para establecer la
     // temperatura inicial
-    let init_solution = Solution::generate_random_solution(data_points, constraints, program_arguments.get_number_of_clusters(), rng);
+    // Esta evaluacion del fitness cuenta dentro del maximo de evaluaciones
+    let init_solution = Solution::generate_random_solution(instance, rng);
+    let init_fitness = match evaluator.fitness(&init_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial. Devolvemos la solucion
+        // inicial reparada, sin evaluar
+        None => {
+            let mut solution = init_solution;
+            if solution.is_valid() == false{
+                solution.repair_solution(rng);
+            }
+            return RunResult::new(solution, evaluator.consumed(), before.elapsed(), FitnessEvolution::new());
+        }
+    };
 
     // Con ello, computamos la temperatura inicial
-    let initial_tmp: f64 = (mu * init_solution.fitness()) / (-mu.ln());
+    let initial_tmp: f64 = (mu * init_fitness) / (-mu.ln());
 
     // Comprobacion de seguridad
-    assert_eq!(final_tmp < initial_tmp, true, "La temperatura final es mayor que la temperatura inicial");
+    assert!(final_tmp < initial_tmp, "La temperatura final es mayor que la temperatura inicial");
 
-    let before = Instant::now();
-    let (solucion_local, fitness_evolution) = run(
-        max_fitness_evaluations,
+    let (solution, fitness_evolution) = run_from_init_sol(
+        &evaluator,
         &init_solution,
         initial_tmp,
         final_tmp,
         M,
         max_neighbours,
         max_successes,
+        cooling_schedule,
+        acceptance,
         rng
     );
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Mostramos los resultados
-    println!("==> Enfriamiento simulado");
-    println!("La distancia global instracluster de la solucion es: {}", solucion_local.global_cluster_mean_distance());
-    println!("El numero de restricciones violadas es: {}", solucion_local.infeasibility());
-    println!("El valor de fitness es: {}", solucion_local.fitness());
-    println!("El valor de lambda es: {}", solucion_local.get_lambda());
-    println!("Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(SearchType::LocalSearch)));
-    println!("");
+    let duration = before.elapsed();
 
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
-/// Ejecuta la metaheuristica de busqueda local y devuelve la solucion encontrada
-/// Parte de una solucion inicial aleatoria
+/// Ejecuta el enfriamiento simulado a partir de la solucion inicial dada, y devuelve la mejor
+/// solucion encontrada
 /// Funcion publica porque se usa como base para iterative local searhc
-pub fn run<'a, 'b>(
-    max_fitness_evaluations: i32,
-    init_sol: &Solution<'a, 'b>,
+///
+/// M es el numero de enfriamientos previstos, que los esquemas de enfriamiento usan para llegar a
+/// la temperatura final. Paramos al bajar de la temperatura final, al agotar las evaluaciones o
+/// cuando no aceptamos ningun vecino en un enfriamiento. Con recalentamiento, en los dos ultimos
+/// casos recalentamos a la mitad de la temperatura del recalentamiento anterior, hasta que esta
+/// no supere la temperatura final
+///
+/// El criterio de aceptacion recibe la temperatura actual en cada enfriamiento. Si pide reiniciar,
+/// continuamos desde una solucion aleatoria
+#[allow(clippy::too_many_arguments)]
+#[allow(non_snake_case)]  // M es la notacion de las transparencias
+pub fn run_from_init_sol(
+    evaluator: &Evaluator,
+    init_sol: &Solution,
     initial_tmp: f64,
     final_tmp: f64,
     M: f64,
     max_neighbours: i32,
     max_successes: i32,
+    cooling_schedule: CoolingSchedule,
+    acceptance: &mut dyn AcceptanceCriterion,
     rng: &mut StdRng
-) -> (Solution<'a, 'b>, FitnessEvolution){
+) -> (Solution, FitnessEvolution){
 
     // Valores iniciales para empezar a iterar
-    let mut current_evaluations = 0;
     let mut current_tmp = initial_tmp;
     let mut current_solution = init_sol.clone();
     let mut best_solution = current_solution.clone();
-    let (mut best_fitness, ev_cons) = best_solution.fitness_and_consumed();
+    let mut fitness_evolution = FitnessEvolution::new();
+    let mut best_fitness = match evaluator.fitness(&best_solution){
+        Some(fitness) => fitness,
 
-    // La solucion inicial deberia haber consumido una iteracion del fitness
-    current_evaluations += ev_cons as i32;
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (best_solution, fitness_evolution),
+    };
 
-    // Necesitamos el valor de beta para el enfriamiento
-    let beta: f64 = (initial_tmp - final_tmp) / (M * initial_tmp * final_tmp);
+    // Numero de enfriamientos realizados, que necesitan algunos esquemas de enfriamiento
+    let mut cooling_index = 0;
 
-    // Para llevar la cuenta de la evolucion del fitness
-    let mut fitness_evolution = FitnessEvolution::new();
+    // Temperatura a la que recalentamos la proxima vez que no aceptemos ningun vecino, si el
+    // esquema de enfriamiento recalienta
+    let mut reheat_tmp = initial_tmp / 2.0;
 
-    while current_evaluations < max_fitness_evaluations && current_tmp >= final_tmp{
+    while evaluator.budget_left() == true && current_tmp >= final_tmp{
+
+        acceptance.set_temperature(current_tmp);
 
         // Bucle interno
         // Solo generamos max_neighbours a lo sumo. Tambien paramos cuando se ha alcanzado un
@@ -97,18 +171,22 @@ pub fn run<'a, 'b>(
         for _ in 0..max_neighbours{
             let current_neighbour = &current_solution.clone().one_random_neighbour(rng);
 
-            // Calculamos el delta del fitness llevando en cuenta las evaluaciones de fitness
-            let (current_solution_fitness, first_ev_cons) = current_solution.fitness_and_consumed();
-            let (current_neighbour_fitness, second_ev_cons) = current_neighbour.fitness_and_consumed();
-            let delta_fitness = current_solution_fitness - current_neighbour_fitness;
-
-            // Añadimos las evaluaciones del fitness
-            current_evaluations += first_ev_cons as i32 + second_ev_cons as i32;
-
-            if delta_fitness < 0.0 && rng.gen::<f64>() > (-delta_fitness * current_tmp){
-                // La solucion es peor y ademas, la probabilidad dependiente de la temperatura de
-                // aceptar soluciones peores ha fallado
-                continue;
+            // Calculamos el delta del fitness. Si el evaluador no puede evaluar alguna de las dos
+            // soluciones, hemos agotado las evaluaciones
+            let (current_solution_fitness, current_neighbour_fitness) = match (evaluator.fitness(&current_solution), evaluator.fitness(current_neighbour)){
+                (Some(current_solution_fitness), Some(current_neighbour_fitness)) => (current_solution_fitness, current_neighbour_fitness),
+                _ => break,
+            };
+            match acceptance.decide(current_solution_fitness, current_neighbour_fitness, rng){
+                Decision::Accept => (),
+                Decision::Reject => continue,
+                Decision::Restart => {
+                    current_solution = Solution::generate_random_solution(current_solution.get_instance(), rng);
+                    if current_solution.is_valid() == false{
+                        current_solution.repair_solution(rng);
+                    }
+                    continue;
+                },
             }
 
             // Hemos aceptado la solucion, asi que hacemos el cambio de solucion actual y llevamos
@@ -118,13 +196,13 @@ pub fn run<'a, 'b>(
 
             // Comprobamos si tenemos mejor coste que la mejor solucion encontrada hasta el
             // momento. Tenemos los valores de fitness ya calculados
-            if current_solution_fitness < best_fitness{
-                best_fitness = current_solution_fitness;
+            if current_neighbour_fitness < best_fitness{
+                best_fitness = current_neighbour_fitness;
                 best_solution = current_solution.clone();
             }
 
             // Llevamos la cuenta de como evoluciona el fitness
-            fitness_evolution.add_iteration(current_solution_fitness);
+            fitness_evolution.add_iteration(current_neighbour_fitness);
 
             // Si hemos alcanzado el numero maximo de exitos, salimos del bucle interno
             if current_successes >= max_successes{
@@ -132,23 +210,142 @@ pub fn run<'a, 'b>(
             }
 
             // Si hemos consumido las evaluaciones maximas en el bucle interno, debemos salir
-            if current_evaluations >= max_fitness_evaluations{
+            if evaluator.budget_left() == false{
                 break;
             }
         }
 
-        // Computamos el siguiente valor de la temperatura
-        let old_tmp = current_tmp;
-        current_tmp = current_tmp / (1.0 + beta * current_tmp);
-
-        // Si se obtuvieron 0 exitos en el bucle interno, paramos de iterar
+        // Si se obtuvieron 0 exitos en el bucle interno, paramos de iterar, salvo que el esquema
+        // de enfriamiento recaliente. En ese caso, cada recalentamiento es a la mitad de
+        // temperatura que el anterior, y paramos cuando no superamos la temperatura final
         if current_successes == 0{
-            break;
+            if cooling_schedule.reheats() == false || reheat_tmp < final_tmp{
+                break;
+            }
+
+            current_tmp = reheat_tmp;
+            reheat_tmp /= 2.0;
+            continue;
         }
 
+        // Computamos el siguiente valor de la temperatura
+        let old_tmp = current_tmp;
+        cooling_index += 1;
+        current_tmp = cooling_schedule.next_temperature(current_tmp, initial_tmp, final_tmp, M, cooling_index);
+
         // Comprobacion de que la temperatura va disminuyendo
         debug_assert!(current_tmp < old_tmp, "La temperatura debe descender monotamente");
+
+        // Al llegar a la temperatura final tambien recalentamos, si el esquema lo permite y
+        // quedan evaluaciones del fitness
+        if current_tmp < final_tmp && cooling_schedule.reheats() == true && reheat_tmp >= final_tmp{
+            current_tmp = reheat_tmp;
+            reheat_tmp /= 2.0;
+        }
     }
 
     return (best_solution.clone(), fitness_evolution);
 }
+
+/// Esquemas de enfriamiento disponibles para el enfriamiento simulado
+/// Todos parten de la temperatura inicial y, salvo el logaritmico, llegan a la temperatura final
+/// tras M enfriamientos, de forma que los resultados sean comparables
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoolingSchedule{
+    /// Cauchy modificado: T_{k+1} = T_k / (1 + beta * T_k)
+    CauchyModified,
+
+    /// Geometrico: T_{k+1} = alpha * T_k
+    Geometric,
+
+    /// Lineal: T_k = T_0 - k * (T_0 - T_f) / M
+    Linear,
+
+    /// Logaritmico: T_k = T_0 / (1 + ln(1 + k)). Enfria muy lentamente, por lo que normalmente
+    /// paramos al agotar las evaluaciones antes de llegar a la temperatura final
+    Logarithmic,
+
+    /// Geometrico, pero cuando en un enfriamiento no aceptamos ningun vecino o llegamos a la
+    /// temperatura final, recalentamos en vez de parar la busqueda
+    AdaptiveReheating,
+}
+
+impl CoolingSchedule{
+    /// Temperatura tras el enfriamiento numero cooling_index (empezando en 1), siendo current_tmp
+    /// la temperatura actual
+    #[allow(non_snake_case)]  // M es la notacion de las transparencias
+    pub fn next_temperature(&self, current_tmp: f64, initial_tmp: f64, final_tmp: f64, M: f64, cooling_index: usize) -> f64{
+        match self{
+            CoolingSchedule::CauchyModified => {
+                let beta: f64 = (initial_tmp - final_tmp) / (M * initial_tmp * final_tmp);
+                return current_tmp / (1.0 + beta * current_tmp);
+            },
+            CoolingSchedule::Geometric | CoolingSchedule::AdaptiveReheating => {
+                let alpha = (final_tmp / initial_tmp).powf(1.0 / M);
+                return alpha * current_tmp;
+            },
+            CoolingSchedule::Linear => {
+                return initial_tmp - cooling_index as f64 * (initial_tmp - final_tmp) / M;
+            },
+            CoolingSchedule::Logarithmic => {
+                return initial_tmp / (1.0 + (1.0 + cooling_index as f64).ln());
+            },
+        }
+    }
+
+    /// Indica si el esquema recalienta cuando no se acepta ningun vecino en un enfriamiento
+    pub fn reheats(&self) -> bool{
+        return *self == CoolingSchedule::AdaptiveReheating;
+    }
+}
+
+impl FromStr for CoolingSchedule{
+    type Err = SimpleError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        match name{
+            "cauchy" => return Ok(CoolingSchedule::CauchyModified),
+            "geometric" => return Ok(CoolingSchedule::Geometric),
+            "linear" => return Ok(CoolingSchedule::Linear),
+            "logarithmic" => return Ok(CoolingSchedule::Logarithmic),
+            "reheating" => return Ok(CoolingSchedule::AdaptiveReheating),
+            _ => return Err(SimpleError::new(format!("el esquema de enfriamiento {} no existe", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::simulated_annealing::CoolingSchedule;
+
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    // Todos los esquemas enfrian de forma monotona, y los que deben llegar a la temperatura final
+    // tras M enfriamientos lo hacen
+    #[allow(non_snake_case)]  // M es la notacion de las transparencias
+    fn test_cooling_schedules(){
+        let initial_tmp = 10.0;
+        let final_tmp = 0.001;
+        let M = 100.0;
+
+        for (name, reaches_final_tmp) in [("cauchy", true), ("geometric", true), ("linear", true), ("logarithmic", false), ("reheating", true)]{
+            let cooling_schedule = name.parse::<CoolingSchedule>().unwrap();
+
+            let mut current_tmp = initial_tmp;
+            for cooling_index in 1..=M as usize{
+                let next_tmp = cooling_schedule.next_temperature(current_tmp, initial_tmp, final_tmp, M, cooling_index);
+                assert!(next_tmp < current_tmp, "El esquema {} no enfria", name);
+                current_tmp = next_tmp;
+            }
+
+            if reaches_final_tmp == true{
+                assert_approx_eq!(current_tmp, final_tmp, 1e-9);
+            }else{
+                assert!(current_tmp > final_tmp);
+            }
+        }
+
+        assert!("no_existe".parse::<CoolingSchedule>().is_err());
+    }
+}
@@ -1,163 +1,236 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
+use crate::problem_datatypes::ProblemInstance;
 use crate::fitness_evolution::FitnessEvolution;
-use crate::arg_parser::ProgramParameters;
-use crate::problem_datatypes::population::Population;
-use crate::fitness_evaluation_result::FitnessEvaluationResult;
-use crate::utils;
-use crate::arg_parser::SearchType;
+use crate::diversity_evolution::DiversityMonitor;
+use crate::problem_datatypes::population::{Population, CrossoverOperator, SelectionOperator, ReplacementStrategy};
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
 
 use rand::rngs::StdRng;
+use std::error::Error;
 use std::time::Instant;
+use std::sync::Arc;
 
-/// Ejecuta y muestra los resultados de la busqueda genetica con modelo estacionario
+/// Algoritmo genetico con modelo estacionario: en cada iteracion cruzamos dos padres, y los hijos
+/// compiten con los peores individuos de la poblacion
 /// cross_uniform == true ==> usamos cruce uniforme
 /// cross_uniform == false ==> usamos cruce de segmento fijo
-pub fn run_and_show_results(data_points: &DataPoints, constraints: &Constraints, program_arguments: ProgramParameters, cross_uniform: bool, rng: &mut StdRng){
-    // Parametros del algoritmo
-    let max_fitness_evaluations = 100000;
-    let population_size = 50;
+/// El parametro crossover permite usar cualquier otro operador de cruce, y el parametro
+/// replacement cualquier otra estrategia de reemplazo
+pub struct SteadyGenetic{
+    cross_uniform: bool,
+}
 
-    // El tamaño de un gen sera el tamaño de la poblacion de datos a asignar a clusters
-    let gen_size = data_points.len();
-    let mutation_probability_per_gen = 0.1 / gen_size as f64;
+impl SteadyGenetic{
+    pub fn new(cross_uniform: bool) -> Self{
+        return Self{cross_uniform};
+    }
+}
 
-    let before = Instant::now();
-    let (solucion, fitness_evolution) = run(&data_points, &constraints, program_arguments.get_number_of_clusters(), max_fitness_evaluations, rng, population_size, mutation_probability_per_gen, cross_uniform);
-    let after = Instant::now();
-    let duration = after.duration_since(before);
-    let duration_numeric = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
-
-    // Para la salida de resultados formateada
-    let search_type;
-    if cross_uniform == true{
-        search_type = SearchType::GenerationalGeneticUniform;
-    }else{
-        search_type = SearchType::GenerationalGeneticSegment;
+impl Metaheuristic for SteadyGenetic{
+    fn name(&self) -> &'static str{
+        if self.cross_uniform == true{
+            return "gsuniform";
+        }
+        return "gssegment";
+    }
+
+    fn description(&self) -> &'static str{
+        if self.cross_uniform == true{
+            return "Busqueda genetica, modelo estacionario, cruce uniforme";
+        }
+        return "Busqueda genetica, modelo estacionario, cruce de segmento fijo";
     }
 
-    // Mostramos los resultados
-    println!("==> Busqueda genetica, modelo estatico, cross_uniform: {}", cross_uniform);
-    println!("\t--> La distancia global instracluster de la solucion es: {}", solucion.global_cluster_mean_distance());
-    println!("\t--> El numero de restricciones violadas es: {}", solucion.infeasibility());
-    println!("\t--> El valor de fitness es: {}", solucion.fitness());
-    println!("\t--> El valor de lambda es: {}", solucion.get_lambda());
-    println!("\t--> Tiempo transcurrido (segundos): {}", duration_numeric);
-    println!("\t--> Salvado del fitness: {:?}", fitness_evolution.save_as_numpy_file(&utils::generate_file_name(search_type)));
-    println!("");
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("population_size", "Tamaño de la poblacion", Some("50")),
+            ParameterSpec::new("mutation_probability_per_gen", "Probabilidad de mutar cada gen de los hijos (por defecto, 0.1 / numero de puntos)", None),
+            ParameterSpec::new("crossover", "Operador de cruce: uniform, segment, aligned_uniform, aligned_segment o group (por defecto, el del nombre del algoritmo)", None),
+            ParameterSpec::new("selection", "Operador de seleccion: binary_tournament, tournament, linear_rank, roulette o sus", Some("binary_tournament")),
+            ParameterSpec::new("tournament_size", "Candidatos de cada torneo en la seleccion tournament", Some("3")),
+            ParameterSpec::new("selection_pressure", "Presion selectiva, entre 1 y 2, de la seleccion linear_rank", Some("1.5")),
+            ParameterSpec::new("replacement", "Estrategia de reemplazo: worst, random, oldest, crowding, restricted_tournament o worst_without_duplicates", Some("worst")),
+            ParameterSpec::new("replacement_window", "Individuos con los que se compara cada hijo en el reemplazo restricted_tournament", Some("5")),
+            ParameterSpec::new("number_of_workers", "Hilos con los que se evalua la poblacion", Some("1")),
+            ParameterSpec::new("diversity_file", "Fichero csv en el que guardamos la diversidad de la poblacion cada population_size / 2 iteraciones (por defecto, no se guarda)", None),
+            ParameterSpec::new("restart_entropy", "Reiniciamos la poblacion cuando la entropia media de sus genes, entre 0 y 1, baja de este valor (por defecto, no se reinicia)", None),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let population_size = params.get::<i32>("population_size")?;
+        let number_of_workers = params.get::<usize>("number_of_workers")?;
+        let default_crossover = if self.cross_uniform == true {CrossoverOperator::Uniform} else {CrossoverOperator::Segment};
+        let crossover = params.get_optional::<CrossoverOperator>("crossover")?.unwrap_or(default_crossover);
+        let selection = SelectionOperator::from_name(
+            &params.get::<String>("selection")?,
+            params.get::<usize>("tournament_size")?,
+            params.get::<f64>("selection_pressure")?
+        )?;
+        let replacement = ReplacementStrategy::from_name(&params.get::<String>("replacement")?, params.get::<usize>("replacement_window")?)?;
+
+        // El tamaño de un gen sera el tamaño de la poblacion de datos a asignar a clusters
+        let gen_size = instance.number_of_points();
+        let mutation_probability_per_gen = params.get_optional::<f64>("mutation_probability_per_gen")?.unwrap_or(0.1 / gen_size as f64);
+
+        // Seguimiento de la diversidad de la poblacion
+        let diversity_file = params.get_optional::<String>("diversity_file")?;
+        let mut diversity_monitor = DiversityMonitor::new(diversity_file.is_some(), params.get_optional::<f64>("restart_entropy")?);
+
+        let result = run(instance, max_fitness_evaluations, rng, number_of_workers, population_size, mutation_probability_per_gen, crossover, selection, replacement, &mut diversity_monitor);
+
+        if let Some(file_path) = diversity_file{
+            diversity_monitor.get_evolution().save_as_csv(&file_path)?;
+        }
+        return Ok(result);
+    }
+}
+
+/// Ejecuta la busqueda genetica consumiendo como mucho max_fitness_evaluations evaluaciones del
+/// fitness, y devuelve el resultado de la busqueda
+/// El seguimiento de la diversidad se hace cada population_size / 2 iteraciones, en las que se
+/// generan tantos hijos como en una generacion del genetico generacional (ver DiversityMonitor)
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    instance: &Arc<ProblemInstance>,
+    max_fitness_evaluations: u32,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    population_size: i32,
+    mutation_probability_per_gen: f64,
+    crossover: CrossoverOperator,
+    selection: SelectionOperator,
+    replacement: ReplacementStrategy,
+    diversity_monitor: &mut DiversityMonitor
+    ) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, rng, number_of_workers, population_size, mutation_probability_per_gen, crossover, selection, replacement, diversity_monitor);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
 }
 
 /// Ejecuta la busqueda genetica y devuelve la solucion encontrada
-fn run<'a, 'b>(
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
-    max_fitness_evaluations: i32,
+#[allow(clippy::too_many_arguments)]
+pub fn run_with_evaluator(
+    instance: &Arc<ProblemInstance>,
+    evaluator: &Evaluator,
     rng: &mut StdRng,
+    number_of_workers: usize,
     population_size: i32,
     mutation_probability_per_gen: f64,
-    cross_uniform: bool // Si es false, significa que usamos cruce de segmento fijo
-                        // Si es true, significa que usamos cruce uniforme
+    crossover: CrossoverOperator,
+    selection: SelectionOperator,
+    replacement: ReplacementStrategy,
+    diversity_monitor: &mut DiversityMonitor
     )
-    -> (Solution<'a, 'b>, FitnessEvolution){
+    -> (Solution, FitnessEvolution){
 
     // Llevamos la cuenta del fitness del mejor individuo en cada iteracion sobre la poblacion
     let mut fitness_evolution = FitnessEvolution::new();
 
-    // Poblacion inicial aleatoria
-    let mut current_population = Population::new_random_population(data_points, constraints, number_of_clusters, population_size, rng);
-
-    // Realizamos las iteraciones pertinentes
-    let mut consumed_fitness_evaluations = 0;
-    while consumed_fitness_evaluations < max_fitness_evaluations{
-
-        // Las evaluaciones del fitness que se consumen en este ciclo
-        let mut iteration_fitness_evaluations = 0;
-
-        // Tomamos dos individuos de la poblacion por torneo binario
-        // Si la poblacion anterior no esta evaluada, puede consumir hasta cuatro evaluaciones del
-        // fitness (2 x 2 candidatos compitiendo)
-        let selection_population_result = current_population.select_population_binary_tournament(2, rng);
-        let selection_population = selection_population_result.get_result();
-        iteration_fitness_evaluations += selection_population_result.get_iterations_consumed();
-        debug_assert!(selection_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
-        debug_assert!(
-            selection_population_result.get_iterations_consumed() <= 4,
-            "En la seleccion de dos individos por torneo binario debemos consumir, como mucho, 4 evaluaciones. Hemos consumido {} evaluaciones",
-            selection_population_result.get_iterations_consumed()
-        );
-
-        // Cruzamos los dos individuos que hemos tomado de la poblacion, generando otros dos
-        // individuos. Esto no deberia provocar evaluaciones del fitness
-        let crossover_probability = 1.00; // Cruzamos forzosamente a los individuos
-        let crossed_population_result;
-        if cross_uniform == true{
-            crossed_population_result = selection_population.cross_population_uniform(crossover_probability, rng);
-        }else{
-            crossed_population_result = selection_population.cross_population_segment(crossover_probability, rng);
-        }
+    // Poblacion inicial aleatoria. La evaluamos entera, pues cada iteracion parte de una
+    // poblacion evaluada y no todas las estrategias de reemplazo evaluan a toda la poblacion
+    let mut current_population = Population::new_random_population(instance, population_size, rng);
+    current_population.evaluate_all_individuals(number_of_workers, evaluator);
 
-        let crossed_population = crossed_population_result.get_result();
-        iteration_fitness_evaluations += crossed_population_result.get_iterations_consumed();
-        debug_assert!(crossed_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
-        debug_assert!(
-            crossed_population_result.get_iterations_consumed() == 0,
-            "El cruce no deberia consumir iteraciones, hemos consumidos {} iteraciones",
-            crossed_population_result.get_iterations_consumed()
-        );
-        debug_assert!(crossed_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
-
-        // A partir de los dos hijos cruzados, mutamos en caso de que se escoja aleatoriamente hacerlo
-        // Esta operacion no consume iteraciones, por lo que no hacemos la suma
-        let mutated_population = crossed_population.mutate_population_given_prob(mutation_probability_per_gen, rng);
-        debug_assert!(mutated_population.population_size() == 2 as usize, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
-        debug_assert!(mutated_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
-
-        // Los dos hijos, cruzados y en algunos casos mutados, compiten contra los peores elementos
-        // de la poblacion original para pasar a ser parte de ella
-        // Esta operacion consume evaluaciones del fitness. Salvo en la primera iteracion, deberia
-        // consumir como mucho dos evaluaciones (en los dos nuevos individuos que entran a competir)
-        let final_population_result = current_population.compete_with_new_individuals(&mutated_population);
-        let final_population = final_population_result.get_result();
-        iteration_fitness_evaluations += final_population_result.get_iterations_consumed();
-        debug_assert!(
-            final_population_result.get_iterations_consumed() >= 2,
-            "La competicion de los dos nuevos individuos contra toda la poblacion original debe provocar al menos dos evaluaciones del fitness, hemos obtenido {} evaluaciones",
-            final_population_result.get_iterations_consumed()
-        );
-
-        // Evaluamnos esta poblacion final. No deberia consumir ninguna iteracion, pues en la
-        // competicion de los dos nuevos individuos, ya deberiamos tener a toda la poblacion
-        // evaluada. Hacemos esto por seguridad, pues al tener todos los fitness cacheados, no
-        // deberia consumir demasiado tiempo
-        let evaluate_poblation_result = final_population.evaluate_all_individuals();
-        iteration_fitness_evaluations += evaluate_poblation_result.get_iterations_consumed();
-        debug_assert!(
-            evaluate_poblation_result.get_iterations_consumed() == 0,
-            "La poblacion deberia estar evaluada tras la competicion, pero estamos consumiendo {} evaluaciones",
-            evaluate_poblation_result.get_iterations_consumed()
-        );
-
-        // Realizamos el cambio de poblacion
-        // Hacemos una comprobacion de seguridad sobne la poblacion, tras las competicion de los
-        // dos nuevos individuos
-        current_population = final_population.clone();
-        debug_assert!(current_population.population_size() == population_size as usize, "La poblacion final tras la iteracion tiene {} elementos", current_population.population_size());
+    // Iteraciones que forman una generacion, para el seguimiento de la diversidad
+    let iterations_per_generation = (population_size / 2).max(1);
+    let mut iterations_in_generation = 0;
 
-        // Añadimos las evaluaciones de fitness consumidas en esta pasada
-        consumed_fitness_evaluations += iteration_fitness_evaluations as i32;
+    // Realizamos las iteraciones pertinentes mientras queden evaluaciones del fitness
+    while evaluator.budget_left() == true{
+        current_population = next_generation(&current_population, evaluator, rng, number_of_workers, mutation_probability_per_gen, crossover, selection, replacement);
+        debug_assert!(current_population.population_size() == population_size as usize, "La poblacion final tras la iteracion tiene {} elementos", current_population.population_size());
 
         // Llevamos la cuenta del valor del fitness de la mejor solucion de la poblacion en esta
         // iteracion. No deberia consumir evaluaciones del fitness porque ya en operaciones
-        // pasadas estamos evaluando toda la poblacion. Ademas, hacemos una evaluacion sobre toda
-        // la poblacion extra por seguridad (de nuevo, esa comprobacion de seguridad no consume
-        // evaluaciones adicionales)
-        let best_individual = current_population.get_best_individual().get_result().0;
+        // pasadas estamos evaluando toda la poblacion
+        let (best_individual, _) = current_population.get_best_individual(evaluator);
         let best_individual_fitness = best_individual.fitness();
         fitness_evolution.add_iteration(best_individual_fitness);
 
+        // Si la poblacion ha perdido la diversidad, la reiniciamos manteniendo al mejor individuo
+        iterations_in_generation += 1;
+        if iterations_in_generation >= iterations_per_generation{
+            iterations_in_generation = 0;
+
+            if diversity_monitor.after_generation(&current_population, best_individual_fitness) == true{
+                current_population = current_population.random_restart(evaluator, rng);
+                current_population.evaluate_all_individuals(number_of_workers, evaluator);
+            }
+        }
     }
 
-    return (current_population.get_best_individual().get_result().0.clone(), fitness_evolution);
+    return (current_population.best_solution(evaluator, rng), fitness_evolution);
+}
+
+/// Realiza una iteracion del genetico estacionario sobre la poblacion actual: seleccionamos dos
+/// padres con el operador de seleccion, los cruzamos y mutamos, y los hijos entran en la poblacion
+/// segun la estrategia de reemplazo. Devuelve la nueva poblacion, ya evaluada
+///
+/// La usan tanto el genetico estacionario como el modelo de islas
+#[allow(clippy::too_many_arguments)]
+pub fn next_generation(
+    current_population: &Population,
+    evaluator: &Evaluator,
+    rng: &mut StdRng,
+    number_of_workers: usize,
+    mutation_probability_per_gen: f64,
+    crossover: CrossoverOperator,
+    selection: SelectionOperator,
+    replacement: ReplacementStrategy
+    ) -> Population{
+    // Tomamos dos individuos de la poblacion con el operador de seleccion
+    // Si la poblacion anterior no esta evaluada, el torneo binario puede consumir hasta cuatro
+    // evaluaciones del fitness (2 x 2 candidatos compitiendo). El resto de operadores evaluan a
+    // toda la poblacion
+    let consumed_before_selection = evaluator.consumed();
+    let selection_population = current_population.select_population(selection, 2, number_of_workers, evaluator, rng);
+    debug_assert!(selection_population.population_size() == 2, "La poblacion de seleccion tiene {} elementos", selection_population.population_size());
+    debug_assert!(
+        (evaluator.consumed() - consumed_before_selection) as usize <= selection.max_evaluations(2, current_population.population_size()),
+        "En la seleccion de dos individuos hemos consumido {} evaluaciones, mas de las que permite el operador de seleccion",
+        evaluator.consumed() - consumed_before_selection
+    );
+
+    // Cruzamos los dos individuos que hemos tomado de la poblacion, generando otros dos
+    // individuos. Esto no provoca evaluaciones del fitness
+    let crossover_probability = 1.00; // Cruzamos forzosamente a los individuos
+    let crossed_population = selection_population.cross_population(crossover, crossover_probability, rng);
+    debug_assert!(crossed_population.population_size() == 2, "La poblacion de seleccion tiene {} elementos", crossed_population.population_size());
+    debug_assert!(crossed_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
+
+    // A partir de los dos hijos cruzados, mutamos en caso de que se escoja aleatoriamente hacerlo
+    // Esta operacion no consume evaluaciones
+    let mutated_population = crossed_population.mutate_population_given_prob(mutation_probability_per_gen, rng);
+    debug_assert!(mutated_population.population_size() == 2, "La poblacion de seleccion tiene {} elementos", mutated_population.population_size());
+    debug_assert!(mutated_population.all_population_is_not_cached() == true, "Uno de los dos individuos tiene el valor del fitness cacheado");
+
+    // Los dos hijos, cruzados y en algunos casos mutados, entran en la poblacion original segun
+    // la estrategia de reemplazo
+    // Esta operacion consume evaluaciones del fitness. Salvo en la primera iteracion, deberia
+    // consumir como mucho dos evaluaciones (en los dos nuevos individuos que entran a competir)
+    let final_population = current_population.replace_with_new_individuals(replacement, &selection_population, &mutated_population, evaluator, rng);
+
+    // Evaluamnos esta poblacion final. No deberia consumir ninguna evaluacion, pues en la
+    // competicion de los dos nuevos individuos, ya deberiamos tener a toda la poblacion
+    // evaluada. Hacemos esto por seguridad, pues al tener todos los fitness cacheados, no
+    // deberia consumir demasiado tiempo
+    let consumed_before_evaluation = evaluator.consumed();
+    final_population.evaluate_all_individuals(number_of_workers, evaluator);
+    debug_assert!(
+        evaluator.consumed() == consumed_before_evaluation,
+        "La poblacion deberia estar evaluada tras la competicion, pero estamos consumiendo {} evaluaciones",
+        evaluator.consumed() - consumed_before_evaluation
+    );
+
+    return final_population;
 }
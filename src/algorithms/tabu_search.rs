@@ -0,0 +1,280 @@
+use crate::problem_datatypes::{Solution, ProblemInstance, NeighbourGenerator};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Busqueda tabu sobre el vecindario de reasignar un punto a otro cluster, partiendo de una
+/// solucion aleatoria
+pub struct TabuSearch;
+
+impl Metaheuristic for TabuSearch{
+    fn name(&self) -> &'static str{
+        return "tabu_search";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Busqueda tabu";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("tabu_tenure", "Iteraciones que un movimiento permanece en la lista tabu (por defecto, 0.1 * numero de puntos)", None),
+            ParameterSpec::new("candidate_list_size", "Vecinos que se muestrean en cada iteracion", Some("50")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let tabu_tenure = params.get_optional::<usize>("tabu_tenure")?.unwrap_or((0.1 * instance.number_of_points() as f64) as usize);
+        let candidate_list_size = params.get::<usize>("candidate_list_size")?;
+
+        return Ok(run(instance, max_fitness_evaluations, tabu_tenure, candidate_list_size, rng));
+    }
+}
+
+/// Ejecuta la busqueda tabu partiendo de una solucion aleatoria, consumiendo como mucho
+/// max_fitness_evaluations evaluaciones del fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, tabu_tenure: usize, candidate_list_size: usize, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, tabu_tenure, candidate_list_size, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta la busqueda tabu partiendo de una solucion aleatoria, descontando las evaluaciones del
+/// fitness del evaluador dado
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, tabu_tenure: usize, candidate_list_size: usize, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    // La solucion aleatoria puede dejar clusters vacios, asi que la reparamos
+    let mut init_sol = Solution::generate_random_solution(instance, rng);
+    if init_sol.is_valid() == false{
+        init_sol.repair_solution(rng);
+    }
+
+    return run_from_init_sol(evaluator, &init_sol, tabu_tenure, candidate_list_size, rng);
+}
+
+/// Lanza la busqueda tabu a partir de la solucion inicial dada
+///
+/// En cada iteracion muestreamos candidate_list_size movimientos aleatorios y nos movemos al
+/// mejor de ellos que no sea tabu, aunque empeore la solucion actual. Al mover un punto desde su
+/// cluster, el par (punto, cluster original) pasa a ser tabu durante tabu_tenure iteraciones, de
+/// forma que no podemos devolver el punto a dicho cluster. Criterio de aspiracion: un movimiento
+/// tabu se acepta si mejora la mejor solucion encontrada hasta el momento
+pub fn run_from_init_sol(evaluator: &Evaluator, init_sol: &Solution, tabu_tenure: usize, candidate_list_size: usize, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    let mut current_solution = init_sol.clone();
+    let mut best_solution = current_solution.clone();
+    let mut best_fitness = match evaluator.fitness(&best_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (best_solution, fitness_evolution),
+    };
+    fitness_evolution.add_iteration(best_fitness);
+
+    let mut tabu_list = TabuList::new(tabu_tenure);
+
+    let number_of_points = current_solution.get_data_points().len();
+    let number_of_clusters = current_solution.get_number_of_clusters() as u32;
+    let mut iteration = 0;
+
+    // Con un unico cluster no hay ningun movimiento posible
+    if number_of_clusters < 2{
+        return (best_solution, fitness_evolution);
+    }
+
+    while evaluator.budget_left() == true{
+        iteration += 1;
+
+        // Mejor candidato admisible de esta iteracion, junto al movimiento que lo genera
+        let mut best_candidate: Option<(Solution, f64, usize, u32)> = None;
+
+        // Indica si alguno de los candidatos muestreados era valido
+        let mut valid_candidates = false;
+
+        let cluster_indexes = current_solution.get_cluster_indexes();
+        for _ in 0..candidate_list_size{
+            // Movimiento aleatorio a un cluster distinto del actual
+            let point_index = rng.gen_range(0..number_of_points);
+            let old_cluster = cluster_indexes[point_index];
+            let new_cluster = (old_cluster + rng.gen_range(1..number_of_clusters)) % number_of_clusters;
+            let generator = NeighbourGenerator::new(point_index as i32, new_cluster);
+
+            let candidate = current_solution.generate_solution_from(generator);
+
+            // Los vecinos no validos no consumen evaluaciones del fitness
+            if candidate.is_valid() == false{
+                continue;
+            }
+            valid_candidates = true;
+
+            let candidate_fitness = match evaluator.fitness(&candidate){
+                Some(fitness) => fitness,
+                None => break,
+            };
+
+            // Comprobamos si el movimiento es tabu y si se cumple el criterio de aspiracion
+            if tabu_list.is_admissible(point_index, new_cluster, iteration, candidate_fitness, best_fitness) == false{
+                continue;
+            }
+
+            let improves_candidate = match &best_candidate{
+                Some((_, fitness, _, _)) => candidate_fitness < *fitness,
+                None => true,
+            };
+            if improves_candidate == true{
+                best_candidate = Some((candidate, candidate_fitness, point_index, old_cluster));
+            }
+        }
+
+        // Todos los movimientos muestreados dejaban un cluster vacio. Volvemos a muestrear,
+        // salvo que no exista ningun movimiento valido, es decir, que todos los clusters tengan
+        // un unico punto
+        if valid_candidates == false{
+            if has_valid_move(&cluster_indexes, number_of_clusters) == false{
+                break;
+            }
+            continue;
+        }
+
+        // Todos los candidatos eran tabu, o se agotaron las evaluaciones
+        let (candidate, candidate_fitness, point_index, old_cluster) = match best_candidate{
+            Some(best_candidate) => best_candidate,
+            None => continue,
+        };
+
+        // Hacemos el movimiento, prohibiendo devolver el punto a su cluster original
+        tabu_list.make_tabu(point_index, old_cluster, iteration);
+        current_solution = candidate;
+
+        if candidate_fitness < best_fitness{
+            best_fitness = candidate_fitness;
+            best_solution = current_solution.clone();
+        }
+
+        fitness_evolution.add_iteration(candidate_fitness);
+    }
+
+    return (best_solution, fitness_evolution);
+}
+
+/// Lista tabu: para cada atributo (punto, cluster) tabu, guardamos la iteracion hasta la que es
+/// tabu
+struct TabuList{
+    tabu_until: HashMap<(usize, u32), usize>,
+    tabu_tenure: usize,
+}
+
+impl TabuList{
+    fn new(tabu_tenure: usize) -> Self{
+        return Self{tabu_until: HashMap::new(), tabu_tenure};
+    }
+
+    /// Prohibe devolver point_index a cluster durante las tabu_tenure iteraciones siguientes a
+    /// iteration
+    fn make_tabu(&mut self, point_index: usize, cluster: u32, iteration: usize){
+        self.tabu_until.insert((point_index, cluster), iteration + self.tabu_tenure);
+    }
+
+    fn is_tabu(&self, point_index: usize, cluster: u32, iteration: usize) -> bool{
+        match self.tabu_until.get(&(point_index, cluster)){
+            Some(&tabu_until) => return tabu_until >= iteration,
+            None => return false,
+        }
+    }
+
+    /// Un movimiento es admisible si no es tabu o si cumple el criterio de aspiracion, es decir,
+    /// si mejora estrictamente la mejor solucion encontrada
+    fn is_admissible(&self, point_index: usize, cluster: u32, iteration: usize, candidate_fitness: f64, best_fitness: f64) -> bool{
+        return self.is_tabu(point_index, cluster, iteration) == false || candidate_fitness < best_fitness;
+    }
+}
+
+/// Comprueba si existe algun movimiento que no deje un cluster vacio, es decir, si algun cluster
+/// tiene mas de un punto
+fn has_valid_move(cluster_indexes: &[u32], number_of_clusters: u32) -> bool{
+    let mut sizes = vec![0; number_of_clusters as usize];
+    for &cluster in cluster_indexes{
+        sizes[cluster as usize] += 1;
+    }
+
+    return sizes.iter().any(|&size| size > 1);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::tabu_search::{TabuList, has_valid_move, run_from_init_sol};
+    use crate::problem_datatypes::{Solution, ProblemInstance, DataPoints, Point, Constraints, ConstraintType};
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    #[test]
+    // Un movimiento tabu solo se admite si mejora a la mejor solucion (aspiracion), y deja de ser
+    // tabu pasadas tabu_tenure iteraciones
+    fn test_tabu_aspiration_and_tenure(){
+        let mut tabu_list = TabuList::new(3);
+        tabu_list.make_tabu(7, 2, 10);
+
+        for iteration in 11..=13{
+            assert!(tabu_list.is_tabu(7, 2, iteration) == true);
+            assert!(tabu_list.is_admissible(7, 2, iteration, 0.5, 0.5) == false);
+            assert!(tabu_list.is_admissible(7, 2, iteration, 0.4, 0.5) == true);
+        }
+        assert!(tabu_list.is_tabu(7, 2, 14) == false);
+        assert!(tabu_list.is_admissible(7, 2, 14, 0.9, 0.5) == true);
+
+        // Solo es tabu el par (punto, cluster) prohibido
+        assert!(tabu_list.is_tabu(7, 1, 11) == false);
+        assert!(tabu_list.is_tabu(6, 2, 11) == false);
+    }
+
+    #[test]
+    // Con casi todos los clusters de un unico punto, la mayoria de movimientos muestreados no son
+    // validos, pero la busqueda debe seguir hasta agotar las evaluaciones
+    fn test_search_uses_whole_budget_with_singleton_clusters(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let number_of_clusters = 20;
+        let points = (0..number_of_clusters + 1).map(|_| Point::random_point(3, &mut rng)).collect();
+
+        // Sin restricciones lambda es infinito y el fitness no es un numero, asi que añadimos una
+        // restriccion que la solucion inicial viola y otra que cumple
+        let mut constraints = Constraints::new();
+        constraints.add_constraint(0, 1, ConstraintType::CannotLink);
+        constraints.add_constraint(2, 3, ConstraintType::CannotLink);
+        let instance = Arc::new(ProblemInstance::new(DataPoints::new(points), constraints, number_of_clusters));
+
+        // Dos puntos en el cluster 0 y uno en cada uno de los demas clusters
+        let cluster_indexes: Vec<u32> = (0..number_of_clusters + 1).map(|index| index.max(1) as u32 - 1).collect();
+        let init_sol = Solution::new(cluster_indexes.clone(), &instance);
+        assert!(init_sol.is_valid() == true);
+        assert!(has_valid_move(&cluster_indexes, number_of_clusters as u32) == true);
+        assert!(has_valid_move(&cluster_indexes[1..], number_of_clusters as u32) == false);
+
+        let evaluator = Evaluator::new(100);
+        let (solution, _) = run_from_init_sol(&evaluator, &init_sol, 2, 3, &mut rng);
+        assert_eq!(evaluator.consumed(), 100);
+        assert!(solution.is_valid() == true);
+
+        // La busqueda nunca devuelve algo peor que la solucion de la que parte
+        let init_fitness = Solution::new(cluster_indexes, &instance).fitness();
+        assert!(init_fitness.is_finite() == true);
+        assert!(solution.fitness() <= init_fitness);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::algorithms::random_neighbour_search::{self, AcceptanceRule};
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Aceptacion por umbral (Threshold Accepting), partiendo de una solucion aleatoria
+pub struct ThresholdAccepting;
+
+impl Metaheuristic for ThresholdAccepting{
+    fn name(&self) -> &'static str{
+        return "threshold_accepting";
+    }
+
+    fn description(&self) -> &'static str{
+        return "Aceptacion por umbral";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("initial_threshold", "Umbral inicial, como fraccion del fitness de la solucion inicial", Some("0.05")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let initial_threshold = params.get::<f64>("initial_threshold")?;
+
+        return Ok(run(instance, max_fitness_evaluations, initial_threshold, rng));
+    }
+}
+
+/// Ejecuta la aceptacion por umbral partiendo de una solucion aleatoria, consumiendo como mucho
+/// max_fitness_evaluations evaluaciones del fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, initial_threshold: f64, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, initial_threshold, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta la aceptacion por umbral partiendo de una solucion aleatoria, descontando las
+/// evaluaciones del fitness del evaluador dado
+///
+/// Un vecino aleatorio se acepta si no empeora la solucion actual en mas del umbral. El umbral
+/// empieza en initial_threshold veces el fitness de la solucion inicial y desciende linealmente
+/// hasta cero segun consumimos las evaluaciones, de forma que al final solo aceptamos mejoras
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, initial_threshold: f64, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    return random_neighbour_search::run_with_evaluator(instance, evaluator, AcceptanceRule::Threshold{initial_threshold}, rng);
+}
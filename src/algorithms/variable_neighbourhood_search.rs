@@ -0,0 +1,138 @@
+use crate::problem_datatypes::{Solution, ProblemInstance};
+use crate::fitness_evolution::FitnessEvolution;
+use crate::algorithms::local_search;
+use crate::evaluator::Evaluator;
+use crate::run_result::RunResult;
+use crate::metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+use crate::acceptance_criterion::ImproveOnly;
+
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::time::Instant;
+use std::sync::Arc;
+
+/// Busqueda de vecindario variable. El vecindario k-esimo consiste en reasignar k puntos a otros
+/// clusters. La version basica aplica busqueda local tras cada sacudida (reduced == false), y la
+/// version reducida se queda directamente con la solucion sacudida (reduced == true)
+pub struct VariableNeighbourhoodSearch{
+    reduced: bool,
+}
+
+impl VariableNeighbourhoodSearch{
+    pub fn new(reduced: bool) -> Self{
+        return Self{reduced};
+    }
+}
+
+impl Metaheuristic for VariableNeighbourhoodSearch{
+    fn name(&self) -> &'static str{
+        if self.reduced == true{
+            return "reduced_vns";
+        }
+        return "vns";
+    }
+
+    fn description(&self) -> &'static str{
+        if self.reduced == true{
+            return "Busqueda de vecindario variable reducida";
+        }
+        return "Busqueda de vecindario variable basica";
+    }
+
+    fn parameters(&self) -> Vec<ParameterSpec>{
+        return vec![
+            ParameterSpec::new("max_fitness_evaluations", "Maximo de evaluaciones del fitness", Some("100000")),
+            ParameterSpec::new("k_max", "Numero de puntos que se reasignan en el vecindario mas grande", Some("10")),
+        ];
+    }
+
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>{
+        let params = params.with_defaults(&self.parameters())?;
+        let max_fitness_evaluations = params.get::<u32>("max_fitness_evaluations")?;
+        let k_max = params.get::<usize>("k_max")?;
+
+        return Ok(run(instance, max_fitness_evaluations, k_max, self.reduced, rng));
+    }
+}
+
+/// Ejecuta la busqueda de vecindario variable partiendo de una solucion aleatoria, consumiendo
+/// como mucho max_fitness_evaluations evaluaciones del fitness
+pub fn run(instance: &Arc<ProblemInstance>, max_fitness_evaluations: u32, k_max: usize, reduced: bool, rng: &mut StdRng) -> RunResult{
+    let evaluator = Evaluator::new(max_fitness_evaluations);
+
+    let before = Instant::now();
+    let (solution, fitness_evolution) = run_with_evaluator(instance, &evaluator, k_max, reduced, rng);
+    let duration = before.elapsed();
+
+    return RunResult::new(solution, evaluator.consumed(), duration, fitness_evolution);
+}
+
+/// Ejecuta la busqueda de vecindario variable partiendo de una solucion aleatoria, descontando las
+/// evaluaciones del fitness del evaluador dado
+///
+/// Sacudimos la mejor solucion con el vecindario k-esimo, empezando en k = 1. Si la solucion
+/// obtenida (tras la busqueda local, en la version basica) mejora a la mejor, nos movemos a ella y
+/// volvemos a k = 1. En otro caso pasamos al vecindario k + 1, volviendo a k = 1 tras k_max.
+/// Iteramos hasta agotar las evaluaciones del fitness
+pub fn run_with_evaluator(instance: &Arc<ProblemInstance>, evaluator: &Evaluator, k_max: usize, reduced: bool, rng: &mut StdRng) -> (Solution, FitnessEvolution){
+    let mut fitness_evolution = FitnessEvolution::new();
+
+    // La solucion aleatoria puede dejar clusters vacios, asi que la reparamos
+    let mut best_solution = Solution::generate_random_solution(instance, rng);
+    if best_solution.is_valid() == false{
+        best_solution.repair_solution(rng);
+    }
+
+    // En la version basica, partimos de un optimo local
+    if reduced == false{
+        let (local_solution, _) = local_search::run_from_init_sol(evaluator, &best_solution, &mut ImproveOnly, rng);
+        best_solution = local_solution;
+    }
+
+    let mut best_fitness = match evaluator.fitness(&best_solution){
+        Some(fitness) => fitness,
+
+        // No quedan evaluaciones ni para evaluar la solucion inicial
+        None => return (best_solution, fitness_evolution),
+    };
+    fitness_evolution.add_iteration(best_fitness);
+
+    let mut k = 1;
+    while evaluator.budget_left() == true{
+        // Sacudida en el vecindario k-esimo
+        let mut new_solution = best_solution.shaken(k, rng);
+
+        // Si ningun cluster tiene mas de un punto, no podemos movernos a ninguna otra solucion
+        if new_solution.get_cluster_indexes() == best_solution.get_cluster_indexes(){
+            break;
+        }
+
+        // Descenso con busqueda local en la version basica
+        if reduced == false{
+            let (local_solution, _) = local_search::run_from_init_sol(evaluator, &new_solution, &mut ImproveOnly, rng);
+            new_solution = local_solution;
+        }
+
+        let new_fitness = match evaluator.fitness(&new_solution){
+            Some(fitness) => fitness,
+            None => break,
+        };
+
+        // Si mejoramos, nos movemos y volvemos al primer vecindario. En otro caso, pasamos al
+        // siguiente vecindario
+        if new_fitness < best_fitness{
+            best_solution = new_solution;
+            best_fitness = new_fitness;
+            k = 1;
+        }else{
+            k += 1;
+            if k > k_max{
+                k = 1;
+            }
+        }
+
+        fitness_evolution.add_iteration(best_fitness);
+    }
+
+    return (best_solution, fitness_evolution);
+}
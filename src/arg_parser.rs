@@ -1,100 +1,10 @@
+use crate::metaheuristic::Parameters;
+
 use std::env;
 use std::error::Error;
 use std::boxed::Box;
 use simple_error::bail; // Devuelve errores simples con un string descriptivo
 
-/// Tipo de busqueda que el usuario quiere ejecutar
-#[derive(Debug, Clone, Copy)]
-pub enum SearchType{
-    Copkmeans,
-    CopkmeansRobust,
-
-    LocalSearch,
-
-    GenerationalGeneticUniform,
-    GenerationalGeneticSegment,
-    SteadyGeneticUniform,
-    SteadyGeneticSegment,
-
-    MemeticAll,
-    MemeticRandom,
-    MemeticElitist,
-
-    MultiStartLocalSearch,
-
-    IterativeLocalSearchBasic,
-    IterativeLocalSearchSimulatedAnnealing,
-
-    SimulatedAnnealing,
-}
-
-impl SearchType{
-    /// Toma un string con el tipo de busqueda y lo convierte al struct
-    /// En el codigo se pueden ver los valores validos para el codigo
-    // TODO -- refactor, usar un HashMap para no tener este bloque de ifs
-    pub fn from_str(code: &str) -> Result<Self, Box<dyn Error>>{
-        if code == "copkmeans"{
-            return Ok(SearchType::Copkmeans);
-        }
-
-        if code == "copkmeans_robust"{
-            return Ok(SearchType::CopkmeansRobust);
-        }
-
-        if code == "local_search"{
-            return Ok(SearchType::LocalSearch);
-        }
-
-        if code == "gguniform"{
-            return Ok(SearchType::GenerationalGeneticUniform);
-        }
-
-        if code == "ggsegment"{
-            return Ok(SearchType::GenerationalGeneticSegment);
-
-        }
-
-        if code == "gsuniform"{
-            return Ok(SearchType::SteadyGeneticUniform);
-        }
-
-        if code == "gssegment"{
-            return Ok(SearchType::SteadyGeneticSegment);
-        }
-
-        if code == "memeall"{
-            return Ok(SearchType::MemeticAll);
-        }
-
-        if code == "memerandom"{
-            return Ok(SearchType::MemeticRandom)
-        }
-
-        if code == "memeelitist"{
-            return Ok(SearchType::MemeticElitist);
-        }
-
-        if code == "multistartlocalsearch" {
-            return Ok(SearchType::MultiStartLocalSearch);
-        }
-
-        if code == "iterative_local_search"{
-            return Ok(SearchType::IterativeLocalSearchBasic);
-        }
-
-        if code == "iterative_local_search_annealing"{
-            return Ok(SearchType::IterativeLocalSearchSimulatedAnnealing);
-        }
-
-        if code == "simulated_annealing"{
-            return Ok(SearchType::SimulatedAnnealing);
-        }
-
-        // Codigo no valido
-        bail!("Valor del string para seleccionar la busqueda no valido");
-    }
-}
-
 /// Representa los parametros del programa
 /// Estos son los que ha introducido el dato por la linea de comandos
 #[derive(Debug)]
@@ -103,7 +13,17 @@ pub struct ProgramParameters{
     constraints_file: String,
     seed: u64,
     number_of_clusters: i32,
-    search_type: SearchType,
+
+    /// Nombre del algoritmo de busqueda, tal y como aparece en crate::algorithms::all_metaheuristics
+    search_type: String,
+
+    /// Numero de hilos con los que se evaluan las poblaciones en los algoritmos geneticos y
+    /// memeticos, y con los que se lanzan los arranques de la busqueda multiarranque. Es
+    /// opcional, por defecto se ejecuta en secuencial con un unico hilo
+    number_of_workers: usize,
+
+    /// Parametros del algoritmo, dados como nombre=valor tras el resto de argumentos
+    parameters: Parameters,
 }
 
 impl ProgramParameters{
@@ -113,8 +33,8 @@ impl ProgramParameters{
         // Tomamos los argumentos pasados por la linea de comandos
         let args: Vec<String> = env::args().collect();
 
-        if args.len() != 6{
-            bail!("5 parameters expected, {} given", args.len() - 1)
+        if args.len() < 6{
+            bail!("At least 5 parameters expected, {} given", args.len() - 1)
         }
 
         let data_file = args[1].parse::<String>()?;
@@ -122,10 +42,25 @@ impl ProgramParameters{
         let seed = args[3].parse::<u64>()?;
         let number_of_clusters = args[4].parse::<i32>()?;
         let search_type = args[5].parse::<String>()?;
-        let search_type = SearchType::from_str(&search_type)?;
+
+        // El numero de hilos es opcional, y lo distinguimos de los parametros del algoritmo
+        // porque no tiene la forma nombre=valor
+        let mut assignments = &args[6..];
+        let mut number_of_workers = 1;
+        if let Some(value) = assignments.first(){
+            if value.contains('=') == false{
+                number_of_workers = value.parse::<usize>()?;
+                assignments = &assignments[1..];
+            }
+        }
+        let parameters = Parameters::from_assignments(assignments)?;
+
+        if number_of_workers == 0{
+            bail!("El numero de hilos debe ser al menos 1");
+        }
 
         return Ok(ProgramParameters{
-            data_file, constraints_file, seed, number_of_clusters, search_type
+            data_file, constraints_file, seed, number_of_clusters, search_type, number_of_workers, parameters
         });
     }
 
@@ -145,7 +80,15 @@ impl ProgramParameters{
         return self.number_of_clusters;
     }
 
-    pub fn get_search_type(&self) -> SearchType{
-        return self.search_type;
+    pub fn get_search_type(&self) -> String{
+        return self.search_type.clone();
+    }
+
+    pub fn get_number_of_workers(&self) -> usize{
+        return self.number_of_workers;
+    }
+
+    pub fn get_parameters(&self) -> &Parameters{
+        return &self.parameters;
     }
 }
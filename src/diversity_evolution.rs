@@ -0,0 +1,148 @@
+use crate::problem_datatypes::population::Population;
+
+use std::error::Error;
+
+/// Medidas de la diversidad de una poblacion. Ninguna consume evaluaciones del fitness, pues solo
+/// miran las asignaciones de clusters de los individuos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiversityMetrics{
+    /// Media de la distancia de particion entre todas las parejas de individuos (ver
+    /// Solution::partition_distance)
+    mean_partition_distance: f64,
+
+    /// Media sobre todos los puntos de la entropia de los clusters a los que asigna ese punto la
+    /// poblacion, normalizada a [0, 1]. Es 0 cuando todos los individuos asignan cada punto al
+    /// mismo cluster, y 1 cuando se reparten por igual entre todos los clusters
+    mean_gene_entropy: f64,
+
+    /// Numero de agrupaciones distintas en la poblacion
+    distinct_individuals: usize,
+}
+
+impl DiversityMetrics{
+    pub fn new(mean_partition_distance: f64, mean_gene_entropy: f64, distinct_individuals: usize) -> Self{
+        return Self{mean_partition_distance, mean_gene_entropy, distinct_individuals};
+    }
+
+    pub fn get_mean_partition_distance(&self) -> f64{
+        return self.mean_partition_distance;
+    }
+
+    pub fn get_mean_gene_entropy(&self) -> f64{
+        return self.mean_gene_entropy;
+    }
+
+    pub fn get_distinct_individuals(&self) -> usize{
+        return self.distinct_individuals;
+    }
+}
+
+/// Evolucion de la diversidad de la poblacion en cada generacion, junto al fitness del mejor
+/// individuo, para estudiar la convergencia de los algoritmos geneticos y memeticos
+#[derive(Debug, Clone, Default)]
+pub struct DiversityEvolution{
+    best_fitness_at_generation: Vec<f64>,
+    metrics_at_generation: Vec<DiversityMetrics>,
+}
+
+impl DiversityEvolution{
+    pub fn new() -> Self{
+        return Self{best_fitness_at_generation: vec![], metrics_at_generation: vec![]};
+    }
+
+    /// Añade una generacion mas
+    pub fn add_generation(&mut self, best_fitness: f64, metrics: DiversityMetrics){
+        self.best_fitness_at_generation.push(best_fitness);
+        self.metrics_at_generation.push(metrics);
+    }
+
+    pub fn number_of_generations(&self) -> usize{
+        return self.metrics_at_generation.len();
+    }
+
+    pub fn get_best_fitness_at_generation(&self) -> Vec<f64>{
+        return self.best_fitness_at_generation.clone();
+    }
+
+    pub fn get_metrics_at_generation(&self) -> Vec<DiversityMetrics>{
+        return self.metrics_at_generation.clone();
+    }
+
+    /// Guarda la evolucion en un fichero csv, con una fila por generacion
+    pub fn save_as_csv(&self, file_path: &str) -> Result<(), Box<dyn Error>>{
+        let mut writer = csv::Writer::from_path(file_path)?;
+        writer.write_record(["generation", "best_fitness", "mean_partition_distance", "mean_gene_entropy", "distinct_individuals"])?;
+
+        for (generation, (best_fitness, metrics)) in self.best_fitness_at_generation.iter().zip(self.metrics_at_generation.iter()).enumerate(){
+            writer.write_record([
+                generation.to_string(),
+                best_fitness.to_string(),
+                metrics.mean_partition_distance.to_string(),
+                metrics.mean_gene_entropy.to_string(),
+                metrics.distinct_individuals.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+
+        return Ok(());
+    }
+}
+
+/// Seguimiento de la diversidad en los algoritmos geneticos y memeticos. Tras cada generacion,
+/// guarda las medidas de diversidad de la poblacion y decide si hay que reiniciarla porque la
+/// diversidad se ha perdido
+///
+/// Si no guardamos la evolucion ni reiniciamos, no calcula nada, pues las medidas de diversidad
+/// tienen un coste cuadratico en el tamaño de la poblacion
+#[derive(Debug, Clone)]
+pub struct DiversityMonitor{
+    /// Si guardamos las medidas de cada generacion
+    record: bool,
+
+    /// Reiniciamos la poblacion cuando la entropia media de los genes baja de este valor
+    restart_threshold: Option<f64>,
+
+    evolution: DiversityEvolution,
+    restarts: usize,
+}
+
+impl DiversityMonitor{
+    pub fn new(record: bool, restart_threshold: Option<f64>) -> Self{
+        return Self{record, restart_threshold, evolution: DiversityEvolution::new(), restarts: 0};
+    }
+
+    /// Seguimiento que no guarda nada ni reinicia la poblacion
+    pub fn disabled() -> Self{
+        return Self::new(false, None);
+    }
+
+    /// Registra una generacion. Devuelve true si la diversidad se ha perdido y hay que reiniciar
+    /// la poblacion, contando el reinicio
+    pub fn after_generation(&mut self, population: &Population, best_fitness: f64) -> bool{
+        if self.record == false && self.restart_threshold.is_none(){
+            return false;
+        }
+
+        let metrics = population.diversity_metrics();
+        if self.record == true{
+            self.evolution.add_generation(best_fitness, metrics);
+        }
+
+        match self.restart_threshold{
+            Some(threshold) if metrics.get_mean_gene_entropy() < threshold => {
+                self.restarts += 1;
+                return true;
+            },
+            _ => return false,
+        }
+    }
+
+    pub fn get_evolution(&self) -> &DiversityEvolution{
+        return &self.evolution;
+    }
+
+    /// Numero de veces que se ha pedido reiniciar la poblacion
+    pub fn get_restarts(&self) -> usize{
+        return self.restarts;
+    }
+}
@@ -0,0 +1,139 @@
+use crate::problem_datatypes::Solution;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Lleva la cuenta de las evaluaciones del fitness que consume un algoritmo, y no permite que se
+/// supere el maximo de evaluaciones establecido
+///
+/// Todas las evaluaciones del fitness que hacen los algoritmos pasan por Evaluator::fitness, de
+/// forma que todos los algoritmos paran exactamente en el mismo numero de evaluaciones. Pedir el
+/// fitness de una solucion que ya lo tiene cacheado no consume evaluaciones
+///
+/// El contador es atomico para poder compartir el evaluador entre hilos
+#[derive(Debug)]
+pub struct Evaluator{
+    /// Maximo de evaluaciones del fitness que podemos consumir
+    max_evaluations: u32,
+
+    /// Evaluaciones del fitness consumidas hasta el momento
+    consumed: AtomicU32,
+}
+
+impl Evaluator{
+    pub fn new(max_evaluations: u32) -> Self{
+        return Self{max_evaluations, consumed: AtomicU32::new(0)};
+    }
+
+    /// Devuelve el valor del fitness de la solucion, consumiendo una evaluacion si no estaba
+    /// cacheado. Si ya no quedan evaluaciones y el fitness no esta cacheado, devuelve None y la
+    /// solucion queda sin evaluar
+    pub fn fitness(&self, solution: &Solution) -> Option<f64>{
+        if solution.is_fitness_cached() == true{
+            return Some(solution.fitness());
+        }
+
+        if self.reserve_evaluation() == false{
+            return None;
+        }
+
+        let (fitness, consumed) = solution.fitness_and_consumed();
+        debug_assert!(consumed == 1, "La solucion no deberia tener el fitness cacheado");
+        return Some(fitness);
+    }
+
+    /// Intenta reservar una evaluacion del fitness. Devuelve false si ya no quedan
+    fn reserve_evaluation(&self) -> bool{
+        let result = self.consumed.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |consumed| {
+            if consumed < self.max_evaluations{
+                return Some(consumed + 1);
+            }
+            return None;
+        });
+
+        return result.is_ok();
+    }
+
+    /// Comprueba si todavia quedan evaluaciones del fitness por consumir
+    pub fn budget_left(&self) -> bool{
+        return self.remaining() > 0;
+    }
+
+    /// Numero de evaluaciones del fitness que todavia podemos consumir
+    pub fn remaining(&self) -> u32{
+        return self.max_evaluations - self.consumed();
+    }
+
+    pub fn consumed(&self) -> u32{
+        return self.consumed.load(Ordering::SeqCst);
+    }
+
+    pub fn get_max_evaluations(&self) -> u32{
+        return self.max_evaluations;
+    }
+
+    /// Genera un evaluador independiente para una parte del algoritmo (por ejemplo, cada una de
+    /// las busquedas locales de ILS), con max_evaluations evaluaciones como mucho y nunca mas de
+    /// las que le quedan a este evaluador
+    /// Cuando la parte del algoritmo termina, hay que sumar sus evaluaciones con Evaluator::absorb
+    pub fn sub_evaluator(&self, max_evaluations: u32) -> Self{
+        return Self::new(max_evaluations.min(self.remaining()));
+    }
+
+    /// Añade a este evaluador las evaluaciones consumidas por un evaluador generado con
+    /// Evaluator::sub_evaluator
+    pub fn absorb(&self, sub_evaluator: &Evaluator){
+        let consumed = self.consumed.fetch_add(sub_evaluator.consumed(), Ordering::SeqCst) + sub_evaluator.consumed();
+        debug_assert!(consumed <= self.max_evaluations, "Se han consumido {} evaluaciones de {}", consumed, self.max_evaluations);
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::evaluator::Evaluator;
+    use crate::problem_datatypes::{ProblemInstance, Solution};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // No se pueden evaluar soluciones una vez agotado el presupuesto, y las soluciones cacheadas no
+    // consumen evaluaciones
+    fn test_budget_is_enforced(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let solutions: Vec<Solution> = (0..5).map(|_| Solution::generate_random_solution(&instance, &mut rng)).collect();
+        let evaluator = Evaluator::new(3);
+
+        for solution in solutions.iter().take(3){
+            assert!(evaluator.fitness(solution).is_some());
+        }
+        assert_eq!(evaluator.consumed(), 3);
+        assert!(evaluator.budget_left() == false);
+
+        // Las soluciones ya evaluadas se pueden seguir consultando
+        assert_eq!(evaluator.fitness(&solutions[0]), Some(solutions[0].fitness()));
+
+        // Las que no estan evaluadas se rechazan y quedan sin evaluar
+        assert_eq!(evaluator.fitness(&solutions[3]), None);
+        assert!(solutions[3].is_fitness_cached() == false);
+        assert_eq!(evaluator.consumed(), 3);
+    }
+
+    #[test]
+    fn test_sub_evaluator_is_bounded_by_parent(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let evaluator = Evaluator::new(5);
+
+        let sub_evaluator = evaluator.sub_evaluator(3);
+        for _ in 0..4{
+            sub_evaluator.fitness(&Solution::generate_random_solution(&instance, &mut rng));
+        }
+        assert_eq!(sub_evaluator.consumed(), 3);
+        evaluator.absorb(&sub_evaluator);
+        assert_eq!(evaluator.remaining(), 2);
+
+        let sub_evaluator = evaluator.sub_evaluator(3);
+        assert_eq!(sub_evaluator.get_max_evaluations(), 2);
+    }
+}
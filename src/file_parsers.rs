@@ -49,7 +49,12 @@ pub fn parse_constraints_file_to_struct(constraint_file_path: &str) -> Result<Co
         .has_headers(false) // Nuestro fichero no tiene headers
         .from_path(constraint_file_path)?;
 
+    // Numero de filas de la matriz de restricciones, que coincide con el numero de puntos
+    let mut number_of_points = 0;
+
     for (index, current_line) in reader.records().enumerate() {
+        number_of_points += 1;
+
         // Unwrap el result
         let current_line = current_line?;
 
@@ -74,5 +79,8 @@ pub fn parse_constraints_file_to_struct(constraint_file_path: &str) -> Result<Co
         }
     }
 
+    // Construimos las listas de adyacencia de cada punto una unica vez
+    constraints.build_adjacency(number_of_points);
+
     return Ok(constraints);
 }
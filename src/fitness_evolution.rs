@@ -1,10 +1,9 @@
 use std::fmt;
-use npy::to_file;
 use serde::{Serialize};
 
 /// Representa la evolucion en fitness de los distintos algoritmos de búsqueda
 /// iterativa
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct FitnessEvolution{
     fitness_at_iteration: Vec<f64>,
 }
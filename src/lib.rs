@@ -0,0 +1,53 @@
+//! Metaheuristicas para el problema del agrupamiento con restricciones
+//!
+//! Ademas del binario, exponemos los tipos del problema, los parsers de ficheros y los algoritmos
+//! de busqueda, para poder usarlos desde otros programas. Cada algoritmo ofrece una funcion `run`
+//! que devuelve un `RunResult` con la solucion, el desglose de su fitness, las evaluaciones
+//! consumidas, el tiempo empleado y la evolucion del fitness
+//!
+//! Ademas, todos los algoritmos implementan `Metaheuristic` y estan registrados por nombre en
+//! `algorithms::all_metaheuristics`, de forma que se pueden lanzar con parametros genericos
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use rand::SeedableRng;
+//! use rand::rngs::StdRng;
+//! use practicas_metaheuristicas::{file_parsers, ProblemInstance};
+//! use practicas_metaheuristicas::algorithms::local_search;
+//! use practicas_metaheuristicas::acceptance_criterion::ImproveOnly;
+//!
+//! let data_points = file_parsers::parse_data_file_to_struct("data.dat").unwrap();
+//! let constraints = file_parsers::parse_constraints_file_to_struct("data.const").unwrap();
+//! let instance = Arc::new(ProblemInstance::new(data_points, constraints, 3));
+//!
+//! let mut rng = StdRng::seed_from_u64(42);
+//! let result = local_search::run(&instance, 100000, &mut ImproveOnly, &mut rng);
+//! println!("{} {}", result.fitness(), result.get_fitness_evaluations());
+//!
+//! // Lo mismo, buscando el algoritmo por nombre
+//! use practicas_metaheuristicas::{algorithms, Parameters};
+//! let local_search = algorithms::find_metaheuristic("local_search").unwrap();
+//! let params = Parameters::new().with("max_fitness_evaluations", "100000");
+//! let result = local_search.run(&instance, &params, &mut rng).unwrap();
+//! ```
+
+// Ficheros en los que separo mi codigo
+pub mod arg_parser;
+pub mod file_parsers;
+pub mod problem_datatypes;
+pub mod algorithms;
+pub mod fitness_evolution;
+pub mod diversity_evolution;
+pub mod evaluator;
+pub mod metaheuristic;
+pub mod acceptance_criterion;
+pub mod run_result;
+pub mod utils;
+
+// Reexportamos los tipos mas usados para no tener que escribir el path completo
+pub use problem_datatypes::{DataPoints, Constraints, ConstraintType, Point, Solution, ProblemInstance};
+pub use fitness_evolution::FitnessEvolution;
+pub use evaluator::Evaluator;
+pub use run_result::RunResult;
+pub use metaheuristic::{Metaheuristic, Parameters, ParameterSpec};
+pub use acceptance_criterion::{AcceptanceCriterion, Decision};
@@ -1,37 +1,39 @@
 use std::process::exit;
+use std::sync::Arc;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 
-// Ficheros en los que separo mi codigo
-mod arg_parser;
-mod file_parsers;
-mod problem_datatypes;
-mod algorithms;
-mod fitness_evolution;
-mod fitness_evaluation_result;
-mod utils;
-
-use algorithms::local_search;
-use algorithms::copkmeans;
-use algorithms::generational_genetic;
-use algorithms::steady_genetic;
-use algorithms::memetic;
-use algorithms::multistart_local_search as multilocal;
-use algorithms::iterative_local_search;
-use algorithms::simulated_annealing;
+// El codigo vive en la biblioteca, el binario solo se encarga de la linea de comandos
+use practicas_metaheuristicas::{arg_parser, file_parsers, problem_datatypes, algorithms};
 
 fn show_help(){
     println!("Modo de uso del programa:");
-    println!("\t./PracticasMetaheuristicas <data_file> <constraints_file> <seed> <number_of_clusters> <search_type>");
-    println!("\t<search_type>:");
-    println!("\t-> copkmeans | copkmeans_robust | local_search...");
-    println!("\t-> gguniform | ggsegment | gsuniform | gssegment");
-    println!("\t-> memeall   | memerandom | memeelitist");
-    println!("\t-> multistartlocalsearch | iterative_local_search | iterative_local_search_annealing | simulated_annealing");
+    println!("\t./PracticasMetaheuristicas <data_file> <constraints_file> <seed> <number_of_clusters> <search_type> [number_of_workers] [parametro=valor ...]");
+    println!("\t./PracticasMetaheuristicas --list: muestra solo los nombres de los algoritmos disponibles");
+    println!("\t[number_of_workers]: hilos para los algoritmos que aceptan el parametro number_of_workers (por defecto 1)");
+    println!("\t<search_type> y sus parametros:");
+    for metaheuristic in algorithms::all_metaheuristics(){
+        println!("\t-> {}: {}", metaheuristic.name(), metaheuristic.description());
+        for spec in metaheuristic.parameters(){
+            match spec.get_default(){
+                Some(default) => println!("\t\t{} = {}: {}", spec.get_name(), default, spec.get_description()),
+                None => println!("\t\t{}: {}", spec.get_name(), spec.get_description()),
+            }
+        }
+    }
 }
 
 fn main() {
 
+    // Listamos los algoritmos disponibles, uno por linea, para que los scripts puedan consultarlos
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 2 && args[1] == "--list"{
+        for metaheuristic in algorithms::all_metaheuristics(){
+            println!("{}", metaheuristic.name());
+        }
+        return;
+    }
+
     // Argumentos del programa que recibimos de la terminal
     let program_arguments = match arg_parser::ProgramParameters::new(){
         Ok(value) => value,
@@ -43,6 +45,32 @@ fn main() {
         }
     };
 
+    // Buscamos el algoritmo que quiere lanzar el usuario
+    let metaheuristic = match algorithms::find_metaheuristic(&program_arguments.get_search_type()){
+        Some(value) => value,
+        None => {
+            eprintln!("No existe ningun algoritmo de busqueda llamado {}", program_arguments.get_search_type());
+            show_help();
+            exit(-1);
+        }
+    };
+
+    // Comprobamos los parametros del algoritmo antes de cargar los datos
+    if let Err(err) = program_arguments.get_parameters().with_defaults(&metaheuristic.parameters()){
+        eprintln!("Parametros no validos para la busqueda {}", metaheuristic.name());
+        eprintln!("[Errcode]: {}", err);
+        show_help();
+        exit(-1);
+    }
+
+    // El numero de hilos se pasa como parametro a los algoritmos que lo aceptan, salvo que ya se
+    // haya dado explicitamente como parametro
+    let mut params = program_arguments.get_parameters().clone();
+    let accepts_workers = metaheuristic.parameters().iter().any(|spec| spec.get_name() == "number_of_workers");
+    if accepts_workers == true && params.contains("number_of_workers") == false{
+        params.insert("number_of_workers", &program_arguments.get_number_of_workers().to_string());
+    }
+
     // Parseamos los datos del archivo de datos
     let data_points = match file_parsers::parse_data_file_to_struct(&program_arguments.get_data_file()){
         Ok(value) => value,
@@ -68,72 +96,22 @@ fn main() {
     println!("\tConstraints file: {}", program_arguments.get_constraints_file());
     println!("\tNumber of clusters: {}", program_arguments.get_number_of_clusters());
     println!("\tSeed: {}", program_arguments.get_seed());
-    println!("\tSearch type: {:?}", program_arguments.get_search_type());
+    println!("\tSearch type: {}", program_arguments.get_search_type());
+    println!("\tNumber of workers: {}", program_arguments.get_number_of_workers());
     println!("================================================================================");
-    println!("");
+    println!();
+
+    // Agrupamos los datos del problema en una instancia compartida por todas las soluciones
+    let instance = Arc::new(problem_datatypes::ProblemInstance::new(data_points, constraints, program_arguments.get_number_of_clusters()));
 
     // Tomamos un generador de numeros aleatorios, que debe ser una referencia
     // mutable para poder generar numeros aleatorios
     let mut rng = StdRng::seed_from_u64(program_arguments.get_seed());
 
-    // Miramos que busqueda quiere realizar el usuario
-    match program_arguments.get_search_type(){
-        arg_parser::SearchType::Copkmeans => {
-            let robust = false;
-            copkmeans::run_and_show_results(&data_points, &constraints, program_arguments, &mut rng, robust);
-        }
-
-        arg_parser::SearchType::CopkmeansRobust => {
-            let robust = true;
-            copkmeans::run_and_show_results(&data_points, &constraints, program_arguments, &mut rng, robust);
-        }
-
-        arg_parser::SearchType::LocalSearch => {
-            local_search::run_and_show_results(&data_points, &constraints, program_arguments, &mut rng);
-        }
-
-        arg_parser::SearchType::GenerationalGeneticUniform => {
-            let cross_uniform = true;
-            generational_genetic::run_and_show_results(&data_points, &constraints, program_arguments, cross_uniform, &mut rng);
-        }
-
-        arg_parser::SearchType::GenerationalGeneticSegment => {
-            let cross_uniform = false; // No usamos cruce uniforme, sino segmento fijo
-            generational_genetic::run_and_show_results(&data_points, &constraints, program_arguments, cross_uniform, &mut rng);
-        }
-
-        arg_parser::SearchType::SteadyGeneticUniform => {
-            let cross_uniform = true;
-            steady_genetic::run_and_show_results(&data_points, &constraints, program_arguments, cross_uniform, &mut rng);
-
-        }
-
-        arg_parser::SearchType::SteadyGeneticSegment => {
-            let cross_uniform = false;
-            steady_genetic::run_and_show_results(&data_points, &constraints, program_arguments, cross_uniform, &mut rng);
-        }
-
-        arg_parser::SearchType::MemeticAll | arg_parser::SearchType::MemeticRandom | arg_parser::SearchType::MemeticElitist => {
-            let memetic_type = program_arguments.get_search_type();
-            memetic::run_and_show_results(&data_points, &constraints, program_arguments, memetic_type, &mut rng);
-        }
-
-        arg_parser::SearchType::MultiStartLocalSearch => {
-            multilocal::run_and_show_results(&data_points, &constraints, program_arguments, &mut rng);
-        }
-
-        arg_parser::SearchType::IterativeLocalSearchBasic => {
-            let basic = true;
-            iterative_local_search::run_and_show_results(&data_points, &constraints, program_arguments, basic, &mut rng);
-        }
-
-        arg_parser::SearchType::IterativeLocalSearchSimulatedAnnealing => {
-            let basic = false;
-            iterative_local_search::run_and_show_results(&data_points, &constraints, program_arguments, basic, &mut rng);
-        }
-
-        arg_parser::SearchType::SimulatedAnnealing => {
-            simulated_annealing::run_and_show_results(&data_points, &constraints, program_arguments, &mut rng);
-        }
+    // Lanzamos la busqueda que quiere realizar el usuario
+    if let Err(err) = metaheuristic.run_and_show_results(&instance, &params, &mut rng){
+        eprintln!("No se pudo realizar la busqueda {}", metaheuristic.name());
+        eprintln!("[Errcode]: {}", err);
+        exit(-1);
     }
 }
@@ -0,0 +1,207 @@
+use crate::problem_datatypes::ProblemInstance;
+use crate::run_result::RunResult;
+use crate::utils;
+
+use rand::rngs::StdRng;
+use simple_error::bail;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Interfaz comun a todos los algoritmos de busqueda
+///
+/// Cada algoritmo se identifica por un nombre (el que se usa en la linea de comandos), describe
+/// los parametros que acepta y se lanza con unos Parameters. Los algoritmos disponibles se
+/// registran en crate::algorithms::all_metaheuristics, y tanto el binario como los usuarios de la
+/// biblioteca los buscan por nombre con crate::algorithms::find_metaheuristic
+pub trait Metaheuristic: Send + Sync{
+    /// Nombre con el que se selecciona el algoritmo
+    fn name(&self) -> &'static str;
+
+    /// Descripcion corta, que se muestra en la ayuda y al mostrar los resultados
+    fn description(&self) -> &'static str;
+
+    /// Parametros que acepta el algoritmo, con sus valores por defecto
+    fn parameters(&self) -> Vec<ParameterSpec>;
+
+    /// Lanza el algoritmo sobre la instancia dada
+    /// Los parametros que no se especifiquen toman su valor por defecto. Se devuelve error si
+    /// algun parametro no existe o no tiene un valor valido, o si el algoritmo no encuentra
+    /// solucion
+    fn run(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<RunResult, Box<dyn Error>>;
+
+    /// Lanza el algoritmo y muestra los resultados por pantalla, guardando la evolucion del
+    /// fitness en un fichero de numpy
+    /// Los algoritmos que quieran mostrar informacion adicional pueden reimplementarlo
+    fn run_and_show_results(&self, instance: &Arc<ProblemInstance>, params: &Parameters, rng: &mut StdRng) -> Result<(), Box<dyn Error>>{
+        let result = self.run(instance, params, rng)?;
+        show_results(self.name(), self.description(), &result);
+        return Ok(());
+    }
+}
+
+/// Muestra por pantalla el resultado de un algoritmo y guarda su evolucion del fitness
+pub fn show_results(name: &str, description: &str, result: &RunResult){
+    println!("==> {}", description);
+    println!("La distancia global instracluster de la solucion es: {}", result.global_cluster_mean_distance());
+    println!("El numero de restricciones violadas es: {}", result.infeasibility());
+    println!("El valor de fitness es: {}", result.fitness());
+    println!("El valor de lambda es: {}", result.get_lambda());
+    println!("Tiempo transcurrido (segundos): {}", result.elapsed_seconds());
+    println!("Evaluaciones del fitness consumidas: {}", result.get_fitness_evaluations());
+    println!("Salvado del fitness: {:?}", result.get_fitness_evolution().save_as_numpy_file(&utils::generate_file_name(name)));
+    println!();
+}
+
+/// Describe un parametro que acepta un algoritmo
+#[derive(Debug, Clone)]
+pub struct ParameterSpec{
+    name: &'static str,
+    description: &'static str,
+
+    /// Valor por defecto del parametro. Es None cuando el valor por defecto depende de la
+    /// instancia del problema (por ejemplo, del numero de puntos), en cuyo caso se explica en la
+    /// descripcion
+    default: Option<&'static str>,
+}
+
+impl ParameterSpec{
+    pub fn new(name: &'static str, description: &'static str, default: Option<&'static str>) -> Self{
+        return Self{name, description, default};
+    }
+
+    pub fn get_name(&self) -> &'static str{
+        return self.name;
+    }
+
+    pub fn get_description(&self) -> &'static str{
+        return self.description;
+    }
+
+    pub fn get_default(&self) -> Option<&'static str>{
+        return self.default;
+    }
+}
+
+/// Valores de los parametros con los que se lanza un algoritmo, indexados por nombre
+/// Los valores se guardan como strings, y se convierten al tipo adecuado al consultarlos
+#[derive(Debug, Clone, Default)]
+pub struct Parameters{
+    values: HashMap<String, String>,
+}
+
+impl Parameters{
+    /// Parametros vacios. Con ellos, el algoritmo usa todos sus valores por defecto
+    pub fn new() -> Self{
+        return Self{values: HashMap::new()};
+    }
+
+    /// Parsea parametros de la forma nombre=valor, tal y como se pasan por la linea de comandos
+    pub fn from_assignments(assignments: &[String]) -> Result<Self, Box<dyn Error>>{
+        let mut params = Self::new();
+        for assignment in assignments{
+            match assignment.split_once('='){
+                Some((name, value)) if name.is_empty() == false => params.insert(name, value),
+                _ => bail!("El parametro {} no tiene la forma nombre=valor", assignment),
+            }
+        }
+
+        return Ok(params);
+    }
+
+    /// Establece el valor de un parametro, sobreescribiendo el que tuviese
+    pub fn insert(&mut self, name: &str, value: &str){
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    /// Igual que insert, pero consumiendo y devolviendo los parametros, para poder encadenar
+    /// llamadas
+    pub fn with(mut self, name: &str, value: &str) -> Self{
+        self.insert(name, value);
+        return self;
+    }
+
+    pub fn contains(&self, name: &str) -> bool{
+        return self.values.contains_key(name);
+    }
+
+    /// Completa los parametros con los valores por defecto de specs
+    /// Devuelve error si alguno de los parametros no aparece en specs
+    pub fn with_defaults(&self, specs: &[ParameterSpec]) -> Result<Self, Box<dyn Error>>{
+        for name in self.values.keys(){
+            if specs.iter().any(|spec| spec.get_name() == name) == false{
+                bail!("El parametro {} no existe para este algoritmo", name);
+            }
+        }
+
+        let mut params = self.clone();
+        for spec in specs{
+            if let Some(default) = spec.get_default(){
+                if params.contains(spec.get_name()) == false{
+                    params.insert(spec.get_name(), default);
+                }
+            }
+        }
+
+        return Ok(params);
+    }
+
+    /// Devuelve el valor del parametro, o error si no esta o no se puede convertir a T
+    pub fn get<T>(&self, name: &str) -> Result<T, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.get_optional(name)?{
+            Some(value) => return Ok(value),
+            None => bail!("Falta el valor del parametro {}", name),
+        }
+    }
+
+    /// Devuelve el valor del parametro si esta, o error si no se puede convertir a T
+    /// Lo usamos para los parametros cuyo valor por defecto depende de la instancia
+    pub fn get_optional<T>(&self, name: &str) -> Result<Option<T>, Box<dyn Error>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = match self.values.get(name){
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match value.parse::<T>(){
+            Ok(value) => return Ok(Some(value)),
+            Err(err) => bail!("Valor {} no valido para el parametro {}: {}", value, name, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::metaheuristic::{Parameters, ParameterSpec};
+
+    #[test]
+    fn test_parameters_with_defaults(){
+        let specs = vec![
+            ParameterSpec::new("max_fitness_evaluations", "", Some("100000")),
+            ParameterSpec::new("max_fails", "", None),
+        ];
+
+        // Los valores dados tienen prioridad sobre los valores por defecto
+        let params = Parameters::from_assignments(&["max_fitness_evaluations=500".to_string()]).unwrap();
+        let params = params.with_defaults(&specs).unwrap();
+        assert_eq!(params.get::<u32>("max_fitness_evaluations").unwrap(), 500);
+        assert_eq!(params.get_optional::<i32>("max_fails").unwrap(), None);
+
+        let params = Parameters::new().with_defaults(&specs).unwrap();
+        assert_eq!(params.get::<u32>("max_fitness_evaluations").unwrap(), 100000);
+
+        // Parametros que no existen, mal formados o con valores no validos
+        assert!(Parameters::new().with("mu", "0.3").with_defaults(&specs).is_err());
+        assert!(Parameters::from_assignments(&["max_fails".to_string()]).is_err());
+        assert!(Parameters::new().with("max_fails", "abc").get::<i32>("max_fails").is_err());
+    }
+}
@@ -7,6 +7,7 @@ mod point;
 mod neighbour_generator;
 mod data_points;
 mod constraints;
+mod problem_instance;
 pub mod population;
 
 pub use solution::Solution;
@@ -14,3 +15,4 @@ pub use point::Point;
 pub use neighbour_generator::NeighbourGenerator;
 pub use data_points::DataPoints;
 pub use constraints::{Constraints, ConstraintType};
+pub use problem_instance::ProblemInstance;
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 #[derive(Debug, PartialEq)]
 pub enum ConstraintType {
@@ -14,9 +15,31 @@ pub enum ConstraintType {
 /// Las restricciones MustLink del tipo (i, i) no se almacenan al ser triviales
 /// Siempre vamos a trabajar con restricciones del tipo (i, j) con i < j para
 /// que sea mas facil de programar algunas funcionalidades
-#[derive(Debug)]
+///
+/// Ademas del hashmap, guardamos para cada punto la lista de puntos con los que tiene una
+/// restriccion. Esta lista se construye con Constraints::build_adjacency, tras haber añadido
+/// todas las restricciones. Si se añade una restriccion despues, la lista se vuelve a construir
+/// la siguiente vez que se consulta
+#[derive(Debug, Default)]
 pub struct Constraints{
     data: HashMap<(i32, i32), ConstraintType>,
+
+    /// Listas de adyacencia. Usamos OnceLock para poder construirlas bajo demanda desde una
+    /// referencia compartida, tambien desde varios hilos
+    adjacency: OnceLock<ConstraintAdjacency>,
+
+    /// Numero de puntos con el que se construyeron por ultima vez las listas de adyacencia
+    number_of_points: usize,
+}
+
+/// Listas de adyacencia comprimidas (formato CSR) de las restricciones de cada punto
+/// Los vecinos del punto i son neighbours[offsets[i] .. offsets[i + 1]]
+#[derive(Debug)]
+struct ConstraintAdjacency{
+    must_link_offsets: Vec<usize>,
+    must_link_neighbours: Vec<u32>,
+    cannot_link_offsets: Vec<usize>,
+    cannot_link_neighbours: Vec<u32>,
 }
 
 impl Constraints{
@@ -24,7 +47,7 @@ impl Constraints{
     /// Es importante usar las funcionalidades de la estructura para no introducir
     /// datos repetidos
     pub fn new() -> Self{
-        return Self{data: HashMap::new()};
+        return Self{data: HashMap::new(), adjacency: OnceLock::new(), number_of_points: 0};
     }
 
     /// Añadimos una restriccion, comprobando si ya estaba anteriormente inicializada
@@ -33,15 +56,113 @@ impl Constraints{
 
         // No añadimos las restricciones triviales MustLink
         if first_index == second_index{
-            return ();
+            return;
         }
 
         if self.has_element(first_index, second_index) == false {
             let (smaller, bigger) = Self::order_pair(first_index, second_index);
             self.data.insert((smaller, bigger), constraint_type);
+
+            // Las listas de adyacencia ya no reflejan todas las restricciones. Se reconstruyen la
+            // siguiente vez que se consulten
+            self.adjacency = OnceLock::new();
         }
     }
 
+    /// Construye las listas de adyacencia de cada punto a partir de las restricciones guardadas
+    /// Conviene llamarla una vez añadidas todas las restricciones. Si despues se añade otra
+    /// restriccion, las listas se reconstruyen bajo demanda con este mismo numero de puntos
+    pub fn build_adjacency(&mut self, number_of_points: usize){
+        self.number_of_points = number_of_points;
+        self.adjacency = OnceLock::from(self.compute_adjacency(number_of_points));
+    }
+
+    /// Listas de adyacencia actualizadas, construyendolas si hace falta. Si alguna restriccion
+    /// tiene un punto fuera del numero de puntos conocido, ampliamos las listas hasta el
+    fn adjacency(&self) -> &ConstraintAdjacency{
+        return self.adjacency.get_or_init(|| {
+            let max_index = self.data.keys().map(|(_, second)| *second as usize + 1).max().unwrap_or(0);
+            return self.compute_adjacency(self.number_of_points.max(max_index));
+        });
+    }
+
+    /// Calcula las listas de adyacencia en formato CSR para el numero de puntos dado
+    fn compute_adjacency(&self, number_of_points: usize) -> ConstraintAdjacency{
+        // Contamos cuantas restricciones de cada tipo tiene cada punto
+        let mut must_link_degree = vec![0; number_of_points];
+        let mut cannot_link_degree = vec![0; number_of_points];
+        for ((first, second), constraint_type) in self.data.iter(){
+            let degree = match constraint_type{
+                ConstraintType::MustLink => &mut must_link_degree,
+                ConstraintType::CannotLink => &mut cannot_link_degree,
+            };
+            degree[*first as usize] += 1;
+            degree[*second as usize] += 1;
+        }
+
+        let must_link_offsets = Self::degrees_to_offsets(&must_link_degree);
+        let cannot_link_offsets = Self::degrees_to_offsets(&cannot_link_degree);
+
+        // Rellenamos los vecinos de cada punto. Recorremos las claves ordenadas para que las
+        // listas no dependan del orden de iteracion del hashmap
+        let mut keys: Vec<&(i32, i32)> = self.data.keys().collect();
+        keys.sort();
+
+        let mut must_link_neighbours = vec![0; *must_link_offsets.last().unwrap()];
+        let mut cannot_link_neighbours = vec![0; *cannot_link_offsets.last().unwrap()];
+        let mut must_link_next = must_link_offsets.clone();
+        let mut cannot_link_next = cannot_link_offsets.clone();
+        for (first, second) in keys{
+            let (neighbours, next) = match self.data[&(*first, *second)]{
+                ConstraintType::MustLink => (&mut must_link_neighbours, &mut must_link_next),
+                ConstraintType::CannotLink => (&mut cannot_link_neighbours, &mut cannot_link_next),
+            };
+
+            neighbours[next[*first as usize]] = *second as u32;
+            next[*first as usize] += 1;
+            neighbours[next[*second as usize]] = *first as u32;
+            next[*second as usize] += 1;
+        }
+
+        return ConstraintAdjacency{
+            must_link_offsets,
+            must_link_neighbours,
+            cannot_link_offsets,
+            cannot_link_neighbours,
+        };
+    }
+
+    /// Indica si las listas de adyacencia estan construidas y actualizadas
+    pub fn has_adjacency(&self) -> bool{
+        return self.adjacency.get().is_some();
+    }
+
+    /// Devuelve los puntos con los que point_index tiene una restriccion MustLink
+    /// Un punto que no aparece en ninguna restriccion no tiene vecinos
+    pub fn must_link_neighbours(&self, point_index: usize) -> &[u32]{
+        let adjacency = self.adjacency();
+        if point_index + 1 >= adjacency.must_link_offsets.len(){
+            return &[];
+        }
+
+        let start = adjacency.must_link_offsets[point_index];
+        let end = adjacency.must_link_offsets[point_index + 1];
+        return &adjacency.must_link_neighbours[start..end];
+    }
+
+    /// Devuelve los puntos con los que point_index tiene una restriccion CannotLink
+    /// Un punto que no aparece en ninguna restriccion no tiene vecinos
+    pub fn cannot_link_neighbours(&self, point_index: usize) -> &[u32]{
+        let adjacency = self.adjacency();
+        if point_index + 1 >= adjacency.cannot_link_offsets.len(){
+            return &[];
+        }
+
+        let start = adjacency.cannot_link_offsets[point_index];
+        let end = adjacency.cannot_link_offsets[point_index + 1];
+        return &adjacency.cannot_link_neighbours[start..end];
+    }
+
     // Comprueba si tenemos el elemento dado por los indices
     // A mano se comprueba que (a, b) == (b, a) a la hora de mirar las claves
     pub fn has_element(&self, first_index: i32, second_index: i32) -> bool{
@@ -66,6 +187,18 @@ impl Constraints{
         return &self.data;
     }
 
+    /// A partir del numero de vecinos de cada punto, calcula las posiciones en las que empiezan
+    /// los vecinos de cada punto en el vector comprimido. Tiene un elemento mas que puntos
+    fn degrees_to_offsets(degrees: &Vec<usize>) -> Vec<usize>{
+        let mut offsets = Vec::with_capacity(degrees.len() + 1);
+        offsets.push(0);
+        for degree in degrees{
+            offsets.push(offsets.last().unwrap() + degree);
+        }
+
+        return offsets;
+    }
+
     /// Toma dos enteros y devuelve el par ordenado en orden ascendente
     fn order_pair(first: i32, second: i32) -> (i32, i32){
         if first < second {
@@ -109,6 +242,28 @@ mod test{
         assert_eq!(calc_inside, exp_inside);
     }
 
+    #[test]
+    // Añadir restricciones despues de construir las listas de adyacencia no debe hacer fallar las
+    // consultas: las listas se reconstruyen con el numero de puntos con el que se construyeron
+    fn test_adjacency_rebuilt_after_adding_constraints(){
+        let mut constraints = Constraints::new();
+        constraints.add_constraint(0, 1, ConstraintType::MustLink);
+        constraints.build_adjacency(5);
+        assert_eq!(constraints.must_link_neighbours(1), &[0]);
+
+        constraints.add_constraint(1, 3, ConstraintType::CannotLink);
+        assert!(constraints.has_adjacency() == false);
+        assert_eq!(constraints.cannot_link_neighbours(3), &[1]);
+        assert_eq!(constraints.must_link_neighbours(4).len(), 0);
+        assert!(constraints.has_adjacency() == true);
+
+        // Sin haber construido nunca las listas
+        let mut constraints = Constraints::new();
+        constraints.add_constraint(2, 6, ConstraintType::MustLink);
+        assert_eq!(constraints.must_link_neighbours(6), &[2]);
+        assert_eq!(constraints.cannot_link_neighbours(10).len(), 0);
+    }
+
     #[test]
     fn test_correct_returned_constraints(){
         let mut constraints = Constraints::new();
@@ -151,4 +306,22 @@ mod test{
         }
     }
 
+    #[test]
+    fn test_adjacency_lists(){
+        let mut constraints = Constraints::new();
+        constraints.add_constraint(0, 1, ConstraintType::MustLink);
+        constraints.add_constraint(3, 1, ConstraintType::MustLink);
+        constraints.add_constraint(3, 4, ConstraintType::CannotLink);
+        constraints.add_constraint(0, 4, ConstraintType::CannotLink);
+        constraints.build_adjacency(6);
+
+        assert_eq!(constraints.must_link_neighbours(0), &[1]);
+        assert_eq!(constraints.must_link_neighbours(1), &[0, 3]);
+        assert_eq!(constraints.must_link_neighbours(3), &[1]);
+        assert_eq!(constraints.must_link_neighbours(4), &[] as &[u32]);
+        assert_eq!(constraints.cannot_link_neighbours(4), &[0, 3]);
+        assert_eq!(constraints.cannot_link_neighbours(0), &[4]);
+        assert_eq!(constraints.cannot_link_neighbours(5), &[] as &[u32]);
+    }
+
 }
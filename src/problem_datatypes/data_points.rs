@@ -15,13 +15,17 @@ impl DataPoints {
         return self.points.len();
     }
 
+    pub fn is_empty(&self) -> bool{
+        return self.points.len() == 0;
+    }
+
     /// Devuelve la dimension de los puntos del problema
     /// Es decir, el numero de coordenadas de cada punto
     // TODO -- es necesaria esta funcion? Porque podria usar directamente el punto primero
     // de la estructura
     pub fn point_dimension(&self) -> Option<usize>{
         // No tenemos puntos para decir cual es su dimension
-        if self.points.len() == 0{
+        if self.points.is_empty() == true{
             return None;
         }
 
@@ -55,7 +55,7 @@ impl Point {
     /// El vector de puntos debe tener al menos un punto, en otro caso hace panic!
     pub fn calculate_centroid(points: &Vec<&Self>) -> Self{
         // Condicion de seguridad
-        if points.len() == 0{
+        if points.is_empty() == true{
             panic!("No se puede calcular el centroide de un conjunto vacio de puntos")
         }
 
@@ -64,15 +64,15 @@ impl Point {
 
         // Calculamos el centroide
         for point in points{
-            sum_point = sum_point + &point.coordinates;
+            sum_point += &point.coordinates;
         }
-        sum_point = sum_point / points.len() as f64;
+        sum_point /= points.len() as f64;
 
         return Self{coordinates: sum_point};
     }
 
     /// Dado un conjunto de puntos, calcula la maxima distancia entre dos de ellos
-    pub fn max_distance_among_two(points: &Vec<Point>) -> f64{
+    pub fn max_distance_among_two(points: &[Point]) -> f64{
         let mut max_dist = 0.0;
 
         for i in 0 .. points.len(){
@@ -89,6 +89,11 @@ impl Point {
 
     }
 
+    /// Devuelve una referencia a las coordenadas del punto
+    pub fn get_coordinates(&self) -> &ndarray::Array1<f64>{
+        return &self.coordinates;
+    }
+
     /// Devuelve la dimension del punto
     /// Es decir, el numero de coordenadas del punto
     pub fn dimension(&self) -> usize {
@@ -108,7 +113,6 @@ mod tests{
 
     // Para comprobar que dos soluciones son practicamente iguales (ignorando problemas
     // del punto flotante)
-    use assert_approx_eq::assert_approx_eq;
     use rand::rngs::StdRng;
     use rand::SeedableRng;
 
@@ -121,20 +125,20 @@ mod tests{
             first: Point,
             second: Point,
             expected_distance: f64
-        };
+        }
 
 
         let test_cases = vec![
             TestCase{
                 first: Point::from_vec(vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
                 second: Point::from_vec(vec![0.0, 1.0, 0.0, 0.0, 0.0, 0.0]),
-                expected_distance: 1.4142135623730951
+                expected_distance: std::f64::consts::SQRT_2
             },
 
             TestCase{
                 first: Point::from_vec(vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
                 second: Point::from_vec(vec![0.5, 0. , 0. , 0. , 0.5, 0. ]),
-                expected_distance: 0.7071067811865476
+                expected_distance: std::f64::consts::FRAC_1_SQRT_2
             }
         ];
 
@@ -1,30 +1,198 @@
 use crate::problem_datatypes::Solution;
-use crate::problem_datatypes::DataPoints;
-use crate::problem_datatypes::Constraints;
-use crate::fitness_evaluation_result::FitnessEvaluationResult;
-use crate::arg_parser::SearchType;
+use crate::problem_datatypes::ProblemInstance;
+use crate::evaluator::Evaluator;
+use crate::utils;
+use crate::diversity_evolution::DiversityMetrics;
 
 use rand::Rng;
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use simple_error::SimpleError;
 use std::io::{stdin, stdout, Read, Write};
+use std::str::FromStr;
 
 // Para usar una cola con prioridad
 use priority_queue::PriorityQueue;
 use ordered_float::OrderedFloat;
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
+/// Peso en la ruleta del peor individuo de la poblacion, relativo al rango de pesos [0, 1] que
+/// reparte el fitness (ver inverted_fitness_weights)
+const ROULETTE_MIN_WEIGHT: f64 = 0.05;
+
+/// Criterio con el que los algoritmos memeticos escogen a que individuos de la poblacion aplicar
+/// la busqueda local suave
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemeticType{
+    /// A todos los individuos
+    All,
+
+    /// A un porcentaje de individuos escogidos aleatoriamente
+    Random,
+
+    /// A un porcentaje de los mejores individuos
+    Elitist,
+}
+
+/// Operador de cruce con el que los algoritmos geneticos y memeticos cruzan a los individuos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossoverOperator{
+    /// Cruce uniforme, posicion a posicion (ver Solution::uniform_cross)
+    Uniform,
+
+    /// Cruce por segmento fijo (ver Solution::cross_segment)
+    Segment,
+
+    /// Cruce uniforme tras alinear las etiquetas de los clusters de los padres
+    AlignedUniform,
+
+    /// Cruce por segmento fijo tras alinear las etiquetas de los clusters de los padres
+    AlignedSegment,
+
+    /// Cruce orientado a grupos, que hereda clusters completos (ver Solution::group_cross)
+    Group,
+}
+
+impl CrossoverOperator{
+    /// Cruza dos padres, generando un hijo que toma como base al primero
+    pub fn cross(&self, first: &Solution, second: &Solution, rng: &mut StdRng) -> Solution{
+        match self{
+            CrossoverOperator::Uniform => return Solution::uniform_cross(first, second, rng),
+            CrossoverOperator::Segment => return Solution::cross_segment(first, second, rng),
+            CrossoverOperator::AlignedUniform => return Solution::aligned_uniform_cross(first, second, rng),
+            CrossoverOperator::AlignedSegment => return Solution::aligned_cross_segment(first, second, rng),
+            CrossoverOperator::Group => return Solution::group_cross(first, second, rng),
+        }
+    }
+}
+
+impl FromStr for CrossoverOperator{
+    type Err = SimpleError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        match name{
+            "uniform" => return Ok(CrossoverOperator::Uniform),
+            "segment" => return Ok(CrossoverOperator::Segment),
+            "aligned_uniform" => return Ok(CrossoverOperator::AlignedUniform),
+            "aligned_segment" => return Ok(CrossoverOperator::AlignedSegment),
+            "group" => return Ok(CrossoverOperator::Group),
+            _ => return Err(SimpleError::new(format!("el operador de cruce {} no existe", name))),
+        }
+    }
+}
+
+/// Operador de seleccion con el que los algoritmos geneticos escogen a los padres
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionOperator{
+    /// Torneo entre el numero de candidatos dado, escogidos aleatoriamente con reemplazamiento.
+    /// Con dos candidatos es el torneo binario clasico
+    Tournament(usize),
+
+    /// Seleccion por ranking lineal con la presion selectiva dada, entre 1 y 2: es el numero
+    /// esperado de copias del mejor individuo, y el peor tiene 2 - presion copias esperadas
+    LinearRank(f64),
+
+    /// Seleccion por ruleta, proporcional al fitness invertido
+    Roulette,
+
+    /// Muestreo universal estocastico, con los mismos pesos que la ruleta pero girandola una
+    /// unica vez con tantos punteros equiespaciados como individuos escogemos
+    StochasticUniversal,
+}
+
+impl SelectionOperator{
+    /// Construye el operador a partir de su nombre: binary_tournament, tournament, linear_rank,
+    /// roulette o sus. El tamaño del torneo solo se usa en tournament y la presion selectiva solo
+    /// en linear_rank
+    pub fn from_name(name: &str, tournament_size: usize, selection_pressure: f64) -> Result<Self, SimpleError>{
+        match name{
+            "binary_tournament" => return Ok(SelectionOperator::Tournament(2)),
+            "tournament" => {
+                if tournament_size == 0{
+                    return Err(SimpleError::new("el torneo debe tener al menos un candidato"));
+                }
+                return Ok(SelectionOperator::Tournament(tournament_size));
+            },
+            "linear_rank" => {
+                if (1.0..=2.0).contains(&selection_pressure) == false{
+                    return Err(SimpleError::new(format!("la presion selectiva {} no esta entre 1 y 2", selection_pressure)));
+                }
+                return Ok(SelectionOperator::LinearRank(selection_pressure));
+            },
+            "roulette" => return Ok(SelectionOperator::Roulette),
+            "sus" => return Ok(SelectionOperator::StochasticUniversal),
+            _ => return Err(SimpleError::new(format!("el operador de seleccion {} no existe", name))),
+        }
+    }
+
+    /// Maximo de evaluaciones del fitness que puede consumir seleccionar new_population_size
+    /// individuos de una poblacion de population_size individuos. El torneo solo evalua a los
+    /// candidatos, el resto de operadores necesitan a toda la poblacion evaluada
+    pub fn max_evaluations(&self, new_population_size: usize, population_size: usize) -> usize{
+        match self{
+            SelectionOperator::Tournament(size) => return population_size.min(size * new_population_size),
+            _ => return population_size,
+        }
+    }
+}
+
+/// Estrategia con la que los hijos del genetico estacionario entran en la poblacion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplacementStrategy{
+    /// Cada hijo sustituye al peor individuo de la poblacion si es mejor que el
+    Worst,
+
+    /// Cada hijo sustituye a un individuo escogido aleatoriamente, salvo al mejor de la poblacion
+    Random,
+
+    /// Cada hijo sustituye al individuo que lleva mas tiempo en la poblacion, salvo al mejor
+    Oldest,
+
+    /// Crowding determinista: emparejamos a cada hijo con el padre al que mas se parece, y el hijo
+    /// sustituye a ese padre si es mejor que el
+    DeterministicCrowding,
+
+    /// Torneo restringido: cada hijo compite con el individuo mas parecido de entre el numero
+    /// dado de individuos escogidos aleatoriamente, y lo sustituye si es mejor que el
+    RestrictedTournament(usize),
+
+    /// Como Worst, pero los hijos que ya estan en la poblacion se descartan sin evaluarlos
+    WorstWithoutDuplicates,
+}
+
+impl ReplacementStrategy{
+    /// Construye la estrategia a partir de su nombre: worst, random, oldest, crowding,
+    /// restricted_tournament o worst_without_duplicates. El tamaño de la ventana solo se usa en
+    /// restricted_tournament
+    pub fn from_name(name: &str, window_size: usize) -> Result<Self, SimpleError>{
+        match name{
+            "worst" => return Ok(ReplacementStrategy::Worst),
+            "random" => return Ok(ReplacementStrategy::Random),
+            "oldest" => return Ok(ReplacementStrategy::Oldest),
+            "crowding" => return Ok(ReplacementStrategy::DeterministicCrowding),
+            "restricted_tournament" => {
+                if window_size == 0{
+                    return Err(SimpleError::new("la ventana del torneo restringido debe tener al menos un individuo"));
+                }
+                return Ok(ReplacementStrategy::RestrictedTournament(window_size));
+            },
+            "worst_without_duplicates" => return Ok(ReplacementStrategy::WorstWithoutDuplicates),
+            _ => return Err(SimpleError::new(format!("la estrategia de reemplazo {} no existe", name))),
+        }
+    }
+}
 
 /// Representa una poblacion para los algoritmos geneticos
 #[derive(Debug, Clone)]
-pub struct Population<'a, 'b>{
+pub struct Population{
     /// Individuos de la poblacion
-    individuals: Vec<Solution<'a, 'b> >,
+    individuals: Vec<Solution>,
 }
 
 /// Implementacion para la parte de los algoritmos geneticos
-impl<'a, 'b> Population<'a, 'b>{
+impl Population{
 
     /// Genera una poblacion vacia, sin individuos
     pub fn new_empty_population() -> Self{
@@ -34,80 +202,101 @@ impl<'a, 'b> Population<'a, 'b>{
     }
 
     /// Genera una nueva poblacion aleatoria
-    pub fn new_random_population(data_points: &'a DataPoints, constraints: &'b Constraints, number_of_clusters: i32, population_size: i32, rng: &mut StdRng) -> Self{
+    pub fn new_random_population(instance: &Arc<ProblemInstance>, population_size: i32, rng: &mut StdRng) -> Self{
         let mut rand_population = Self{individuals: vec![]};
 
         // Añadimos las soluciones aleatorias a la poblacion
         for _ in 0..population_size{
-            let new_individual = Solution::generate_random_solution(data_points, constraints, number_of_clusters, rng);
+            let new_individual = Solution::generate_random_solution(instance, rng);
             rand_population.individuals.push(new_individual);
         }
 
         return rand_population;
     }
 
+    /// Genera una poblacion con los individuos dados
+    pub fn from_individuals(individuals: Vec<Solution>) -> Self{
+        return Self{individuals};
+    }
+
+    /// Consume la poblacion, devolviendo sus individuos
+    pub fn into_individuals(self) -> Vec<Solution>{
+        return self.individuals;
+    }
+
     /// Devuelve el numero de individuos de nuestra poblacion
     pub fn population_size(&self) -> usize{
         return self.individuals.len();
     }
 
-    pub fn get_individual(&self, index: usize) -> &Solution<'a, 'b>{
+    pub fn get_individual(&self, index: usize) -> &Solution{
         return &self.individuals[index];
     }
 
     /// Devuelve la mejor solucion de la poblacion y el indice en el que se encuentra
-    /// Debe haber al menos un individuo en la poblacion
-    pub fn get_best_individual(&self) -> FitnessEvaluationResult<(&Solution<'a, 'b>, u32)>{
-
-        let mut fit_eval_consumed = 0;
-
-        // Comprobacion inicial de seguridad
-        assert!(self.population_size() > 0, "La poblacion no puede ser nula en get_best_individual");
-
-        let (mut best_fitness, fit_cons) = self.individuals[0].fitness_and_consumed();
-        fit_eval_consumed += fit_cons;
-        let mut best_index = 0;
+    /// Los individuos que el evaluador no puede evaluar, por haber agotado las evaluaciones, no se
+    /// tienen en cuenta. Debe haber al menos un individuo evaluado en la poblacion
+    pub fn get_best_individual(&self, evaluator: &Evaluator) -> (&Solution, u32){
+        let mut best: Option<(usize, f64)> = None;
 
         for (index, individual) in self.individuals.iter().enumerate(){
-            let (individual_fitness, fit_cons) = individual.fitness_and_consumed();
-            fit_eval_consumed += fit_cons;
-            if individual_fitness < best_fitness{
-                best_index = index;
-                best_fitness = individual.fitness();
+            let individual_fitness = match evaluator.fitness(individual){
+                Some(fitness) => fitness,
+                None => continue,
+            };
+
+            match best{
+                Some((_, best_fitness)) if individual_fitness >= best_fitness => (),
+                _ => best = Some((index, individual_fitness)),
             }
         }
 
-        return FitnessEvaluationResult::new((self.get_individual(best_index), best_index as u32), fit_eval_consumed);
+        let (best_index, _) = best.expect("La poblacion debe tener al menos un individuo evaluado en get_best_individual");
+        return (self.get_individual(best_index), best_index as u32);
+    }
+
+    /// Solucion final de los algoritmos poblacionales: el mejor individuo evaluado de la
+    /// poblacion. Si no se ha podido evaluar ningun individuo, por no haber evaluaciones del
+    /// fitness, devolvemos el primer individuo reparado, sin evaluar
+    pub fn best_solution(&self, evaluator: &Evaluator, rng: &mut StdRng) -> Solution{
+        if self.individuals.iter().any(|individual| individual.is_fitness_cached() == true){
+            return self.get_best_individual(evaluator).0.clone();
+        }
+
+        let mut solution = self.individuals[0].clone();
+        if solution.is_valid() == false{
+            solution.repair_solution(rng);
+        }
+        return solution;
     }
 
     /// Calcula el indice del individuo de la poblacion con peor fitness
-    /// Debe haber al menos un individuo en la poblacion
-    pub fn get_index_worst_individual(&self) -> FitnessEvaluationResult<usize>{
+    /// Los individuos que no se pueden evaluar se consideran los peores, pues no sabemos nada de
+    /// ellos. Debe haber al menos un individuo en la poblacion
+    pub fn get_index_worst_individual(&self, evaluator: &Evaluator) -> usize{
         // Comprobacion inicial de seguridad
         debug_assert!(self.population_size() > 0, "La poblacion no puede ser nula en get_index_worst_individual");
 
-        let mut fit_eval_consumed = 0;
-
-        let (mut worst_fitness, fit_cons) = self.individuals[0].fitness_and_consumed();
-        fit_eval_consumed += fit_cons;
-        let mut worst_index = 0;
+        let mut worst: Option<(usize, f64)> = None;
 
         for (index, individual) in self.individuals.iter().enumerate(){
-            let (individual_fitness, fit_cons) = individual.fitness_and_consumed();
-            fit_eval_consumed += fit_cons;
-
-            if individual_fitness > worst_fitness{
-                worst_index = index;
-                worst_fitness = individual_fitness;
+            let individual_fitness = match evaluator.fitness(individual){
+                Some(fitness) => fitness,
+                None => return index,
+            };
+
+            match worst{
+                Some((_, worst_fitness)) if individual_fitness <= worst_fitness => (),
+                _ => worst = Some((index, individual_fitness)),
             }
         }
 
-        return FitnessEvaluationResult::new(worst_index, fit_eval_consumed);
+        return worst.map(|(index, _)| index).unwrap_or(0);
     }
 
     /// Modifica el individuo en una posicion dada
     /// 0 <= index < population_size para que no de errores
-    pub fn set_individual(&mut self, index: usize, individual: Solution<'a, 'b>){
+    pub fn set_individual(&mut self, index: usize, individual: Solution){
         self.individuals[index] = individual;
     }
 
@@ -115,62 +304,160 @@ impl<'a, 'b> Population<'a, 'b>{
     /// partir de repetir new_population_size veces un torneo binario
     /// Los valores comunes para new_population_size son o bien el tamaño de la poblacion pasada o
     /// bien 2, para el modelo estacionario
-    pub fn select_population_binary_tournament(&self, new_population_size: i32, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
+    pub fn select_population_binary_tournament(&self, new_population_size: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        return self.select_population_tournament(2, new_population_size, number_of_workers, evaluator, rng);
+    }
+
+    /// Genera una poblacion de seleccion de new_population_size individuos con el operador de
+    /// seleccion dado. Las evaluaciones del fitness que haga falta realizar se descuentan del
+    /// evaluador, y nunca superan selection.max_evaluations
+    pub fn select_population(&self, selection: SelectionOperator, new_population_size: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        match selection{
+            SelectionOperator::Tournament(size) => return self.select_population_tournament(size, new_population_size, number_of_workers, evaluator, rng),
+            SelectionOperator::LinearRank(pressure) => {
+                let weights = self.linear_rank_weights(pressure, number_of_workers, evaluator);
+                let selected = utils::roulette_indexes(&weights, new_population_size as usize, rng);
+                return self.select_indexes(&selected);
+            },
+            SelectionOperator::Roulette => {
+                let weights = self.inverted_fitness_weights(number_of_workers, evaluator);
+                let selected = utils::roulette_indexes(&weights, new_population_size as usize, rng);
+                return self.select_indexes(&selected);
+            },
+            SelectionOperator::StochasticUniversal => {
+                // Los punteros recorren la poblacion en orden, asi que barajamos a los
+                // seleccionados para que el cruce empareje individuos aleatorios
+                let weights = self.inverted_fitness_weights(number_of_workers, evaluator);
+                let mut selected = utils::stochastic_universal_indexes(&weights, new_population_size as usize, rng);
+                selected.shuffle(rng);
+                return self.select_indexes(&selected);
+            },
+        }
+    }
+
+    /// Seleccion por torneos de tournament_size candidatos. Gana el candidato con menor fitness;
+    /// los candidatos que no se han podido evaluar pierden contra los evaluados, y en caso de
+    /// empate gana el ultimo candidato sorteado. Con dos candidatos coincide con
+    /// Solution::binary_tournament
+    ///
+    /// Primero sorteamos todos los candidatos, despues evaluamos en paralelo a los candidatos que
+    /// no esten evaluados y por ultimo realizamos los torneos. Como sortear los candidatos no
+    /// depende del fitness, el resultado y las evaluaciones consumidas son los mismos que
+    /// realizando los torneos uno a uno
+    fn select_population_tournament(&self, tournament_size: usize, new_population_size: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
         let mut new_pop = Self::new_empty_population();
-        let mut fit_ev_consumed = 0;
 
-        // Añadimos individuos usando el torneo binario
+        // Sorteamos los candidatos que van a competir en cada torneo
+        let positions: Vec<usize> = (0..self.population_size()).collect();
+        let mut tournaments = vec![];
         for _ in 0..new_population_size{
+            let candidates: Vec<usize> = (0..tournament_size)
+                .map(|_| *positions.choose(rng).expect("La poblacion no puede estar vacia para hacer el torneo"))
+                .collect();
+            tournaments.push(candidates);
+        }
 
-            // Los dos individuos que van a competir en el torneo
-            let first_candidate = self.individuals.choose(rng).expect("La poblacion no puede estar vacia para hacer el tornero binario");
-            let second_candidate = self.individuals.choose(rng).expect("La poblacion no puede estar vacia para hacer el tornero binario");
+        // Evaluamos a los candidatos. Cada individuo solo se cuenta una vez, aunque participe en
+        // varios torneos, igual que ocurre con la cache del fitness
+        let mut candidates: Vec<usize> = tournaments.iter().flatten().copied().collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        self.evaluate_individuals(&candidates, number_of_workers, evaluator);
+
+        // Añadimos los ganadores de cada torneo
+        for candidates in tournaments{
+            let mut winner = candidates[0];
+            let mut winner_fitness = evaluator.fitness(&self.individuals[winner]);
+
+            for &candidate in candidates.iter().skip(1){
+                let candidate_fitness = evaluator.fitness(&self.individuals[candidate]);
+                let candidate_wins = match (winner_fitness, candidate_fitness){
+                    (Some(winner_fitness), Some(candidate_fitness)) => candidate_fitness <= winner_fitness,
+                    (None, Some(_)) => true,
+                    _ => false,
+                };
+
+                if candidate_wins == true{
+                    winner = candidate;
+                    winner_fitness = candidate_fitness;
+                }
+            }
 
-            // Seleccionamos el ganador
-            let (winner, fit_consumed) = Solution::binary_tournament(first_candidate, second_candidate);
-            new_pop.individuals.push(winner.clone());
-            fit_ev_consumed += fit_consumed;
+            new_pop.individuals.push(self.individuals[winner].clone());
         }
 
-        return FitnessEvaluationResult::new(new_pop, fit_ev_consumed);
+        return new_pop;
     }
 
-    /// Genera una poblacion de cruce a partir de una poblacion (que deberia ser de seleccion, pues
-    /// confiamos en que provenga de seleccion para que esto haya introducido ya la aleatoriedad)
-    /// La nueva poblacion tiene el mismo tamaño que la poblacion original
-    /// Se cruzan los primeros n elementos, este orden se considera aleatorio por venir de un
-    /// proceso de seleccion, que introduce aleatoriedad, como ya hemos comentado
-    pub fn cross_population_uniform(&self, crossover_probability: f64, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
-        // Partimos de una poblacion identica a la dada
-        let mut new_population = self.clone();
+    /// Evalua a toda la poblacion y devuelve el fitness de cada individuo, o None si no se ha
+    /// podido evaluar por haberse agotado las evaluaciones
+    fn evaluated_fitness(&self, number_of_workers: usize, evaluator: &Evaluator) -> Vec<Option<f64>>{
+        self.evaluate_all_individuals(number_of_workers, evaluator);
 
-        // Mutamos el numero de individuos que coincide con la esperanza matematica, para
-        // ahorrarnos evaluaciones de los numeros aleatorios
-        let inidividuals_to_cross = (crossover_probability * self.population_size() as f64) as usize;
-
-        // Cruzamos los inidividuals_to_cross primeros individos
-        let mut index = 0;
-        while index < inidividuals_to_cross - 1{
+        return self.individuals.iter().map(|individual| {
+            if individual.is_fitness_cached() == true{
+                return Some(individual.fitness());
+            }
+            return None;
+        }).collect();
+    }
 
-            // Tomamos los dos padres
-            let first_parent = new_population.individuals[index].clone();
-            let second_parent = new_population.individuals[index + 1].clone();
+    /// Pesos de la ruleta para minimizar el fitness. Escalamos el fitness al intervalo [min, max]
+    /// de la poblacion, de forma que el peso va de 1 + ROULETTE_MIN_WEIGHT para el mejor individuo
+    /// a ROULETTE_MIN_WEIGHT para el peor. Asi la seleccion no depende de la escala del fitness,
+    /// y el peor individuo conserva alguna probabilidad de ser escogido. Los individuos sin
+    /// evaluar tienen peso cero
+    fn inverted_fitness_weights(&self, number_of_workers: usize, evaluator: &Evaluator) -> Vec<f64>{
+        let fitness = self.evaluated_fitness(number_of_workers, evaluator);
+        let evaluated: Vec<f64> = fitness.iter().flatten().copied().collect();
+        let max_fitness = evaluated.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min_fitness = evaluated.iter().copied().fold(f64::INFINITY, f64::min);
+        let fitness_range = max_fitness - min_fitness;
+
+        let weights = fitness.iter().map(|fitness| match fitness{
+            // Si todos los individuos tienen el mismo fitness, tienen el mismo peso
+            Some(_) if fitness_range <= 0.0 => 1.0,
+            Some(fitness) => (max_fitness - fitness) / fitness_range + ROULETTE_MIN_WEIGHT,
+            None => 0.0,
+        }).collect();
+        return Self::uniform_if_degenerate(weights, &fitness);
+    }
 
-            // Generamos los dos hijos usando los dos padres
-            let first_child = Solution::uniform_cross(&first_parent, &second_parent, rng);
-            let second_child = Solution::uniform_cross(&second_parent, &first_parent, rng);
+    /// Pesos del ranking lineal: ordenamos a los individuos evaluados de mejor a peor, y el peso
+    /// baja linealmente desde pressure para el mejor hasta 2 - pressure para el peor. Los
+    /// individuos sin evaluar tienen peso cero
+    fn linear_rank_weights(&self, pressure: f64, number_of_workers: usize, evaluator: &Evaluator) -> Vec<f64>{
+        let fitness = self.evaluated_fitness(number_of_workers, evaluator);
+
+        let mut ranking: Vec<(usize, f64)> = fitness.iter().enumerate()
+            .filter_map(|(index, fitness)| fitness.map(|fitness| (index, fitness)))
+            .collect();
+        ranking.sort_by(|first, second| first.1.partial_cmp(&second.1).unwrap());
+
+        let mut weights = vec![0.0; self.population_size()];
+        let last_rank = ranking.len().saturating_sub(1).max(1) as f64;
+        for (rank, (index, _)) in ranking.iter().enumerate(){
+            weights[*index] = pressure - (2.0 * pressure - 2.0) * rank as f64 / last_rank;
+        }
+        return Self::uniform_if_degenerate(weights, &fitness);
+    }
 
-            // Sustituimos los dos individuos
-            new_population.individuals[index] = first_child;
-            new_population.individuals[index + 1] = second_child;
+    /// Si ningun individuo tiene peso, repartimos el peso por igual entre los individuos
+    /// evaluados, o entre todos si no hay ninguno evaluado
+    fn uniform_if_degenerate(weights: Vec<f64>, fitness: &[Option<f64>]) -> Vec<f64>{
+        if weights.iter().sum::<f64>() > 0.0{
+            return weights;
+        }
 
-            // Avanzamos la iteracion
-            index = index + 2;
+        if fitness.iter().any(|fitness| fitness.is_some()){
+            return fitness.iter().map(|fitness| if fitness.is_some() {1.0} else {0.0}).collect();
         }
+        return vec![1.0; fitness.len()];
+    }
 
-        // En esta parte, directamente no estamos haciendo evaluaciones del fitness
-        let fit_evals_consumed = 0;
-        return FitnessEvaluationResult::new(new_population, fit_evals_consumed);
+    /// Poblacion con copias de los individuos en las posiciones dadas
+    fn select_indexes(&self, indexes: &[usize]) -> Self{
+        return Self{individuals: indexes.iter().map(|&index| self.individuals[index].clone()).collect()};
     }
 
     /// Genera una poblacion de cruce a partir de una poblacion (que deberia ser de seleccion, pues
@@ -178,7 +465,21 @@ impl<'a, 'b> Population<'a, 'b>{
     /// La nueva poblacion tiene el mismo tamaño que la poblacion original
     /// Se cruzan los primeros n elementos, este orden se considera aleatorio por venir de un
     /// proceso de seleccion, que introduce aleatoriedad, como ya hemos comentado
-    pub fn cross_population_segment(&self, crossover_probability: f64, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
+    pub fn cross_population_uniform(&self, crossover_probability: f64, rng: &mut StdRng) -> Self{
+        return self.cross_population(CrossoverOperator::Uniform, crossover_probability, rng);
+    }
+
+    /// Genera una poblacion de cruce a partir de una poblacion (que deberia ser de seleccion, pues
+    /// confiamos en que provenga de seleccion para que esto haya introducido ya la aleatoriedad)
+    /// La nueva poblacion tiene el mismo tamaño que la poblacion original
+    /// Se cruzan los primeros n elementos, este orden se considera aleatorio por venir de un
+    /// proceso de seleccion, que introduce aleatoriedad, como ya hemos comentado
+    pub fn cross_population_segment(&self, crossover_probability: f64, rng: &mut StdRng) -> Self{
+        return self.cross_population(CrossoverOperator::Segment, crossover_probability, rng);
+    }
+
+    /// Genera una poblacion de cruce con el operador de cruce dado. Ver cross_population_uniform
+    pub fn cross_population(&self, crossover: CrossoverOperator, crossover_probability: f64, rng: &mut StdRng) -> Self{
         // Partimos de una poblacion identica a la dada
         let mut new_population = self.clone();
 
@@ -195,21 +496,19 @@ impl<'a, 'b> Population<'a, 'b>{
             let second_parent = new_population.individuals[index + 1].clone();
 
             // Generamos los dos hijos usando los dos padres
-            let first_child = Solution::cross_segment(&first_parent, &second_parent, rng);
-            let second_child = Solution::cross_segment(&second_parent, &first_parent, rng);
+            let first_child = crossover.cross(&first_parent, &second_parent, rng);
+            let second_child = crossover.cross(&second_parent, &first_parent, rng);
 
             // Sustituimos los dos individuos
             new_population.individuals[index] = first_child;
             new_population.individuals[index + 1] = second_child;
 
             // Avanzamos la iteracion
-            index = index + 2;
+            index += 2;
         }
 
         // En esta parte, directamente no estamos haciendo evaluaciones del fitness
-        let fit_evals_consumed = 0;
-        return FitnessEvaluationResult::new(new_population, fit_evals_consumed);
-
+        return new_population;
     }
 
     /// Mutamos una poblacion a partir de la poblacion que ya ha sido seleccionada y cruzada
@@ -221,7 +520,7 @@ impl<'a, 'b> Population<'a, 'b>{
         let mut new_pop = self.clone();
 
         // Posiciones sobre las que podemos elegir aleatoriamente
-        let positions: Vec<usize> = (0..self.population_size() as usize).collect();
+        let positions: Vec<usize> = (0..self.population_size()).collect();
 
         for _ in 0..individuals_to_mutate as usize{
             let random_index = *positions.choose(rng).expect("No se ha podido escoger un valor aleatorio");
@@ -258,42 +557,34 @@ impl<'a, 'b> Population<'a, 'b>{
     /// Dada una poblacion original, comprueba si el mejor individuo de la poblacion original esta
     /// en esta poblacion. En caso de que no este, se introduce en la nueva poblacion, en la
     /// posicion en la que estaba en la poblacion original
-    pub fn preserve_best_past_parent(&self, original_population: &Population<'a, 'b>) -> FitnessEvaluationResult<Self>{
+    pub fn preserve_best_past_parent(&self, original_population: &Population, evaluator: &Evaluator) -> Self{
         let mut new_pop = self.clone();
-        let mut fit_eval_cons = 0;
 
         // Tomamos el mejor individuo de la poblacion original
-        // Añadimos las iteraciones que consume esto, deberian ser cero pues esa poblacion ya
-        // deberia estar evaluada
-        let best_individual_at_original_pop_result= original_population.get_best_individual();
-        let (best_individual_at_original_pop, best_individual_index_original_pop) = best_individual_at_original_pop_result.get_result();
-        fit_eval_cons += best_individual_at_original_pop_result.get_iterations_consumed();
+        // No deberia consumir evaluaciones, pues esa poblacion ya deberia estar evaluada
+        let (best_individual_at_original_pop, best_individual_index_original_pop) = original_population.get_best_individual(evaluator);
 
         // Comprobamos si esta dentro de la poblacion
         // Esta operacion no consume iteraciones, porque solo estamos comprobando la igualdad entre
-        // vectores de posiciones
-        let search_result = self.search_individual_with_same_cluster_indixes(best_individual_at_original_pop.get_cluster_indexes());
-        match search_result{
-            // El mejor individuo pasado ha sobrevivido, devolvemos la poblacion sin modificar
-            // junto a las evaluaciones consumidas
-            Some(_) => return FitnessEvaluationResult::new(self.clone(), fit_eval_cons),
-
-            // No hemos encontrado el individuo, no hacemos nada, por lo que seguimos con el
-            // proceso de incluir el mejor individuo pasado en la poblacion
-            None => (),
-        };
+        // las formas canonicas de los vectores de posiciones
+        // Si el mejor individuo pasado ha sobrevivido, devolvemos la poblacion sin modificar. Si
+        // no, seguimos con el proceso de incluir el mejor individuo pasado en la poblacion
+        if self.search_same_individual(best_individual_at_original_pop).is_some() == true{
+            return self.clone();
+        }
 
         // El mejor individuo pasado no esta en la nueva poblacion, lo introducimos en su posicion
         // de la poblacion original en la nueva poblacion
-        new_pop.individuals[*best_individual_index_original_pop as usize] = (*best_individual_at_original_pop).clone();
-        return FitnessEvaluationResult::new(new_pop, fit_eval_cons);
+        new_pop.individuals[best_individual_index_original_pop as usize] = best_individual_at_original_pop.clone();
+        return new_pop;
     }
 
-    /// Busca el individuo en la poblacion con la misma asignacion de cluster
-    fn search_individual_with_same_cluster_indixes(&self, cluster_indixes: Vec<u32>) -> Option<u32>{
+    /// Busca el individuo en la poblacion que representa la misma agrupacion, aunque tenga otras
+    /// etiquetas para los clusters
+    fn search_same_individual(&self, searched: &Solution) -> Option<u32>{
         // Realizamos la busqueda
         for (index, individual) in self.individuals.iter().enumerate(){
-            if individual.get_cluster_indexes() == cluster_indixes{
+            if individual == searched{
                 return Some(index as u32)
             }
         }
@@ -307,34 +598,165 @@ impl<'a, 'b> Population<'a, 'b>{
     // candidata luchan contra los peores individuos de la poblacion original (&self) para quedarse
     // en dicha poblacion
     // La poblacion original no se modifica, se devuelve una copia con la poblacion resultante
-    pub fn compete_with_new_individuals(&self, candidate_population: &Population<'a, 'b>) -> FitnessEvaluationResult<Self>{
+    // Un candidato que no se puede evaluar, por haber agotado las evaluaciones, no entra en la
+    // poblacion
+    pub fn compete_with_new_individuals(&self, candidate_population: &Population, evaluator: &Evaluator) -> Self{
         let mut new_pop = self.clone();
-        let mut fit_eval_cons = 0;
 
         for candidate in candidate_population.individuals.iter(){
 
             // Tomamos el peor individuo de la poblacion
-            let worst_individual_result = new_pop.get_index_worst_individual();
-            let worst_individual_index = worst_individual_result.get_result();
-            fit_eval_cons += worst_individual_result.get_iterations_consumed();
-
-            // Evaluamos el fitness del peor individuo y del candidato. En ambos casos tenemos en
-            // cuenta las evaluaciones que esto puede suponer. El peor individuo deberia estar
-            // evaluado, mientras que el candidato no. Hacemos las dos cuentas por seguridad
-            let (worst_fitness, worst_it_cons) = new_pop.individuals[*worst_individual_index].fitness_and_consumed();
-            let (candidate_fitness, candidate_it_cons) = candidate.fitness_and_consumed();
-            fit_eval_cons += worst_it_cons + candidate_it_cons;
+            let worst_individual_index = new_pop.get_index_worst_individual(evaluator);
 
-            // Comprobacion de seguridad
-            debug_assert!(candidate_it_cons == 1, "El candidato debe tener el fitness sin evaluar, el valor de consumiciones es {}", candidate_it_cons);
+            // Evaluamos el fitness del peor individuo y del candidato. El peor individuo deberia
+            // estar evaluado, mientras que el candidato no
+            debug_assert!(candidate.is_fitness_cached() == false, "El candidato debe tener el fitness sin evaluar");
+            let candidate_fitness = match evaluator.fitness(candidate){
+                Some(fitness) => fitness,
+                None => continue,
+            };
 
             // Decidimos si el candidato entra o no en la poblacion
-            if candidate_fitness < worst_fitness {
-                new_pop.individuals[*worst_individual_index] = candidate.clone();
+            match evaluator.fitness(&new_pop.individuals[worst_individual_index]){
+                Some(worst_fitness) if candidate_fitness >= worst_fitness => (),
+                _ => new_pop.individuals[worst_individual_index] = candidate.clone(),
             }
         }
 
-        return FitnessEvaluationResult::new(new_pop, fit_eval_cons);
+        return new_pop;
+    }
+
+    /// Introduce a los individuos de la poblacion candidata en la poblacion original (&self)
+    /// siguiendo la estrategia de reemplazo dada. parents son los padres de los candidatos, en el
+    /// orden en que se cruzaron, y solo se usan en el crowding determinista
+    /// La poblacion original no se modifica, se devuelve una copia con la poblacion resultante
+    ///
+    /// Cada candidato consume como mucho una evaluacion del fitness. Un candidato que no se puede
+    /// evaluar, por haber agotado las evaluaciones, no entra en la poblacion
+    ///
+    /// Para reemplazar al mas antiguo, mantenemos a los individuos ordenados de mas antiguo a mas
+    /// nuevo: el candidato entra al final de la poblacion. En la poblacion inicial todos tienen la
+    /// misma edad, y tomamos como mas antiguos a los primeros
+    pub fn replace_with_new_individuals(
+        &self,
+        replacement: ReplacementStrategy,
+        parents: &Population,
+        candidate_population: &Population,
+        evaluator: &Evaluator,
+        rng: &mut StdRng
+    ) -> Self{
+        // Mantenemos el comportamiento de siempre del genetico estacionario
+        if replacement == ReplacementStrategy::Worst{
+            return self.compete_with_new_individuals(candidate_population, evaluator);
+        }
+
+        // En el crowding determinista, cada candidato compite con su padre mas parecido
+        let paired_parents = match replacement{
+            ReplacementStrategy::DeterministicCrowding => Self::pair_with_most_similar_parents(parents, candidate_population),
+            _ => vec![],
+        };
+
+        let mut new_pop = self.clone();
+        for (candidate_index, candidate) in candidate_population.individuals.iter().enumerate(){
+
+            // Descartamos a los duplicados antes de gastar una evaluacion en ellos
+            if replacement == ReplacementStrategy::WorstWithoutDuplicates && new_pop.search_same_individual(candidate).is_some(){
+                continue;
+            }
+
+            let candidate_fitness = match evaluator.fitness(candidate){
+                Some(fitness) => fitness,
+                None => continue,
+            };
+
+            match replacement{
+                ReplacementStrategy::Worst | ReplacementStrategy::WorstWithoutDuplicates => {
+                    let worst_individual_index = new_pop.get_index_worst_individual(evaluator);
+                    new_pop.replace_if_better(worst_individual_index, candidate, candidate_fitness, evaluator);
+                },
+
+                ReplacementStrategy::Random => {
+                    let (_, best_index) = new_pop.get_best_individual(evaluator);
+                    let replaceable: Vec<usize> = (0..new_pop.population_size()).filter(|&index| index != best_index as usize).collect();
+                    if let Some(&index) = replaceable.choose(rng){
+                        new_pop.individuals[index] = candidate.clone();
+                    }
+                },
+
+                ReplacementStrategy::Oldest => {
+                    let (_, best_index) = new_pop.get_best_individual(evaluator);
+                    let oldest_index = if best_index == 0 {1} else {0};
+                    if oldest_index < new_pop.population_size(){
+                        new_pop.individuals.remove(oldest_index);
+                        new_pop.individuals.push(candidate.clone());
+                    }
+                },
+
+                ReplacementStrategy::DeterministicCrowding => {
+                    // Si el padre ya no esta en la poblacion, compite con el individuo mas parecido
+                    let parent = &paired_parents[candidate_index];
+                    let parent_index = match new_pop.search_same_individual(parent){
+                        Some(index) => index as usize,
+                        None => new_pop.most_similar_individual(candidate, 0..new_pop.population_size()),
+                    };
+                    new_pop.replace_if_better(parent_index, candidate, candidate_fitness, evaluator);
+                },
+
+                ReplacementStrategy::RestrictedTournament(window_size) => {
+                    let window_size = window_size.min(new_pop.population_size());
+                    let window = rand::seq::index::sample(rng, new_pop.population_size(), window_size);
+                    let closest_index = new_pop.most_similar_individual(candidate, window.into_iter());
+                    new_pop.replace_if_better(closest_index, candidate, candidate_fitness, evaluator);
+                },
+            }
+        }
+
+        return new_pop;
+    }
+
+    /// El candidato sustituye al individuo en la posicion dada si tiene mejor fitness. Un
+    /// individuo que no se puede evaluar siempre se sustituye
+    fn replace_if_better(&mut self, index: usize, candidate: &Solution, candidate_fitness: f64, evaluator: &Evaluator){
+        match evaluator.fitness(&self.individuals[index]){
+            Some(fitness) if candidate_fitness >= fitness => (),
+            _ => self.individuals[index] = candidate.clone(),
+        }
+    }
+
+    /// Indice, de entre los dados, del individuo con menor distancia de particion al buscado. En
+    /// caso de empate, el primero. No consume evaluaciones del fitness
+    fn most_similar_individual(&self, searched: &Solution, indexes: impl Iterator<Item = usize>) -> usize{
+        return indexes
+            .min_by_key(|&index| self.individuals[index].partition_distance(searched))
+            .expect("Debe haber al menos un individuo con el que comparar");
+    }
+
+    /// Empareja a cada hijo con uno de sus padres para el crowding determinista. Los hijos se
+    /// generan por parejas a partir de dos padres consecutivos, y escogemos el emparejamiento de
+    /// la pareja que minimiza la suma de distancias entre padres e hijos. Si sobra un hijo, lo
+    /// emparejamos con el padre de su misma posicion
+    fn pair_with_most_similar_parents(parents: &Population, children: &Population) -> Vec<Solution>{
+        debug_assert!(parents.population_size() == children.population_size(), "Debe haber tantos padres como hijos en el crowding determinista");
+
+        let mut paired_parents = vec![];
+        for (parents, children) in parents.individuals.chunks(2).zip(children.individuals.chunks(2)){
+            if parents.len() < 2 || children.len() < 2{
+                paired_parents.push(parents[0].clone());
+                continue;
+            }
+
+            let straight = parents[0].partition_distance(&children[0]) + parents[1].partition_distance(&children[1]);
+            let crossed = parents[0].partition_distance(&children[1]) + parents[1].partition_distance(&children[0]);
+            if straight <= crossed{
+                paired_parents.push(parents[0].clone());
+                paired_parents.push(parents[1].clone());
+            }else{
+                paired_parents.push(parents[1].clone());
+                paired_parents.push(parents[0].clone());
+            }
+        }
+
+        return paired_parents;
     }
 
     /// Evaluamos a todos los individuos de la poblacion
@@ -345,16 +767,28 @@ impl<'a, 'b> Population<'a, 'b>{
     ///
     /// Notar que los elementos mutan, pero al estar usando un patron de mutabilidad interior, no
     /// tenemos un patron de mutabilidad interior, no hace falta pasar una referencia mutable
-    pub fn evaluate_all_individuals(&self) -> FitnessEvaluationResult<()>{
-        let mut fit_evals_consumed = 0;
-
-        for individual in &self.individuals{
-            let (_, ev_consumed) = individual.fitness_and_consumed();
-            fit_evals_consumed += ev_consumed;
-
-        }
+    ///
+    /// Los individuos sin evaluar se reparten entre number_of_workers hilos. El fitness de cada
+    /// individuo no depende del resto, asi que el resultado es el mismo que evaluando en secuencial
+    pub fn evaluate_all_individuals(&self, number_of_workers: usize, evaluator: &Evaluator){
+        let all_indixes: Vec<usize> = (0..self.population_size()).collect();
+        self.evaluate_individuals(&all_indixes, number_of_workers, evaluator);
+    }
 
-        return FitnessEvaluationResult::new((), fit_evals_consumed);
+    /// Evalua en paralelo los individuos en las posiciones dadas, que no deben repetirse
+    /// Solo se lanzan hilos para los individuos que no tienen el fitness cacheado
+    ///
+    /// Si no quedan evaluaciones para todos, se evaluan los primeros en el orden dado. Lo decidimos
+    /// antes de lanzar los hilos para que el resultado no dependa de que hilo evalua antes
+    fn evaluate_individuals(&self, indixes: &[usize], number_of_workers: usize, evaluator: &Evaluator){
+        let not_cached: Vec<&Solution> = indixes
+            .iter()
+            .map(|&index| &self.individuals[index])
+            .filter(|individual| individual.is_fitness_cached() == false)
+            .take(evaluator.remaining() as usize)
+            .collect();
+
+        utils::parallel_map(&not_cached, number_of_workers, |individual| evaluator.fitness(individual));
     }
 
     /// Comprueba si todos los individuos de una poblacion tienen todos los valores del fitness sin
@@ -398,7 +832,7 @@ impl<'a, 'b> Population<'a, 'b>{
             for col in 0..max_values_in_row{
                 print!("{} ", individual.get_cluster_indexes()[col]);
             }
-            println!("");
+            println!();
         }
 
         // Esperamos a que el usuario pulse una tecla
@@ -407,82 +841,131 @@ impl<'a, 'b> Population<'a, 'b>{
 
     fn wait_for_user_input() {
         let mut stdout = stdout();
-        stdout.write(b"Press Enter to continue...").unwrap();
+        stdout.write_all(b"Press Enter to continue...").unwrap();
         stdout.flush().unwrap();
-        stdin().read(&mut [0]).unwrap();
+        stdin().read_exact(&mut [0]).unwrap();
     }
 
-    /// Intentamos medir la variedad que tenemos en nuestra poblacion. La variedad se medira como
-    /// el numero de elementos con distinto valor de fitness
-    /// WARNING -- Lo usamos para debuggear el codigo. No usar en codigo final porque puede evaluar
-    /// una poblacion sin considerar las evaluaciones del fitness
-    pub fn measure_variety(&self) -> u32{
-        // Para comprobar que no podemos llamar a esta funcion
-        // Quitar esto cuando hagamos debug del codigo. Tenemos un test para comprobar que esta
-        // funcion no puede ser llamada
-        panic!("function disabled");
+    /// Numero de agrupaciones distintas en la poblacion. Dos individuos con la misma agrupacion y
+    /// distintas etiquetas cuentan como uno solo. No consume evaluaciones del fitness
+    ///
+    /// Guardamos las formas canonicas en lugar de las soluciones, pues son las que determinan la
+    /// igualdad entre soluciones
+    pub fn number_of_distinct_individuals(&self) -> usize{
+        return self.individuals.iter().map(|individual| individual.canonical_cluster_indexes()).collect::<HashSet<Vec<u32>>>().len();
+    }
 
-        let mut fitness_values = HashSet::new();
+    /// Medidas de la diversidad de la poblacion (ver DiversityMetrics). No consume evaluaciones
+    /// del fitness
+    ///
+    /// Para la entropia de cada gen, renombramos antes los clusters de todos los individuos para
+    /// que coincidan con los del primero, pues si no dos individuos con la misma agrupacion y
+    /// distintas etiquetas aportarian entropia
+    pub fn diversity_metrics(&self) -> DiversityMetrics{
+        let population_size = self.population_size();
+        if population_size == 0{
+            return DiversityMetrics::new(0.0, 0.0, 0);
+        }
 
+        // Media de la distancia entre todas las parejas
+        let mut total_distance = 0;
+        for first in 0..population_size{
+            for second in (first + 1)..population_size{
+                total_distance += self.individuals[first].partition_distance(&self.individuals[second]);
+            }
+        }
+        let number_of_pairs = population_size * (population_size - 1) / 2;
+        let mean_partition_distance = if number_of_pairs > 0 {total_distance as f64 / number_of_pairs as f64} else {0.0};
+
+        // Entropia de cada gen, normalizada dividiendo entre la entropia maxima
+        let reference = &self.individuals[0];
+        let number_of_clusters = reference.get_number_of_clusters() as usize;
+        let number_of_genes = reference.get_cluster_indexes().len();
+        let mut counts = vec![vec![0; number_of_clusters]; number_of_genes];
         for individual in self.individuals.iter(){
-            fitness_values.insert(OrderedFloat::<f64>::from(individual.fitness()));
+            for (gene, cluster) in individual.relabeled_like(reference).get_cluster_indexes().iter().enumerate(){
+                counts[gene][*cluster as usize] += 1;
+            }
         }
 
-        return fitness_values.len() as u32;
+        let max_entropy = (number_of_clusters as f64).ln();
+        let mean_gene_entropy = if max_entropy > 0.0 && number_of_genes > 0 {
+            let total_entropy: f64 = counts.iter().map(|gene_counts| {
+                return gene_counts.iter()
+                    .filter(|count| **count > 0)
+                    .map(|count| {
+                        let probability = *count as f64 / population_size as f64;
+                        return -probability * probability.ln();
+                    })
+                    .sum::<f64>();
+            }).sum();
+            total_entropy / (number_of_genes as f64 * max_entropy)
+        }else{
+            0.0
+        };
+
+        return DiversityMetrics::new(mean_partition_distance, mean_gene_entropy, self.number_of_distinct_individuals());
     }
 
+    /// Reinicia la poblacion cuando ha perdido la diversidad: mantenemos al mejor individuo, en la
+    /// posicion en la que estaba, y el resto pasan a ser individuos aleatorios
+    /// No consume evaluaciones del fitness, pues la poblacion ya deberia estar evaluada. Debe haber
+    /// al menos un individuo evaluado en la poblacion
+    pub fn random_restart(&self, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        let (best_individual, best_index) = self.get_best_individual(evaluator);
+        let instance = best_individual.get_instance();
+
+        let mut individuals = vec![];
+        for index in 0..self.population_size(){
+            if index == best_index as usize{
+                individuals.push(best_individual.clone());
+                continue;
+            }
+
+            let mut new_individual = Solution::generate_random_solution(instance, rng);
+            if new_individual.is_valid() == false{
+                new_individual.repair_solution(rng);
+            }
+            individuals.push(new_individual);
+        }
+
+        return Self{individuals};
+    }
 }
 
 /// Implementacion para la parte de algoritmos memeticos
-impl<'a, 'b> Population<'a, 'b>{
+impl Population{
     /// Aplica la busqueda local suave, segun el criterio indicado por memetic_type, a la
     /// poblacion, generando una nueva poblacion
-    pub fn soft_local_search(&self, memetic_type: SearchType, max_fails: i32, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
+    /// Las busquedas locales sobre los individuos se reparten entre number_of_workers hilos
+    pub fn soft_local_search(&self, memetic_type: MemeticType, max_fails: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
         // Lanzamos la busqueda local suave correspondiente
         match memetic_type{
-            SearchType::MemeticAll => {
-                return self.soft_local_search_all(max_fails, rng);
+            MemeticType::All => {
+                return self.soft_local_search_all(max_fails, number_of_workers, evaluator, rng);
             }
 
-            SearchType::MemeticRandom => {
+            MemeticType::Random => {
                 let search_percentage = 0.1;
-                return self.soft_local_search_random(max_fails, search_percentage, rng);
+                return self.soft_local_search_random(max_fails, search_percentage, number_of_workers, evaluator, rng);
             }
 
-            SearchType::MemeticElitist => {
+            MemeticType::Elitist => {
                 let search_percentage = 0.1;
-                return self.soft_local_search_elitist(max_fails, search_percentage, rng);
-            }
-
-            _ => {
-                panic!("Valor erroneo para memetic_type")
+                return self.soft_local_search_elitist(max_fails, search_percentage, number_of_workers, evaluator, rng);
             }
         }
     }
 
     // Aplica la busqueda local suave, sobre todos los individuos de la poblacion
-    fn soft_local_search_all(&self, max_fails: i32, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
-        let mut new_pop = self.clone();
-        let mut fit_eval_cons = 0;
-
+    fn soft_local_search_all(&self, max_fails: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
         // Aplicamos la busqueda local suave a todos los individuos de la poblacion
-        // Itero sobre indices, asi que puedo iterar sobre self para no tener problemas de
-        // mutabilidad con new_pop
-        for (index, _individual) in self.individuals.iter().enumerate(){
-            let new_individual_result = new_pop.individuals[index].soft_local_search(max_fails, rng);
-            let new_individual = new_individual_result.get_result();
-            fit_eval_cons += new_individual_result.get_iterations_consumed();
-            new_pop.individuals[index] = new_individual.clone();
-        }
-
-        return FitnessEvaluationResult::new(new_pop, fit_eval_cons);
+        let indixes: Vec<usize> = (0..self.population_size()).collect();
+        return self.soft_local_search_over(&indixes, max_fails, number_of_workers, evaluator, rng);
     }
 
     // Aplica la busqueda local suave, sobre un porcentaje de individuos aleatorios de la poblacion
-    fn soft_local_search_random(&self, max_fails: i32, search_percentage: f64, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
-        let mut new_pop = self.clone();
-        let mut fit_eval_cons = 0;
-
+    fn soft_local_search_random(&self, max_fails: i32, search_percentage: f64, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
         // Numero de individuos sobre los que vamos a realizar la busqueda local suave
         let number_of_individuals_to_intensify = (self.individuals.len() as f64 * search_percentage) as i32;
 
@@ -492,67 +975,91 @@ impl<'a, 'b> Population<'a, 'b>{
 
         // Aplicamos la busqueda local solo a un numero dado de los individuos. Usando los indices
         // en orden aleatorio, escogemos aleatoriamente a dichos individuos
-        for i in 0..number_of_individuals_to_intensify{
-            // Escogemos aleatoriamente al individuo
-            let index = individuals_indixes[i as usize] as usize;
-
-            // Aplicamos la busqueda local a ese individuo
-            let new_individual_result = new_pop.individuals[index].soft_local_search(max_fails, rng);
-            let new_individual = new_individual_result.get_result();
-            fit_eval_cons += new_individual_result.get_iterations_consumed();
-            new_pop.individuals[index] = new_individual.clone();
-        }
+        let selected_indixes: Vec<usize> = individuals_indixes
+            .iter()
+            .take(number_of_individuals_to_intensify as usize)
+            .map(|&index| index as usize)
+            .collect();
 
-        return FitnessEvaluationResult::new(new_pop, fit_eval_cons);
+        return self.soft_local_search_over(&selected_indixes, max_fails, number_of_workers, evaluator, rng);
     }
 
     // Aplica la busqueda local suave, sobre el mejor porcentaje de individuos de la poblacion
-    fn soft_local_search_elitist(&self, max_fails: i32, search_percentage: f64, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
-        let mut new_pop = self.clone();
-        let mut fit_eval_cons = 0;
+    fn soft_local_search_elitist(&self, max_fails: i32, search_percentage: f64, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
 
         // Numero de individuos sobre los que vamos a realizar la busqueda local suave
         let number_of_individuals_to_intensify = (self.individuals.len() as f64 * search_percentage) as i32;
 
         // Seleccionamos los indices del mejor porcentaje de la poblacion
-        let best_indixes_result = self.select_best_indixes(number_of_individuals_to_intensify);
-        let best_indixes = best_indixes_result.get_result();
-        fit_eval_cons += best_indixes_result.get_iterations_consumed();
+        let best_indixes: Vec<usize> = self.select_best_indixes(number_of_individuals_to_intensify, number_of_workers, evaluator)
+            .iter()
+            .map(|&index| index as usize)
+            .collect();
 
         // Aplicamos la busqueda local a este porcentaje mejor de individuos
-        for index in best_indixes{
+        return self.soft_local_search_over(&best_indixes, max_fails, number_of_workers, evaluator, rng);
+    }
+
+    /// Aplica la busqueda local suave a los individuos en las posiciones dadas, en ese orden
+    ///
+    /// Los ordenes aleatorios de cada busqueda se generan primero de forma secuencial, consumiendo
+    /// el generador de numeros aleatorios igual que si hicieramos las busquedas una a una. Despues
+    /// las busquedas, que ya no usan numeros aleatorios, se reparten entre number_of_workers hilos
+    ///
+    /// Cuando las evaluaciones que quedan podrian no bastar para todas las busquedas, estas se
+    /// hacen en secuencial, pues en paralelo el resultado dependeria de que hilo agota antes las
+    /// evaluaciones
+    fn soft_local_search_over(&self, indixes: &[usize], max_fails: i32, number_of_workers: usize, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        let mut new_pop = self.clone();
 
-            // Aplicamos la busqueda local a ese individuo
-            let new_individual_result = new_pop.individuals[*index as usize].soft_local_search(max_fails, rng);
-            let new_individual = new_individual_result.get_result();
-            fit_eval_cons += new_individual_result.get_iterations_consumed();
-            new_pop.individuals[*index as usize] = new_individual.clone();
+        // Generamos los ordenes en los que cada busqueda recorre las posiciones
+        let jobs: Vec<(usize, Vec<i32>)> = indixes
+            .iter()
+            .map(|&index| (index, self.individuals[index].soft_local_search_order(rng)))
+            .collect();
+
+        // Cada busqueda consume como mucho una evaluacion por cada punto y cluster
+        let max_evaluations_per_search = self.individuals.first().map_or(0, |individual| {
+            return individual.get_data_points().len() * individual.get_number_of_clusters() as usize;
+        });
+        let number_of_workers = if jobs.len() * max_evaluations_per_search <= evaluator.remaining() as usize {number_of_workers} else {1};
+
+        // Lanzamos las busquedas
+        let results = utils::parallel_map(&jobs, number_of_workers, |(index, order)| {
+            return self.individuals[*index].soft_local_search_with_order(max_fails, order, evaluator);
+        });
+
+        // Sustituimos los individuos por el resultado de sus busquedas
+        for ((index, _), new_individual) in jobs.iter().zip(results){
+            new_pop.individuals[*index] = new_individual;
         }
 
-        return FitnessEvaluationResult::new(new_pop, fit_eval_cons);
+        return new_pop;
     }
 
     /// Dado un numero de individuos, selecciona los indices de los mejores individuos de la
     /// poblacion. Es decir, aquellos indices de individuos con mejor valor de fitness
     // TODO -- es bastante facil de testear
-    fn select_best_indixes(&self, number_of_individuals: i32) -> FitnessEvaluationResult<Vec<u32>>{
-        let mut fit_evals_cons = 0;
+    /// Si no quedan evaluaciones para evaluar a toda la poblacion, solo se tienen en cuenta los
+    /// individuos evaluados, por lo que podemos devolver menos indices de los pedidos
+    fn select_best_indixes(&self, number_of_individuals: i32, number_of_workers: usize, evaluator: &Evaluator) -> Vec<u32>{
         let mut best_indixes = vec![];
 
         // Necesitamos que toda la poblacion este evaluada para poder ordenar a sus individuos
-        let eval_result = self.evaluate_all_individuals();
-        fit_evals_cons += eval_result.get_iterations_consumed();
-        debug_assert!(self.all_population_is_cached());
+        self.evaluate_all_individuals(number_of_workers, evaluator);
 
         // Guardamos a los individuos de la poblacion en una priority queue.
         // En verdad, solo necesitamos guardar los indices de los individuos ordenados por su valor
         // de fitness.
-        // Podemos guardar todos los elementos en esta, sabiendo que el fitness ya esta evaluado
-        // porque hemos evaluado toda la poblacion anteriormente.
+        // Solo guardamos a los individuos evaluados, para no consumir evaluaciones sin contarlas
         let mut priority_queue = PriorityQueue::new();
         for (index, individual) in self.individuals.iter().enumerate(){
+            if individual.is_fitness_cached() == false{
+                continue;
+            }
+
             // -1.0 * porque asi devolvemos los mejores individuos, y no los peores
-            priority_queue.push(index, OrderedFloat::<f64>::from(-1.0 * individual.fitness()));
+            priority_queue.push(index, OrderedFloat::<f64>::from(-individual.fitness()));
         }
 
         // Sacamos a los number_of_individuals primeros individuos de la prioqueu
@@ -568,22 +1075,306 @@ impl<'a, 'b> Population<'a, 'b>{
         }
 
         // Comprobacion de seguridad
-        debug_assert!(best_indixes.len() == number_of_individuals as usize);
+        debug_assert!(best_indixes.len() <= number_of_individuals as usize);
+
+        return best_indixes;
+    }
+}
+
+/// Implementacion para el algoritmo CHC
+impl Population{
+    /// Genera la poblacion de hijos del CHC. Emparejamos aleatoriamente a todos los individuos y
+    /// cruzamos con HUX solo las parejas cuyos padres difieren en mas de 2 * incest_threshold
+    /// puntos, para evitar el incesto. La distancia entre padres no depende de las etiquetas de
+    /// los clusters (ver Solution::partition_distance)
+    ///
+    /// La poblacion devuelta puede tener menos individuos que la original, o incluso ninguno.
+    /// Esta operacion no consume evaluaciones del fitness
+    pub fn cross_population_hux(&self, incest_threshold: usize, rng: &mut StdRng) -> Self{
+        let mut indixes: Vec<usize> = (0..self.population_size()).collect();
+        indixes.shuffle(rng);
+
+        let mut children = vec![];
+        for pair in indixes.chunks_exact(2){
+            let first_parent = &self.individuals[pair[0]];
+            let second_parent = &self.individuals[pair[1]];
+
+            if first_parent.partition_distance(second_parent) / 2 > incest_threshold{
+                let (first_child, second_child) = Solution::hux_cross(first_parent, second_parent, rng);
+                children.push(first_child);
+                children.push(second_child);
+            }
+        }
+
+        return Self{individuals: children};
+    }
+
+    /// Seleccion elitista (mu + lambda): juntamos padres (&self) e hijos y nos quedamos con los
+    /// population_size mejores. A igualdad de fitness preferimos a los padres. Devuelve la nueva
+    /// poblacion y el numero de hijos que han entrado en ella
+    ///
+    /// Un hijo que no se puede evaluar, por haber agotado las evaluaciones, no entra en la
+    /// poblacion. Los padres sin evaluar se consideran los peores
+    pub fn elitist_survival(&self, offspring: &Population, evaluator: &Evaluator) -> (Self, usize){
+        let mut candidates: Vec<(Option<f64>, bool, &Solution)> = vec![];
+        for parent in self.individuals.iter(){
+            candidates.push((evaluator.fitness(parent), false, parent));
+        }
+        for child in offspring.individuals.iter(){
+            if let Some(fitness) = evaluator.fitness(child){
+                candidates.push((Some(fitness), true, child));
+            }
+        }
+
+        // La ordenacion es estable, asi que los padres quedan delante de los hijos con el mismo
+        // fitness
+        candidates.sort_by(|first, second| match (first.0, second.0){
+            (Some(first), Some(second)) => first.partial_cmp(&second).unwrap(),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        candidates.truncate(self.population_size());
+
+        let entered_children = candidates.iter().filter(|(_, is_child, _)| *is_child == true).count();
+        let individuals = candidates.into_iter().map(|(_, _, individual)| individual.clone()).collect();
+
+        return (Self{individuals}, entered_children);
+    }
 
-        return FitnessEvaluationResult::new(best_indixes, fit_evals_cons);
+    /// Reinicio cataclismico o divergencia del CHC: mantenemos al mejor individuo, y el resto de
+    /// la poblacion pasan a ser copias del mejor con divergence_rate * numero de puntos puntos
+    /// reasignados aleatoriamente
+    ///
+    /// Esta operacion no consume evaluaciones del fitness, pues la poblacion ya deberia estar
+    /// evaluada. Debe haber al menos un individuo evaluado en la poblacion
+    pub fn cataclysmic_restart(&self, divergence_rate: f64, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        let (best_individual, _) = self.get_best_individual(evaluator);
+        let points_to_move = (divergence_rate * best_individual.get_cluster_indexes().len() as f64) as usize;
+
+        let mut individuals = vec![best_individual.clone()];
+        for _ in 1..self.population_size(){
+            individuals.push(best_individual.shaken(points_to_move, rng));
+        }
+
+        return Self{individuals};
     }
 }
 
+#[cfg(test)]
 mod test{
     use crate::problem_datatypes::population::Population;
+    use crate::problem_datatypes::Solution;
+    use crate::problem_datatypes::ProblemInstance;
+    use crate::problem_datatypes::population::MemeticType;
+    use crate::problem_datatypes::population::SelectionOperator;
+    use crate::problem_datatypes::population::ReplacementStrategy;
+    use crate::problem_datatypes::population::ROULETTE_MIN_WEIGHT;
+    use crate::evaluator::Evaluator;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // La seleccion por torneo y la busqueda local suave deben dar exactamente los mismos
+    // individuos y consumir las mismas evaluaciones del fitness con cualquier numero de hilos
+    fn test_parallel_evaluation_matches_sequential(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let population = Population::new_random_population(&instance, 20, &mut rng);
+
+        let mut results = vec![];
+        for number_of_workers in [1, 3, 8]{
+            let mut rng = StdRng::seed_from_u64(987654321);
+            let population = population.clone();
+
+            let evaluator = Evaluator::new(100000);
+            let selection_population = population.select_population_binary_tournament(20, number_of_workers, &evaluator, &mut rng);
+            let selection_consumed = evaluator.consumed();
+            let search_population = selection_population.soft_local_search(MemeticType::All, 4, number_of_workers, &evaluator, &mut rng);
+            let search_consumed = evaluator.consumed();
+            search_population.evaluate_all_individuals(number_of_workers, &evaluator);
+
+            let individuals: Vec<(Vec<u32>, f64)> = search_population.individuals
+                .iter()
+                .map(|individual| (individual.get_cluster_indexes(), individual.fitness()))
+                .collect();
+            let consumed = (selection_consumed, search_consumed, evaluator.consumed());
+            results.push((individuals, consumed));
+        }
+
+        for result in results.iter().skip(1){
+            assert_eq!(*result, results[0]);
+        }
+    }
+
+    #[test]
+    // Todos los operadores de seleccion dan el numero de individuos pedido sin consumir mas
+    // evaluaciones de las que indican. El ranking lineal con presion 2 nunca escoge al peor, y el
+    // muestreo universal estocastico escoge al mejor al menos tantas veces como indica su peso
+    fn test_selection_operators(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let population = Population::new_random_population(&instance, 20, &mut rng);
+
+        let operators = [
+            SelectionOperator::Tournament(2),
+            SelectionOperator::Tournament(5),
+            SelectionOperator::LinearRank(2.0),
+            SelectionOperator::Roulette,
+            SelectionOperator::StochasticUniversal,
+        ];
+        for selection in operators{
+            let population = population.clone();
+            let evaluator = Evaluator::new(100000);
+            let selection_population = population.select_population(selection, 40, 1, &evaluator, &mut rng);
+            assert_eq!(selection_population.population_size(), 40);
+            assert!(evaluator.consumed() as usize <= selection.max_evaluations(40, 20));
+
+            if selection == SelectionOperator::LinearRank(2.0){
+                let worst_individual = population.individuals.iter()
+                    .max_by(|first, second| first.fitness().partial_cmp(&second.fitness()).unwrap())
+                    .unwrap();
+                assert!(selection_population.individuals.iter().all(|individual| individual != worst_individual));
+            }
+
+            if selection == SelectionOperator::StochasticUniversal{
+                let fitness: Vec<f64> = population.individuals.iter().map(|individual| individual.fitness()).collect();
+                let max_fitness = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let min_fitness = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+                let weight = |fitness: f64| (max_fitness - fitness) / (max_fitness - min_fitness) + ROULETTE_MIN_WEIGHT;
+                let total_weight: f64 = fitness.iter().map(|fitness| weight(*fitness)).sum();
+                let expected_copies = 40.0 * weight(min_fitness) / total_weight;
+
+                let (best_individual, _) = population.get_best_individual(&evaluator);
+                let copies = selection_population.individuals.iter().filter(|individual| *individual == best_individual).count();
+                assert!(copies as f64 >= expected_copies.floor());
+            }
+        }
+
+        // La ruleta no depende de la escala del fitness: aunque todos los individuos tengan un
+        // fitness parecido, el mejor recibe muchas mas copias que el peor
+        let evaluator = Evaluator::new(100000);
+        let (best_individual, _) = population.get_best_individual(&evaluator);
+        let worst_individual = population.individuals.iter()
+            .max_by(|first, second| first.fitness().partial_cmp(&second.fitness()).unwrap())
+            .unwrap();
+        for selection in [SelectionOperator::Roulette, SelectionOperator::StochasticUniversal]{
+            let selection_population = population.select_population(selection, 2000, 1, &evaluator, &mut rng);
+            let best_copies = selection_population.individuals.iter().filter(|individual| *individual == best_individual).count();
+            let worst_copies = selection_population.individuals.iter().filter(|individual| *individual == worst_individual).count();
+            assert!(best_copies > 5 * worst_copies.max(1), "{:?}: {} copias del mejor, {} del peor", selection, best_copies, worst_copies);
+        }
+
+        // Con dos candidatos, el torneo es el torneo binario de siempre
+        let mut first_rng = StdRng::seed_from_u64(42);
+        let mut second_rng = StdRng::seed_from_u64(42);
+        let binary = population.select_population_binary_tournament(20, 1, &Evaluator::new(100000), &mut first_rng);
+        let tournament = population.select_population(SelectionOperator::Tournament(2), 20, 1, &Evaluator::new(100000), &mut second_rng);
+        assert_eq!(binary.individuals, tournament.individuals);
+    }
+
+    #[test]
+    // Ninguna estrategia de reemplazo cambia el tamaño de la poblacion ni empeora al mejor
+    // individuo, y cada hijo consume como mucho una evaluacion. Los duplicados se descartan sin
+    // evaluarlos, y el reemplazo del mas antiguo deja al hijo al final de la poblacion
+    fn test_replacement_strategies(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let evaluator = Evaluator::new(100000);
+        let population = Population::new_random_population(&instance, 10, &mut rng);
+        population.evaluate_all_individuals(1, &evaluator);
+        let (best_individual, _) = population.get_best_individual(&evaluator);
+
+        let strategies = [
+            ReplacementStrategy::Worst,
+            ReplacementStrategy::Random,
+            ReplacementStrategy::Oldest,
+            ReplacementStrategy::DeterministicCrowding,
+            ReplacementStrategy::RestrictedTournament(3),
+            ReplacementStrategy::WorstWithoutDuplicates,
+        ];
+        for replacement in strategies{
+            let parents = population.select_population_binary_tournament(2, 1, &evaluator, &mut rng);
+            let children = parents.cross_population_uniform(1.0, &mut rng);
+
+            let evaluator = Evaluator::new(100000);
+            let new_population = population.replace_with_new_individuals(replacement, &parents, &children, &evaluator, &mut rng);
+            assert_eq!(new_population.population_size(), 10);
+            assert!(evaluator.consumed() <= 2);
+            assert!(new_population.get_best_individual(&evaluator).0.fitness() <= best_individual.fitness());
+
+            if replacement == ReplacementStrategy::Oldest{
+                assert_eq!(new_population.individuals[9].get_cluster_indexes(), children.individuals[1].get_cluster_indexes());
+            }
+        }
+
+        // Copias sin evaluar y con otras etiquetas de dos individuos de la poblacion
+        let number_of_clusters = instance.get_number_of_clusters() as u32;
+        let duplicates = Population::from_individuals([3, 7].iter().map(|&index| {
+            let permuted_indexes = population.individuals[index].get_cluster_indexes().iter().map(|cluster| (cluster + 1) % number_of_clusters).collect();
+            return Solution::new(permuted_indexes, &instance);
+        }).collect());
+        assert!(duplicates.all_population_is_not_cached() == true);
+        let evaluator = Evaluator::new(100000);
+        let new_population = population.replace_with_new_individuals(ReplacementStrategy::WorstWithoutDuplicates, &duplicates, &duplicates, &evaluator, &mut rng);
+        assert_eq!(evaluator.consumed(), 0);
+        assert_eq!(new_population.individuals, population.individuals);
+    }
+
+    #[test]
+    // Tras la seleccion elitista sobreviven los mejores entre padres e hijos, y cada superviviente
+    // es al menos tan bueno como cualquier individuo descartado
+    fn test_elitist_survival_keeps_the_best(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let evaluator = Evaluator::new(100000);
+
+        let parents = Population::new_random_population(&instance, 10, &mut rng);
+        let offspring = Population::new_random_population(&instance, 6, &mut rng);
+        parents.evaluate_all_individuals(1, &evaluator);
+        offspring.evaluate_all_individuals(1, &evaluator);
+
+        let (survivors, entered_children) = parents.elitist_survival(&offspring, &evaluator);
+        assert_eq!(survivors.population_size(), 10);
+
+        let mut all_fitness: Vec<f64> = parents.individuals.iter().chain(offspring.individuals.iter()).map(|individual| individual.fitness()).collect();
+        all_fitness.sort_by(|first, second| first.partial_cmp(second).unwrap());
+        let survivors_fitness: Vec<f64> = survivors.individuals.iter().map(|individual| individual.fitness()).collect();
+        assert_eq!(survivors_fitness, all_fitness[..10].to_vec());
+
+        let children_in_survivors = survivors.individuals.iter()
+            .filter(|survivor| offspring.individuals.iter().any(|child| child.get_cluster_indexes() == survivor.get_cluster_indexes()))
+            .count();
+        assert_eq!(children_in_survivors, entered_children);
+    }
 
     #[test]
-    #[should_panic(expected="function disabled")]
-    /// Comprobamos que tenemos desactivada esta funcion, porque si se puede llamar puede afectar
-    /// al comportamiento de los algoritmos (pues evalua el fitness de todos los elementos de la
-    /// poblacion sin notifcar cuantas evaluaciones efectivas del fitness estamos realizando)
-    fn test_measure_variety_disabled(){
-        let pop = Population::new_empty_population();
-        pop.measure_variety();
+    // Una poblacion con la misma agrupacion repetida, aunque con etiquetas distintas, no tiene
+    // diversidad. Medir la diversidad no consume evaluaciones del fitness
+    fn test_diversity_metrics(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let number_of_clusters = instance.get_number_of_clusters() as u32;
+
+        let mut individual = Solution::generate_random_solution(&instance, &mut rng);
+        individual.repair_solution(&mut rng);
+        let individuals: Vec<Solution> = (0..10).map(|shift| {
+            let permuted_indexes = individual.get_cluster_indexes().iter().map(|cluster| (cluster + shift) % number_of_clusters).collect();
+            return Solution::new(permuted_indexes, &instance);
+        }).collect();
+        let converged = Population::from_individuals(individuals);
+
+        let metrics = converged.diversity_metrics();
+        assert_eq!(metrics.get_mean_partition_distance(), 0.0);
+        assert_eq!(metrics.get_mean_gene_entropy(), 0.0);
+        assert_eq!(metrics.get_distinct_individuals(), 1);
+
+        let random = Population::new_random_population(&instance, 10, &mut rng);
+        let metrics = random.diversity_metrics();
+        assert!(metrics.get_mean_partition_distance() > 0.0);
+        assert!(metrics.get_mean_gene_entropy() > 0.0 && metrics.get_mean_gene_entropy() <= 1.0);
+        assert_eq!(metrics.get_distinct_individuals(), 10);
+        assert!(random.all_population_is_not_cached() == true);
     }
 }
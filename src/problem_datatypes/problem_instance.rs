@@ -0,0 +1,93 @@
+use crate::problem_datatypes::{DataPoints, Constraints, Point};
+
+#[cfg(test)]
+use crate::problem_datatypes::ConstraintType;
+#[cfg(test)]
+use rand::{Rng, rngs::StdRng};
+#[cfg(test)]
+use std::sync::Arc;
+
+/// Representa una instancia del problema: los puntos a agrupar, las restricciones sobre dichos
+/// puntos y el numero de clusters en los que queremos agruparlos
+///
+/// Tambien guarda los valores que dependen unicamente de la instancia y que son costosos de
+/// calcular, como lambda, que requiere la maxima distancia entre dos puntos (O(n^2)). Asi solo se
+/// calculan una vez, y no cada vez que construimos una solucion
+///
+/// Las soluciones guardan un Arc<ProblemInstance>, de forma que todas comparten la misma
+/// instancia sin necesidad de lifetimes y pueden pasarse a otros hilos
+#[derive(Debug)]
+pub struct ProblemInstance{
+    data_points: DataPoints,
+    constraints: Constraints,
+    number_of_clusters: i32,
+
+    /// Maxima distancia entre dos puntos del conjunto de datos
+    max_distance: f64,
+
+    /// Representa el peso de infeasibility en el calculo de fitness
+    lambda: f64,
+}
+
+impl ProblemInstance{
+    /// Construye la instancia, calculando los valores precomputados
+    /// Si las restricciones no tienen construidas sus listas de adyacencia, se construyen aqui
+    pub fn new(data_points: DataPoints, mut constraints: Constraints, number_of_clusters: i32) -> Self{
+        if constraints.has_adjacency() == false{
+            constraints.build_adjacency(data_points.len());
+        }
+
+        let max_distance = Point::max_distance_among_two(data_points.get_points());
+        let lambda = max_distance / constraints.get_data().len() as f64;
+
+        return Self{data_points, constraints, number_of_clusters, max_distance, lambda};
+    }
+
+    pub fn get_data_points(&self) -> &DataPoints{
+        return &self.data_points;
+    }
+
+    pub fn get_constraints(&self) -> &Constraints{
+        return &self.constraints;
+    }
+
+    pub fn get_number_of_clusters(&self) -> i32{
+        return self.number_of_clusters;
+    }
+
+    pub fn get_max_distance(&self) -> f64{
+        return self.max_distance;
+    }
+
+    pub fn get_lambda(&self) -> f64{
+        return self.lambda;
+    }
+
+    /// Numero de puntos que tenemos que asignar a clusters
+    pub fn number_of_points(&self) -> usize{
+        return self.data_points.len();
+    }
+
+    /// Genera una instancia aleatoria pequeña para los tests: 40 puntos de dimension 3, unas 60
+    /// restricciones aleatorias y 4 clusters
+    #[cfg(test)]
+    pub fn random_instance(rng: &mut StdRng) -> Arc<Self>{
+        let number_of_points: i32 = 40;
+        let points = (0..number_of_points).map(|_| Point::random_point(3, rng)).collect();
+        let data_points = DataPoints::new(points);
+
+        let mut constraints = Constraints::new();
+        for _ in 0..60{
+            let first = rng.gen_range(0..number_of_points);
+            let second = rng.gen_range(0..number_of_points);
+            if first == second{
+                continue;
+            }
+
+            let constraint_type = if rng.gen::<bool>() == true {ConstraintType::MustLink} else {ConstraintType::CannotLink};
+            constraints.add_constraint(first, second, constraint_type);
+        }
+
+        return Arc::new(Self::new(data_points, constraints, 4));
+    }
+}
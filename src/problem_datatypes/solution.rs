@@ -5,55 +5,107 @@ use rand::rngs::StdRng;
 // Para hacer shuffle de un vector
 use rand::seq::SliceRandom;
 
-// Para tener mutabilidad interior
-use std::cell::RefCell;
+// Para tener mutabilidad interior que se pueda compartir entre hilos
+use std::sync::Mutex;
 
-use crate::problem_datatypes::{DataPoints, Constraints, Point, ConstraintType, NeighbourGenerator};
-use crate::fitness_evaluation_result::FitnessEvaluationResult;
+// Para compartir la instancia del problema entre soluciones
+use std::sync::Arc;
+
+// Para usar soluciones en tablas hash
+use std::hash::{Hash, Hasher};
+
+use crate::problem_datatypes::{DataPoints, Point, ConstraintType, NeighbourGenerator, ProblemInstance};
+use crate::evaluator::Evaluator;
+use crate::acceptance_criterion::{AcceptanceCriterion, Decision};
 
 /// Estructura que representa una solucion del problema
 ///
 /// La solucion viene representada como un vector de indices
 /// En dicho vector, la posicion i-esima indica el cluster al que pertenece el i-esimo
 /// punto del conjunto de datos
-#[derive(Debug, Clone)]
-pub struct Solution<'a, 'b> {
+/// Ademas de la asignacion, la solucion guarda un puntero compartido a la instancia del problema
+#[derive(Debug)]
+pub struct Solution {
     cluster_indexes: Vec<u32>,
-    data_points: &'a DataPoints,
-    constraints: &'b Constraints,
-    number_of_clusters: i32,
-
-    /// Representa el peso de infeasibility en el calculo de fitness
-    /// Solo se calcula una vez al invocar a Solution::new
-    lambda: f64,
+    instance: Arc<ProblemInstance>,
 
     // Para cachear el valor de fitness pues es un calculo costoso de realizar
     // Como los datos del struct no cambian, podemos hacer el cacheo sin miedo
-    // Usamos RefCell para tener un patron de mutabilidad interior
-    fitness: RefCell<Option<f64>>,
+    // Usamos Mutex para tener un patron de mutabilidad interior. A diferencia de RefCell, esto
+    // hace que Solution sea Sync, y podamos evaluar una poblacion desde varios hilos
+    fitness: Mutex<Option<f64>>,
 
+    // Estadisticas por cluster que nos permiten recalcular el fitness de forma incremental
+    // cuando movemos un unico punto de cluster. Se calculan la primera vez que se necesitan
+    statistics: Mutex<Option<ClusterStatistics>>,
 }
 
-impl<'a, 'b> Solution<'a, 'b> {
-    /// Util cuando no conocemos el valor de lambda, pues se calcula en esta llamada
-    /// En otro caso, se puede construir el struct de forma directa
-    pub fn new(
-        cluster_indexes: Vec<u32>,
-        data_points: &'a DataPoints,
-        constraints: &'b Constraints,
-        number_of_clusters: i32,
-    ) -> Self {
+/// Clone no se puede derivar porque Mutex no implementa Clone. Copiamos los valores cacheados, de
+/// forma que la copia no tenga que volver a evaluar el fitness
+impl Clone for Solution {
+    fn clone(&self) -> Self {
+        return Self {
+            cluster_indexes: self.cluster_indexes.clone(),
+            instance: Arc::clone(&self.instance),
+            fitness: Mutex::new(*self.fitness.lock().unwrap()),
+            statistics: Mutex::new(self.statistics.lock().unwrap().clone()),
+        };
+    }
+}
+
+/// Movimientos de puntos tras los que recalculamos desde cero las sumas de coordenadas de las
+/// estadisticas por cluster
+const COORDINATE_SUMS_REFRESH_INTERVAL: usize = 1000;
+
+/// Estadisticas por cluster que mantenemos en cada solucion para no tener que recalcular el
+/// fitness desde cero cada vez que movemos un punto de un cluster a otro
+///
+/// Al mover un punto del cluster a al cluster b, actualizamos tamaños, sumas de coordenadas e
+/// infeasibility en O(d + restricciones del punto), y marcamos los clusters a y b como sucios.
+/// Cada COORDINATE_SUMS_REFRESH_INTERVAL movimientos recalculamos las sumas de coordenadas desde
+/// cero, para que los centroides no acumulen errores de redondeo
+///
+/// El fitness usa distancias euclideas, no cuadradas, al centroide, y esa suma no se puede
+/// actualizar a partir de sumas por cluster: al moverse el centroide, la distancia de cada punto
+/// del cluster cambia de forma no lineal. Por eso la suma de distancias de un cluster sucio se
+/// recalcula la siguiente vez que se pide el fitness, recorriendo solo los puntos de ese cluster.
+/// Evaluar un movimiento de un punto cuesta O((|a| + |b|) * d + restricciones del punto), en
+/// lugar del O(n * d) de calcularlo desde cero
+#[derive(Debug, Clone)]
+struct ClusterStatistics{
+    /// Numero de puntos asignados a cada cluster
+    sizes: Vec<usize>,
+
+    /// Indices de los puntos de cada cluster, en orden creciente. Mantener el orden hace que las
+    /// sumas den exactamente lo mismo que recorriendo todos los puntos
+    members: Vec<Vec<usize>>,
 
-        // Calculamos el valor de lambda
-        let lambda = Point::max_distance_among_two(&data_points.get_points()) / constraints.get_data().len() as f64;
+    /// Suma de las coordenadas de los puntos de cada cluster. Dividiendo entre el tamaño del
+    /// cluster obtenemos su centroide
+    coordinate_sums: Vec<ndarray::Array1<f64>>,
 
+    /// Suma de las distancias de los puntos de cada cluster a su centroide
+    distance_sums: Vec<f64>,
+
+    /// Clusters cuya suma de distancias no esta actualizada
+    dirty: Vec<bool>,
+
+    /// Movimientos realizados desde que recalculamos las sumas de coordenadas desde cero
+    moves_since_refresh: usize,
+
+    /// Numero de restricciones violadas por la solucion
+    infeasibility: i32,
+}
+
+impl Solution {
+    /// Construye una solucion a partir de la asignacion de clusters y la instancia del problema
+    /// Lambda ya esta precalculado en la instancia, por lo que esta operacion es barata
+    pub fn new(cluster_indexes: Vec<u32>, instance: &Arc<ProblemInstance>) -> Self {
         return Self {
             cluster_indexes,
-            data_points,
-            constraints,
-            number_of_clusters,
-            lambda,
-            fitness: RefCell::new(None),
+            instance: Arc::clone(instance),
+            fitness: Mutex::new(None),
+            statistics: Mutex::new(None),
         };
     }
 
@@ -62,11 +114,19 @@ impl<'a, 'b> Solution<'a, 'b> {
     }
 
     pub fn get_lambda(&self) -> f64{
-        return self.lambda;
+        return self.instance.get_lambda();
     }
 
     pub fn get_data_points(&self) -> &DataPoints{
-        return self.data_points;
+        return self.instance.get_data_points();
+    }
+
+    pub fn get_instance(&self) -> &Arc<ProblemInstance>{
+        return &self.instance;
+    }
+
+    pub fn get_number_of_clusters(&self) -> i32{
+        return self.instance.get_number_of_clusters();
     }
 
     /// Comprueba si la solucion es valida o no
@@ -77,13 +137,18 @@ impl<'a, 'b> Solution<'a, 'b> {
 
         // Condicion de seguridad que nunca deberia ocurrir
         // Por eso pongo el panic!, porque es un problema de probramacion
-        if self.cluster_indexes.len() != self.data_points.get_points().len(){
+        if self.cluster_indexes.len() != self.get_data_points().get_points().len(){
             eprintln!("No puede ocurrir que la longitud de los indices sea distinta al numero de puntos");
             return false;
         }
 
+        // Si tenemos las estadisticas calculadas, basta con mirar los tamaños de los clusters
+        if let Some(statistics) = self.statistics.lock().unwrap().as_ref(){
+            return statistics.sizes.iter().all(|&size| size > 0);
+        }
+
         // Comprobamos que no haya clusters vacios
-        for cluster in 0..self.number_of_clusters{
+        for cluster in 0..self.get_number_of_clusters(){
             match self.cluster_indexes.iter().find(|&&x| x == cluster as u32){
                 // Se ha encontrado, no hacemos nada
                 Some(_) => (),
@@ -102,7 +167,7 @@ impl<'a, 'b> Solution<'a, 'b> {
     /// Devuelve el valor de fitness. Si ya ha sido calculado antes, devuelve
     /// el valor cacheado sin repetir los calculos
     pub fn fitness(&self) -> f64 {
-        let fit_opt = *self.fitness.borrow();
+        let fit_opt = *self.fitness.lock().unwrap();
 
         match fit_opt{
             // Tenemos el valor cacheado del fitness, no repetimos calculos
@@ -111,8 +176,8 @@ impl<'a, 'b> Solution<'a, 'b> {
             // No hemos calculado todavia el valor de fitness
             // Lo calculamos, lo guardamos y lo devolvemos
             None => {
-                let calc_fitness = self.global_cluster_mean_distance() + self.lambda * self.infeasibility() as f64;
-                *self.fitness.borrow_mut() = Some(calc_fitness);
+                let calc_fitness = self.global_cluster_mean_distance() + self.get_lambda() * self.infeasibility() as f64;
+                *self.fitness.lock().unwrap() = Some(calc_fitness);
                 return calc_fitness;
             }
         }
@@ -121,7 +186,7 @@ impl<'a, 'b> Solution<'a, 'b> {
     /// Calcula el valor del fitness, y las iteraciones consumidas en el proceso
     /// Las iteraciones consumidas pueden ser o bien 0 o bien 1
     pub fn fitness_and_consumed(&self) -> (f64, u32){
-        let consumed = match *self.fitness.borrow(){
+        let consumed = match *self.fitness.lock().unwrap(){
             None => 1,
             Some(_) => 0,
         };
@@ -131,33 +196,168 @@ impl<'a, 'b> Solution<'a, 'b> {
 
     /// Resetea el valor de fitness a None, por lo tanto, cuando se intente acceder a este valor,
     /// deberemos volver a calcular su valor
+    /// Tambien descarta las estadisticas por cluster, pues se usa tras modificar muchas posiciones
+    /// de la solucion a la vez. Para mover un unico punto usar Solution::move_point
     pub fn invalid_fitness_cache(&mut self){
-        *self.fitness.borrow_mut() = None;
+        *self.fitness.get_mut().unwrap() = None;
+        *self.statistics.get_mut().unwrap() = None;
+    }
+
+    /// Mueve el punto point_index al cluster new_cluster
+    ///
+    /// Si las estadisticas por cluster estan calculadas, se actualizan de forma incremental, por
+    /// lo que la siguiente evaluacion del fitness es mucho mas barata que calcularla desde cero.
+    /// El fitness de la solucion queda sin evaluar aunque el punto ya estuviera en new_cluster,
+    /// para no alterar el conteo de evaluaciones del fitness de los algoritmos
+    pub fn move_point(&mut self, point_index: usize, new_cluster: u32){
+        *self.fitness.get_mut().unwrap() = None;
+
+        let old_cluster = self.cluster_indexes[point_index];
+        if old_cluster == new_cluster{
+            return;
+        }
+
+        if self.statistics.get_mut().unwrap().is_some(){
+            // Calculamos el cambio en infeasibility antes de realizar el movimiento
+            let delta_infeasibility =
+                self.violated_constraints_if_assigned(point_index, new_cluster) -
+                self.violated_constraints_if_assigned(point_index, old_cluster);
+            let coordinates = self.instance.get_data_points().get_points()[point_index].get_coordinates();
+
+            let statistics = self.statistics.get_mut().unwrap().as_mut().unwrap();
+            statistics.sizes[old_cluster as usize] -= 1;
+            statistics.sizes[new_cluster as usize] += 1;
+            let old_members = &mut statistics.members[old_cluster as usize];
+            if let Ok(position) = old_members.binary_search(&point_index){
+                old_members.remove(position);
+            }
+            let new_members = &mut statistics.members[new_cluster as usize];
+            if let Err(position) = new_members.binary_search(&point_index){
+                new_members.insert(position, point_index);
+            }
+            statistics.coordinate_sums[old_cluster as usize] -= coordinates;
+            statistics.coordinate_sums[new_cluster as usize] += coordinates;
+            statistics.dirty[old_cluster as usize] = true;
+            statistics.dirty[new_cluster as usize] = true;
+            statistics.moves_since_refresh += 1;
+            statistics.infeasibility += delta_infeasibility;
+        }
+
+        self.cluster_indexes[point_index] = new_cluster;
+    }
+
+    /// Numero de restricciones en las que participa point_index que viola la solucion
+    pub fn violated_constraints_of_point(&self, point_index: usize) -> i32{
+        return self.violated_constraints_if_assigned(point_index, self.cluster_indexes[point_index]);
+    }
+
+    /// Numero de restricciones en las que participa point_index que se violarian si el punto
+    /// estuviese asignado a cluster, dejando el resto de asignaciones como estan
+    /// Solo recorre las restricciones del punto, usando las listas de adyacencia de Constraints
+    fn violated_constraints_if_assigned(&self, point_index: usize, cluster: u32) -> i32{
+        let mut violations = 0;
+
+        // Las MustLink se violan cuando no estan en el mismo cluster
+        for other_index in self.instance.get_constraints().must_link_neighbours(point_index){
+            if self.cluster_indexes[*other_index as usize] != cluster{
+                violations += 1;
+            }
+        }
+
+        // Las CannotLink se violan cuando estan en el mismo cluster
+        for other_index in self.instance.get_constraints().cannot_link_neighbours(point_index){
+            if self.cluster_indexes[*other_index as usize] == cluster{
+                violations += 1;
+            }
+        }
+
+        return violations;
+    }
+
+    /// Se asegura de que las estadisticas por cluster esten calculadas y actualizadas
+    /// La primera vez se calculan desde cero, recorriendo todos los puntos. En otro caso, solo se
+    /// recalculan las sumas de distancias de los clusters marcados como sucios por
+    /// Solution::move_point, recorriendo unicamente los puntos de esos clusters, y las sumas de
+    /// coordenadas cuando toca refrescarlas
+    fn update_statistics(&self){
+        let mut statistics = self.statistics.lock().unwrap();
+        let number_of_clusters = self.get_number_of_clusters() as usize;
+        let points = self.get_data_points().get_points();
+
+        // Primera vez que necesitamos las estadisticas: todos los clusters estan sucios
+        if statistics.is_none(){
+            let dimension = self.get_data_points().point_dimension().unwrap_or(0);
+            let mut members = vec![vec![]; number_of_clusters];
+            for (index, cluster) in self.cluster_indexes.iter().enumerate(){
+                members[*cluster as usize].push(index);
+            }
+
+            *statistics = Some(ClusterStatistics{
+                sizes: members.iter().map(|cluster_members| cluster_members.len()).collect(),
+                members,
+                coordinate_sums: vec![ndarray::Array1::zeros(dimension); number_of_clusters],
+                distance_sums: vec![0.0; number_of_clusters],
+                dirty: vec![true; number_of_clusters],
+                moves_since_refresh: COORDINATE_SUMS_REFRESH_INTERVAL,
+                infeasibility: self.compute_infeasibility(),
+            });
+        }
+
+        let statistics = statistics.as_mut().unwrap();
+
+        // Recalculamos las sumas de coordenadas desde cero, para que los centroides no acumulen
+        // errores de redondeo tras muchos movimientos. Cambian todos los centroides, asi que
+        // todos los clusters quedan sucios
+        if statistics.moves_since_refresh >= COORDINATE_SUMS_REFRESH_INTERVAL{
+            for cluster in 0..number_of_clusters{
+                statistics.coordinate_sums[cluster].fill(0.0);
+                for &index in statistics.members[cluster].iter(){
+                    statistics.coordinate_sums[cluster] += points[index].get_coordinates();
+                }
+                statistics.dirty[cluster] = true;
+            }
+            statistics.moves_since_refresh = 0;
+        }
+
+        for cluster in 0..number_of_clusters{
+            if statistics.dirty[cluster] == false{
+                continue;
+            }
+
+            statistics.distance_sums[cluster] = 0.0;
+            if statistics.sizes[cluster] > 0{
+                let centroid = Point::new(&statistics.coordinate_sums[cluster] / statistics.sizes[cluster] as f64);
+                for &index in statistics.members[cluster].iter(){
+                    statistics.distance_sums[cluster] += Point::distance(&points[index], &centroid);
+                }
+            }
+
+            statistics.dirty[cluster] = false;
+        }
     }
 
     /// Comprueba si el fitness de la solucion esta cacheado (ya calculado) o no
     pub fn is_fitness_cached(&self) -> bool{
-        match *self.fitness.borrow(){
+        match *self.fitness.lock().unwrap(){
             Some(_) => return true,
             None => return false,
         };
     }
 
-    /// Devuelve el primer vecino de la solucion valido que mejora la solucion
-    /// actual (el primero mejor)
-    /// Necesitamos saber cuantas evaluaciones podemos consumir como criterio de parada, para
-    /// evitar generar todo un vecindario cuando esto no sea posible por agotar las evaluaciones
-    pub fn get_neighbour(&self, left_iterations: i32, rng: &mut StdRng) -> FitnessEvaluationResult<Option<Self>> {
-
-        // Para llevar las cuentas de las llamadas a funcion fitness consumidas en la operacion
-        let mut fitness_consumed = 0;
+    /// Devuelve el primer vecino de la solucion valido que acepta el criterio de aceptacion (con
+    /// ImproveOnly, el primero mejor), junto a la decision tomada
+    /// Si el criterio pide reiniciar la busqueda, devolvemos el vecino con el que lo ha pedido y
+    /// la decision Restart, para que la busqueda decida como reiniciar
+    /// Las evaluaciones del fitness se hacen a traves del evaluador, de forma que dejamos de
+    /// generar el vecindario en cuanto se agotan las evaluaciones. En ese caso, o si no se acepta
+    /// ningun vecino, devolvemos None
+    pub fn get_neighbour(&self, evaluator: &Evaluator, acceptance: &mut dyn AcceptanceCriterion, rng: &mut StdRng) -> Option<(Self, Decision)> {
 
         // Fitness de la solucion a mejorar. Solo lo calculamos una unica vez
-        let (fitness_to_beat, ev_cons) = self.fitness_and_consumed();
-        fitness_consumed += ev_cons;
+        let fitness_to_beat = evaluator.fitness(self)?;
 
         // Tomo los generadores de vecinos
-        let mut neighbours_generator = NeighbourGenerator::generate_all_neighbours(self.data_points.len() as i32, self.number_of_clusters);
+        let mut neighbours_generator = NeighbourGenerator::generate_all_neighbours(self.get_data_points().len() as i32, self.get_number_of_clusters());
 
         // Mezclo los generadores de vecinos
         neighbours_generator.shuffle(rng);
@@ -172,58 +372,40 @@ impl<'a, 'b> Solution<'a, 'b> {
                 continue;
             }
 
-            // Tomamos el valor del fitness de la nueva solucion
-            let (current_fitness, ev_cons) = current_solution.fitness_and_consumed();
-            fitness_consumed += ev_cons;
-
-
-            // Comprobamos si hemos mejorado a la solucion original
-            if current_fitness < fitness_to_beat{
-                return FitnessEvaluationResult::new(Some(current_solution), fitness_consumed);
-            }
+            // Tomamos el valor del fitness de la nueva solucion. Si no quedan evaluaciones, no
+            // hemos encontrado un vecino mejor a tiempo
+            let current_fitness = evaluator.fitness(&current_solution)?;
 
-            // Comprobamos si hemos excedido el maximo de evaluaciones de las que disponiamos
-            if fitness_consumed >= left_iterations as u32{
-                // No hemos encontrado un vecino mejor a tiempo
-                return FitnessEvaluationResult::new(None, fitness_consumed);
+            // Comprobamos si el criterio acepta el vecino
+            match acceptance.decide(fitness_to_beat, current_fitness, rng){
+                Decision::Reject => continue,
+                decision => return Some((current_solution, decision)),
             }
         }
 
-        // No hemos encontrado un vecino mejor
-        return FitnessEvaluationResult::new(None, fitness_consumed);
+        // No hemos encontrado un vecino que se acepte
+        return None;
     }
 
     /// A partir de un NeighbourGenerator, genera la solucion que representa el
     /// generador aplicado a la solucion &self
-    fn generate_solution_from(&self, generator: NeighbourGenerator) -> Self{
-        let mut new_solution = Self{
-            cluster_indexes: self.cluster_indexes.clone(),
-            data_points: &self.data_points,
-            constraints: &self.constraints,
-            number_of_clusters: self.number_of_clusters,
-            lambda: self.lambda,
-            fitness: RefCell::new(None), // None porque hemos cambiado la solucion, por tanto,
-                                         // tendra otro valor de fitness
-        };
-
-        new_solution.cluster_indexes[generator.get_element_index() as usize] = generator.get_new_cluster();
+    pub fn generate_solution_from(&self, generator: NeighbourGenerator) -> Self{
+        // Copiamos tambien las estadisticas por cluster, de forma que el fitness del vecino se
+        // calcula de forma incremental. move_point deja el fitness sin evaluar, pues hemos
+        // cambiado la solucion y tendra otro valor de fitness
+        let mut new_solution = self.clone();
+        new_solution.move_point(generator.get_element_index() as usize, generator.get_new_cluster());
         return new_solution;
     }
 
     /// Genera una solucion inicial aleatoria, como punto de partida de las busquedas
     // TODO -- no puede dejar clusters vacios
-    pub fn generate_random_solution(
-        data_points: &'a DataPoints,
-        constraints: &'b Constraints,
-        number_of_clusters: i32,
-        rng: &mut StdRng
-    ) -> Self {
+    pub fn generate_random_solution(instance: &Arc<ProblemInstance>, rng: &mut StdRng) -> Self {
+        let number_of_clusters = instance.get_number_of_clusters();
 
         return Self::new(
-            (0..data_points.get_points().len()).into_iter().map(|_| rng.gen_range(0..number_of_clusters) as u32).collect(),
-            data_points,
-            constraints,
-            number_of_clusters,
+            (0..instance.number_of_points()).map(|_| rng.gen_range(0..number_of_clusters) as u32).collect(),
+            instance,
         );
 
     }
@@ -236,7 +418,7 @@ impl<'a, 'b> Solution<'a, 'b> {
         let cluster_points = self.get_points_in_cluster(cluster);
 
         // Comprobacion de seguridad
-        if cluster_points.len() == 0{
+        if cluster_points.is_empty() == true{
             panic!("[Err: Solution::intra_cluster_distance] Cluster without points");
         }
 
@@ -260,7 +442,7 @@ impl<'a, 'b> Solution<'a, 'b> {
 
         for (index, curr_cluster) in self.cluster_indexes.iter().enumerate(){
             if *curr_cluster == cluster{
-                cluster_points.push(&self.data_points.get_points()[index]);
+                cluster_points.push(&self.get_data_points().get_points()[index]);
             }
         }
 
@@ -283,19 +465,37 @@ impl<'a, 'b> Solution<'a, 'b> {
 
     /// Calcula la media de distancias intracluster sobre todos los clusters
     /// Esto representa una de las componentes de la funcion fitness
+    /// Usa las estadisticas por cluster, por lo que solo se recalculan los clusters que han
+    /// cambiado desde la ultima vez
     pub fn global_cluster_mean_distance(&self) -> f64{
+        self.update_statistics();
+        let statistics = self.statistics.lock().unwrap();
+        let statistics = statistics.as_ref().unwrap();
+
         let mut cum_sum = 0.0;
-        for i in 0 .. self.number_of_clusters as u32 {
-            cum_sum += self.intra_cluster_distance(i);
+        for cluster in 0 .. self.get_number_of_clusters() as usize {
+            // Comprobacion de seguridad
+            if statistics.sizes[cluster] == 0{
+                panic!("[Err: Solution::global_cluster_mean_distance] Cluster without points");
+            }
+
+            cum_sum += statistics.distance_sums[cluster] / statistics.sizes[cluster] as f64;
         }
 
-        return cum_sum / self.number_of_clusters as f64;
+        return cum_sum / self.get_number_of_clusters() as f64;
     }
 
-    /// Calcula el numero de restricciones que se violan en la solucion actual
+    /// Devuelve el numero de restricciones que se violan en la solucion actual
+    /// Usa el valor mantenido en las estadisticas por cluster
     pub fn infeasibility(&self) -> i32{
+        self.update_statistics();
+        return self.statistics.lock().unwrap().as_ref().unwrap().infeasibility;
+    }
+
+    /// Calcula desde cero el numero de restricciones que se violan en la solucion actual
+    fn compute_infeasibility(&self) -> i32{
         let mut infea = 0;
-        for ((first_index, second_index), value) in self.constraints.get_data(){
+        for ((first_index, second_index), value) in self.instance.get_constraints().get_data(){
 
             // Tomamos los dos indices de cluster para compararlos
             let first_cluster = self.cluster_indexes[*first_index as usize];
@@ -324,7 +524,7 @@ impl<'a, 'b> Solution<'a, 'b> {
     /// Muestra las distancias intracluster de cada uno de los clusters
     /// Esta funcion ha sido usada para debuggear el codigo
     pub fn show_intra_cluster_distances(&self){
-        for cluster in 0 .. self.number_of_clusters{
+        for cluster in 0 .. self.get_number_of_clusters(){
             println!("\tDistancia intra-cluster del cluster {}: {}", cluster, self.intra_cluster_distance(cluster as u32));
         }
 
@@ -335,7 +535,7 @@ impl<'a, 'b> Solution<'a, 'b> {
     pub fn get_clusters_with_more_than_one_point(&self) -> Vec<i32>{
         let mut clusters_with_more_than_one_point = vec![];
 
-        for cluster in 0..self.number_of_clusters{
+        for cluster in 0..self.get_number_of_clusters(){
             let points_in_cluster = self.get_points_in_cluster(cluster as u32);
 
             if points_in_cluster.len() >= 2{
@@ -352,10 +552,10 @@ impl<'a, 'b> Solution<'a, 'b> {
     pub fn get_cluster_without_points(&self) -> Vec<i32>{
         let mut clusters_without_points = vec![];
 
-        for cluster in 0..self.number_of_clusters{
+        for cluster in 0..self.get_number_of_clusters(){
             let points_in_cluster = self.get_points_in_cluster(cluster as u32);
 
-            if points_in_cluster.len() == 0{
+            if points_in_cluster.is_empty() == true{
                 clusters_without_points.push(cluster);
             }
         }
@@ -366,18 +566,27 @@ impl<'a, 'b> Solution<'a, 'b> {
 }
 
 /// Metodos asociados a la parte genetica de las practicas
-impl<'a, 'b> Solution<'a, 'b> {
+impl Solution {
 
     /// Dadas dos soluciones, devuelve aquella con mejor fitness
     /// Entendemos por mejor fitness, aquel con menor valor numerico, pues estamos buscando
     /// minimizar la funcion de fitness
-    pub fn binary_tournament<'c>(first: &'c Solution<'a, 'b>, second: &'c Solution<'a, 'b>) -> (&'c Solution<'a, 'b>, u32){
-        let (first_fitness, first_consumed) = first.fitness_and_consumed();
-        let (second_fitness, second_consumed) = second.fitness_and_consumed();
-        if first_fitness < second_fitness{
-            return (first, first_consumed + second_consumed);
-        }else{
-            return (second, first_consumed + second_consumed);
+    /// Si el evaluador no puede evaluar a uno de los dos, gana el otro. Si no puede evaluar a
+    /// ninguno, devolvemos el primero
+    pub fn binary_tournament<'c>(first: &'c Solution, second: &'c Solution, evaluator: &Evaluator) -> &'c Solution{
+        let first_fitness = evaluator.fitness(first);
+        let second_fitness = evaluator.fitness(second);
+
+        match (first_fitness, second_fitness){
+            (Some(first_fitness), Some(second_fitness)) => {
+                if first_fitness < second_fitness{
+                    return first;
+                }else{
+                    return second;
+                }
+            }
+            (None, Some(_)) => return second,
+            _ => return first,
         }
     }
 
@@ -391,23 +600,21 @@ impl<'a, 'b> Solution<'a, 'b> {
         // quedamos. Para ello, tomamos una permutacion aleatoria de {0, ..., gen_size - 1} y nos
         // quedamos con la primera mitad. La segunda mitad nos indicara las posiciones que usamos
         // del segundo padre
-        let mut positions_to_mutate: Vec<usize> = (0..gen_size as usize).collect();
+        let mut positions_to_mutate: Vec<usize> = (0..gen_size).collect();
         positions_to_mutate.shuffle(rng);
 
         // Nueva solucion a partir de la informacion de uno de los padres
         let mut crossed_solution = first.clone();
 
         // Tomamos los elementos aleatorios del primer padre
-        for index in 0..half_gen_size{
+        for &curr_index in positions_to_mutate.iter().take(half_gen_size){
             // Tenemos que usar el indice que indica de la permutacion aleatoria
-            let curr_index = positions_to_mutate[index];
             crossed_solution.cluster_indexes[curr_index] = first.cluster_indexes[curr_index];
         }
 
         // Tomamos los elementos aleatorios del segundo padre
-        for index in half_gen_size..gen_size{
+        for &curr_index in positions_to_mutate.iter().skip(half_gen_size){
             // Tenemos que usar el indice que indica de la permutacion aleatoria
-            let curr_index = positions_to_mutate[index];
             crossed_solution.cluster_indexes[curr_index] = second.cluster_indexes[curr_index];
         }
 
@@ -486,20 +693,18 @@ impl<'a, 'b> Solution<'a, 'b> {
         // si se da el caso. Otra opcion es comprobar que el cluster escogido tenga al menos dos
         // puntos asignados, pero entonces excluimos clusters con solo un punto, disminuyendo en
         // parte la variabilidad que introduce la mutacion
-        let mut_position_candidates: Vec<i32> = (0..mutated_sol.data_points.len() as i32).collect();
+        let mut_position_candidates: Vec<i32> = (0..mutated_sol.get_data_points().len() as i32).collect();
         let mut_position = mut_position_candidates.choose(rng).expect("No tenemos puntos en nuestro dataset que asignar a clusters");
 
         // Podemos elegir como nuevo valor aquellos que esten en el itervalo adecuado y que no sean
         // el cluster original que ya teniamos, pues estariamos perdiendo una mutacion efectiva
-        let mut new_cluster_candidates: Vec<i32> = (0..mutated_sol.number_of_clusters).collect();
+        let mut new_cluster_candidates: Vec<i32> = (0..mutated_sol.get_number_of_clusters()).collect();
         new_cluster_candidates.retain(|&x| x != mutated_sol.cluster_indexes[*mut_position as usize] as i32);
         let mut_value = new_cluster_candidates.choose(rng).expect("No hemos podido generar una lista de clusters candidatos");
 
-        // Mutamos el valor
-        mutated_sol.cluster_indexes[*mut_position as usize] = *mut_value as u32;
-
-        // Reseteamos el fitness, porque estamos haciendo un cambio a la solucion que devolvemos
-        mutated_sol.invalid_fitness_cache();
+        // Mutamos el valor. move_point resetea el fitness, porque estamos haciendo un cambio a la
+        // solucion que devolvemos
+        mutated_sol.move_point(*mut_position as usize, *mut_value as u32);
 
         // Comprobamos que la solucion sea valida. En caso de que no lo sea, la reparamos
         if mutated_sol.is_valid() == false{
@@ -533,8 +738,8 @@ impl<'a, 'b> Solution<'a, 'b> {
 
             // Mutamos dicho valor. No comprobamos que la mutacion sea ahora valida, para dar mas
             // variedad. Mas adelante repararemos la solucion
-            let new_cluster = rng.gen_range(0..mutated.number_of_clusters);
-            mutated.cluster_indexes[index] = new_cluster as u32;
+            let new_cluster = rng.gen_range(0..mutated.get_number_of_clusters());
+            mutated.move_point(index, new_cluster as u32);
         }
 
         // Reparamos la solucion si la solucion mutada acaba por no ser valida
@@ -552,7 +757,7 @@ impl<'a, 'b> Solution<'a, 'b> {
         // Tomamos los clusters sin puntos asignados
         let clusters_without_points = self.get_cluster_without_points();
 
-        if clusters_without_points.len() == 0{
+        if clusters_without_points.is_empty() == true{
             return;
         }
 
@@ -571,51 +776,64 @@ impl<'a, 'b> Solution<'a, 'b> {
         let point_indixes_selected_cluster = self.get_index_points_in_cluster(*selected_cluster as u32);
         let selected_point_index = point_indixes_selected_cluster.choose(rng).expect("No hay puntos en el cluster seleccionado");
 
-        // Realizamos la asignacion
-        self.cluster_indexes[*selected_point_index] = cluster_without_points as u32;
+        // Realizamos la asignacion. Al haber modificado la solucion, move_point invalida la
+        // cache del fitness
+        self.move_point(*selected_point_index, cluster_without_points as u32);
 
         // Si quedan mas clusters sin puntos asignados, volvemos a llamar a esta funcion para que
         // siga reparando la solucion
         if clusters_without_points.len() >= 2{
             self.repair_solution(rng);
         }
-
-        // Al haber modificado la solucion, debemos invalidar la cache del fitness
-        self.invalid_fitness_cache();
     }
 }
 
 /// Metodos asociados a la parte memetica de las practicas
-impl<'a, 'b> Solution<'a, 'b> {
-    pub fn soft_local_search(&self, max_fails: i32, rng: &mut StdRng) -> FitnessEvaluationResult<Self>{
-        let mut new_solution = self.clone();
-        let mut fit_eval_cons = 0;
+impl Solution {
+    pub fn soft_local_search(&self, max_fails: i32, evaluator: &Evaluator, rng: &mut StdRng) -> Self{
+        let indixes = self.soft_local_search_order(rng);
+        return self.soft_local_search_with_order(max_fails, &indixes, evaluator);
+    }
 
-        // Recorreremos las posiciones de los puntos en orden aleatorio
-        let mut indixes: Vec<i32> = (0..self.data_points.len() as i32).collect();
+    /// Genera el orden aleatorio en el que la busqueda local suave recorre las posiciones
+    /// Es la unica parte de la busqueda local suave que usa numeros aleatorios. Separarlo nos
+    /// permite generar los ordenes de toda una poblacion de forma secuencial y lanzar despues las
+    /// busquedas en paralelo, obteniendo los mismos resultados que en secuencial
+    pub fn soft_local_search_order(&self, rng: &mut StdRng) -> Vec<i32>{
+        let mut indixes: Vec<i32> = (0..self.get_data_points().len() as i32).collect();
         indixes.shuffle(rng);
+        return indixes;
+    }
+
+    /// Busqueda local suave recorriendo las posiciones en el orden dado por indixes
+    /// Si se agotan las evaluaciones del fitness, paramos la busqueda
+    pub fn soft_local_search_with_order(&self, max_fails: i32, indixes: &[i32], evaluator: &Evaluator) -> Self{
+        let mut new_solution = self.clone();
 
         // Valores iniciales para el algoritmo
         let mut fails = 0;
         let mut i = 0;
 
         // Iteramos sobre las asignaciones de clusters mientras no sobrepasemos el valor de fallos
-        while fails < max_fails && i < self.data_points.len(){
+        while fails < max_fails && i < self.get_data_points().len(){
             // Tomamos la posicion que toca cambiar en esta iteracion
             let index = indixes[i];
 
             // Seleccionamos el mejor cluster para este punto en la posicion index
-            let new_cluster_result = new_solution.select_best_cluster(index as u32);
-            let new_cluster = new_cluster_result.get_result();
-            fit_eval_cons += new_cluster_result.get_iterations_consumed();
+            let new_cluster = match new_solution.select_best_cluster(index as u32, evaluator){
+                Some(cluster) => cluster,
+
+                // Se han agotado las evaluaciones del fitness
+                None => break,
+            };
 
             // Realizamos el cambio, guardando el valor original de la asignacion
             let past_cluster = new_solution.cluster_indexes[index as usize];
-            new_solution.cluster_indexes[index as usize] = *new_cluster;
+            new_solution.move_point(index as usize, new_cluster);
 
             // Comprobamos si hemos realizado un cambio o no, incrementando el contador de fallos
             // en caso de que sea necesario
-            if *new_cluster == past_cluster{
+            if new_cluster == past_cluster{
                 fails += 1;
             }
 
@@ -623,41 +841,40 @@ impl<'a, 'b> Solution<'a, 'b> {
             i += 1;
         }
 
-        return FitnessEvaluationResult::new(new_solution, fit_eval_cons);
+        return new_solution;
     }
 
     /// Selecciona la mejor asignacion de cluster para un punto dado por su indice.
     /// La mejor asignacion es aquella que es valida y que tiene el minimo valor de fitness
     /// Esta operacion va a consumir muchas evaluaciones del fitness
+    /// Si el evaluador no puede evaluar todas las asignaciones, devolvemos None
     /// La solucion &self debe ser una solucion valida para poder hacer esta busqueda sin problemas
     // TODO -- TEST -- bastante facil de testear, ademas es una parte critica de los memeticos
-    pub fn select_best_cluster(&self, point_index: u32) -> FitnessEvaluationResult<u32>{
+    pub fn select_best_cluster(&self, point_index: u32, evaluator: &Evaluator) -> Option<u32>{
         // Comprobacion de seguridad
         debug_assert!(
             self.is_valid() == true,
             "La solucion original no es valida, no se puede buscar la mejor asignacion de cluster"
         );
 
-        let mut fit_eval_cons = 0;
         let mut best_cluster = -1;
         let mut best_fitness = -1.0;
 
         // Recorremos todos las posibles asignaciones de cluster
-        for cluster in 0..self.number_of_clusters{
+        for cluster in 0..self.get_number_of_clusters(){
 
-            // Generamos la solucion asociada al cambio a este cluster
+            // Generamos la solucion asociada al cambio a este cluster. move_point invalida la
+            // cache del fitness, que se recalcula de forma incremental
             let mut new_sol = self.clone();
-            new_sol.cluster_indexes[point_index as usize] = cluster as u32;
+            new_sol.move_point(point_index as usize, cluster as u32);
 
             // Comrpobamos que la solucion generada sea valida
             if new_sol.is_valid() == false{
                 continue;
             }
 
-            // Evaluamos el fitness. Para ello, previamente tenemos que invalidar la cache
-            new_sol.invalid_fitness_cache();
-            let (new_sol_fit, ev_cons) = new_sol.fitness_and_consumed();
-            fit_eval_cons += ev_cons;
+            // Evaluamos el fitness
+            let new_sol_fit = evaluator.fitness(&new_sol)?;
 
             // Comprobamos si esta es la mejor
             if best_fitness == -1.0 || new_sol_fit < best_fitness{
@@ -672,12 +889,12 @@ impl<'a, 'b> Solution<'a, 'b> {
             "No hemos encontrado una mejor asignacion. Esto no es correcto, pues la solucion original es valida y podria ser un primer candidato"
         );
 
-        return FitnessEvaluationResult::new(best_cluster as u32, fit_eval_cons);
+        return Some(best_cluster as u32);
     }
 }
 
 /// Metodos asociados al algoritmo de enfriamiento simulado
-impl<'a, 'b> Solution<'a, 'b>{
+impl Solution{
     /// Genera un vecino aleatorio unico, sin recurrir a usar generador de vecinos
     /// Se parece mucho a mutated. Sin embargo, en mutated generamos la mutacion y permitimos
     /// soluciones no validas, que son reparadas. Esto hace que en una mutacion pueda cambiar mas
@@ -710,6 +927,338 @@ impl<'a, 'b> Solution<'a, 'b>{
 
 }
 
+/// Metodos asociados a la busqueda de vecindario variable
+impl Solution{
+    /// Sacude la solucion reasignando k puntos distintos, cada uno a un cluster aleatorio distinto
+    /// del suyo. Es el vecindario k-esimo de la busqueda de vecindario variable
+    ///
+    /// A diferencia de hard_mutated, no necesitamos reparar: solo movemos puntos de clusters que
+    /// tienen mas de un punto, de forma que la solucion sigue siendo valida y difiere de la
+    /// original en exactamente k posiciones (o en todas las que se puedan mover, si k es demasiado
+    /// grande)
+    pub fn shaken(&self, k: usize, rng: &mut StdRng) -> Self{
+        let mut shaken = self.clone();
+
+        // Con un unico cluster no hay ningun movimiento posible
+        let number_of_clusters = self.get_number_of_clusters() as u32;
+        if number_of_clusters < 2{
+            return shaken;
+        }
+
+        // Tamaño de cada cluster, que actualizamos segun movemos puntos
+        let mut cluster_sizes = vec![0; number_of_clusters as usize];
+        for cluster in self.cluster_indexes.iter(){
+            cluster_sizes[*cluster as usize] += 1;
+        }
+
+        // Recorremos los puntos en orden aleatorio, de forma que no movemos dos veces el mismo
+        let mut point_indexes: Vec<usize> = (0..self.cluster_indexes.len()).collect();
+        point_indexes.shuffle(rng);
+
+        let mut moved = 0;
+        for point_index in point_indexes{
+            if moved >= k{
+                break;
+            }
+
+            // No podemos dejar el cluster del punto vacio
+            let old_cluster = shaken.cluster_indexes[point_index];
+            if cluster_sizes[old_cluster as usize] <= 1{
+                continue;
+            }
+
+            let new_cluster = (old_cluster + rng.gen_range(1..number_of_clusters)) % number_of_clusters;
+            shaken.move_point(point_index, new_cluster);
+            cluster_sizes[old_cluster as usize] -= 1;
+            cluster_sizes[new_cluster as usize] += 1;
+            moved += 1;
+        }
+
+        return shaken;
+    }
+}
+
+/// Metodos asociados a scatter search y path relinking
+impl Solution{
+    /// Devuelve la misma agrupacion, pero con las etiquetas de los clusters renombradas para que
+    /// coincidan lo maximo posible con las de reference
+    ///
+    /// Las etiquetas de los clusters son arbitrarias, asi que dos soluciones pueden representar la
+    /// misma agrupacion con vectores de asignacion muy distintos. Emparejamos los clusters de
+    /// forma que se maximice el numero total de puntos que comparten los clusters emparejados
+    /// (ver max_overlap_matching). Como el fitness no depende de las etiquetas, mantenemos el
+    /// fitness cacheado
+    pub fn relabeled_like(&self, reference: &Self) -> Self{
+        let new_labels = max_overlap_matching(&self.shared_points(reference));
+
+        let cluster_indexes = self.cluster_indexes.iter().map(|cluster| new_labels[*cluster as usize] as u32).collect();
+        let relabeled = Self::new(cluster_indexes, &self.instance);
+        *relabeled.fitness.lock().unwrap() = *self.fitness.lock().unwrap();
+
+        return relabeled;
+    }
+
+    /// Distancia de particion: minimo numero de puntos que hay que cambiar de cluster para pasar
+    /// de una agrupacion a la otra. Es el numero de puntos en los que difieren las soluciones
+    /// tras renombrar los clusters de other (ver relabeled_like), por lo que no depende de las
+    /// etiquetas de los clusters
+    pub fn partition_distance(&self, other: &Self) -> usize{
+        return other.relabeled_like(self).number_of_discrepancies(self) as usize;
+    }
+
+    /// Distancia de Rand: proporcion de parejas de puntos en las que las dos agrupaciones no
+    /// coinciden, es decir, que estan juntos en una solucion y separados en la otra. Es 1 menos el
+    /// indice de Rand, y tampoco depende de las etiquetas de los clusters
+    ///
+    /// La calculamos a partir de la tabla de contingencia, sin recorrer todas las parejas
+    pub fn rand_distance(&self, other: &Self) -> f64{
+        let number_of_points = self.cluster_indexes.len();
+        if number_of_points < 2{
+            return 0.0;
+        }
+
+        let pairs = |count: usize| -> usize {count * count.saturating_sub(1) / 2};
+        let shared_points = self.shared_points(other);
+
+        // Parejas juntas en self, juntas en other y juntas en ambas
+        let together_in_self: usize = shared_points.iter().map(|row| pairs(row.iter().sum())).sum();
+        let together_in_other: usize = (0..shared_points.len()).map(|other_cluster| pairs(shared_points.iter().map(|row| row[other_cluster]).sum())).sum();
+        let together_in_both: usize = shared_points.iter().flatten().map(|count| pairs(*count)).sum();
+
+        let disagreements = together_in_self + together_in_other - 2 * together_in_both;
+        return disagreements as f64 / pairs(number_of_points) as f64;
+    }
+
+    /// Tabla de contingencia: puntos que comparten cada cluster de esta solucion con cada cluster
+    /// de other
+    fn shared_points(&self, other: &Self) -> Vec<Vec<usize>>{
+        let number_of_clusters = self.get_number_of_clusters() as usize;
+
+        let mut shared_points = vec![vec![0; number_of_clusters]; number_of_clusters];
+        for (own, other) in self.cluster_indexes.iter().zip(other.cluster_indexes.iter()){
+            shared_points[*own as usize][*other as usize] += 1;
+        }
+
+        return shared_points;
+    }
+}
+
+/// Metodos asociados a la forma canonica de una solucion
+impl Solution{
+    /// Asignacion de clusters en forma canonica: renumeramos los clusters por orden de primera
+    /// aparicion, de forma que el primer punto esta en el cluster 0, el primer punto que no esta
+    /// en el cluster 0 esta en el cluster 1, etc. Dos soluciones representan la misma agrupacion
+    /// si y solo si tienen la misma forma canonica
+    pub fn canonical_cluster_indexes(&self) -> Vec<u32>{
+        let mut new_labels: Vec<Option<u32>> = vec![None; self.get_number_of_clusters() as usize];
+        let mut next_label = 0;
+
+        return self.cluster_indexes.iter().map(|cluster| {
+            let new_label = new_labels[*cluster as usize].get_or_insert_with(|| {
+                next_label += 1;
+                return next_label - 1;
+            });
+            return *new_label;
+        }).collect();
+    }
+
+    /// Devuelve la misma solucion con las etiquetas en forma canonica (ver
+    /// canonical_cluster_indexes). Mantenemos el fitness cacheado, pues no depende de las
+    /// etiquetas
+    pub fn canonicalized(&self) -> Self{
+        let canonical = Self::new(self.canonical_cluster_indexes(), &self.instance);
+        *canonical.fitness.lock().unwrap() = *self.fitness.lock().unwrap();
+        return canonical;
+    }
+}
+
+/// Dos soluciones son iguales si representan la misma agrupacion, aunque usen etiquetas distintas
+/// para los clusters. Suponemos que ambas soluciones son de la misma instancia
+impl PartialEq for Solution{
+    fn eq(&self, other: &Self) -> bool{
+        return self.canonical_cluster_indexes() == other.canonical_cluster_indexes();
+    }
+}
+
+impl Eq for Solution{}
+
+/// Consistente con PartialEq: el hash se calcula sobre la forma canonica
+impl Hash for Solution{
+    fn hash<H: Hasher>(&self, state: &mut H){
+        self.canonical_cluster_indexes().hash(state);
+    }
+}
+
+/// Empareja las filas con las columnas de una matriz cuadrada de forma que se maximice la suma de
+/// los valores emparejados. Devuelve, para cada fila, la columna con la que se empareja
+///
+/// Es el algoritmo hungaro, en O(n^3), sobre los costes max - value. Con las tablas de contingencia
+/// entre dos soluciones, n es el numero de clusters, asi que es muy barato
+fn max_overlap_matching(values: &[Vec<usize>]) -> Vec<usize>{
+    let size = values.len();
+    let max_value = values.iter().flatten().copied().max().unwrap_or(0);
+    let cost = |row: usize, col: usize| -> i64 {(max_value - values[row][col]) as i64};
+
+    // Potenciales de filas y columnas. Trabajamos con indices desde 1, usando la columna 0 como
+    // columna ficticia desde la que se buscan los caminos de aumento
+    let mut row_potential = vec![0i64; size + 1];
+    let mut col_potential = vec![0i64; size + 1];
+    let mut row_of_col = vec![0usize; size + 1];
+    let mut previous_col = vec![0usize; size + 1];
+
+    for row in 1..=size{
+        row_of_col[0] = row;
+        let mut current_col = 0;
+        let mut min_slack = vec![i64::MAX; size + 1];
+        let mut used = vec![false; size + 1];
+
+        // Buscamos un camino de aumento desde la fila row
+        loop{
+            used[current_col] = true;
+            let current_row = row_of_col[current_col];
+            let mut delta = i64::MAX;
+            let mut next_col = 0;
+
+            for col in 1..=size{
+                if used[col] == true{
+                    continue;
+                }
+
+                let slack = cost(current_row - 1, col - 1) - row_potential[current_row] - col_potential[col];
+                if slack < min_slack[col]{
+                    min_slack[col] = slack;
+                    previous_col[col] = current_col;
+                }
+                if min_slack[col] < delta{
+                    delta = min_slack[col];
+                    next_col = col;
+                }
+            }
+
+            for col in 0..=size{
+                if used[col] == true{
+                    row_potential[row_of_col[col]] += delta;
+                    col_potential[col] -= delta;
+                }else{
+                    min_slack[col] -= delta;
+                }
+            }
+
+            current_col = next_col;
+            if row_of_col[current_col] == 0{
+                break;
+            }
+        }
+
+        // Aumentamos el emparejamiento a lo largo del camino
+        while current_col != 0{
+            let col = previous_col[current_col];
+            row_of_col[current_col] = row_of_col[col];
+            current_col = col;
+        }
+    }
+
+    let mut col_of_row = vec![0; size];
+    for col in 1..=size{
+        col_of_row[row_of_col[col] - 1] = col - 1;
+    }
+
+    return col_of_row;
+}
+
+/// Metodos asociados al algoritmo CHC
+impl Solution{
+    /// Cruce HUX adaptado a asignaciones de clusters. Genera dos hijos intercambiando entre los
+    /// padres exactamente la mitad de los puntos en los que difieren, escogidos aleatoriamente
+    ///
+    /// Antes de comparar renombramos los clusters de second para que coincidan con los de first
+    /// (ver relabeled_like). Si no, dos padres que representan la misma agrupacion con distintas
+    /// etiquetas diferirian en casi todos los puntos. Reparamos los hijos que dejen algun cluster
+    /// vacio
+    pub fn hux_cross(first: &Self, second: &Self, rng: &mut StdRng) -> (Self, Self){
+        let second = second.relabeled_like(first);
+
+        let mut differing_positions: Vec<usize> = (0..first.cluster_indexes.len())
+            .filter(|index| first.cluster_indexes[*index] != second.cluster_indexes[*index])
+            .collect();
+        differing_positions.shuffle(rng);
+
+        let mut first_child = first.clone();
+        let mut second_child = second.clone();
+        for index in differing_positions.iter().take(differing_positions.len() / 2){
+            first_child.move_point(*index, second.cluster_indexes[*index]);
+            second_child.move_point(*index, first.cluster_indexes[*index]);
+        }
+
+        // Si los padres no difieren en al menos dos puntos, los hijos son copias de los padres y
+        // conservarian el fitness cacheado
+        first_child.invalid_fitness_cache();
+        second_child.invalid_fitness_cache();
+
+        for child in [&mut first_child, &mut second_child]{
+            if child.is_valid() == false{
+                child.repair_solution(rng);
+            }
+        }
+
+        return (first_child, second_child);
+    }
+}
+
+/// Operadores de cruce que no dependen de las etiquetas de los clusters
+impl Solution{
+    /// Cruce uniforme tras renombrar los clusters de second para que coincidan con los de first
+    /// (ver relabeled_like). Asi, dos padres que representan la misma agrupacion con etiquetas
+    /// distintas generan un hijo con esa misma agrupacion, y no una mezcla sin sentido
+    pub fn aligned_uniform_cross(first: &Self, second: &Self, rng: &mut StdRng) -> Self{
+        return Self::uniform_cross(first, &second.relabeled_like(first), rng);
+    }
+
+    /// Cruce por segmento fijo tras renombrar los clusters de second para que coincidan con los
+    /// de first. Ver aligned_uniform_cross
+    pub fn aligned_cross_segment(first: &Self, second: &Self, rng: &mut StdRng) -> Self{
+        return Self::cross_segment(first, &second.relabeled_like(first), rng);
+    }
+
+    /// Cruce orientado a grupos: el hijo hereda clusters completos de los padres, en lugar de
+    /// asignaciones sueltas de puntos
+    ///
+    /// Tras renombrar los clusters de second para que coincidan con los de first, escogemos
+    /// aleatoriamente la mitad de las etiquetas, y el hijo hereda de first los clusters con esas
+    /// etiquetas. Del resto de etiquetas, hereda los clusters de second, salvo los puntos que ya
+    /// estan en un cluster heredado de first. Los puntos que quedan sin asignar son los que second
+    /// pone en una etiqueta heredada de first, y los devolvemos al cluster que tienen en first.
+    /// Reparamos el hijo si queda algun cluster vacio
+    pub fn group_cross(first: &Self, second: &Self, rng: &mut StdRng) -> Self{
+        let second = second.relabeled_like(first);
+        let number_of_clusters = first.get_number_of_clusters() as usize;
+
+        // Etiquetas de los clusters que heredamos de first
+        let mut labels: Vec<usize> = (0..number_of_clusters).collect();
+        labels.shuffle(rng);
+        let mut from_first = vec![false; number_of_clusters];
+        for label in labels.iter().take((number_of_clusters / 2).max(1)){
+            from_first[*label] = true;
+        }
+
+        let cluster_indexes: Vec<u32> = first.cluster_indexes.iter().zip(second.cluster_indexes.iter())
+            .map(|(first_cluster, second_cluster)| {
+                if from_first[*first_cluster as usize] == true || from_first[*second_cluster as usize] == true{
+                    return *first_cluster;
+                }
+                return *second_cluster;
+            })
+            .collect();
+
+        let mut crossed_solution = Self::new(cluster_indexes, &first.instance);
+        if crossed_solution.is_valid() == false{
+            crossed_solution.repair_solution(rng);
+        }
+
+        return crossed_solution;
+    }
+}
+
 #[cfg(test)]
 mod tests{
     use crate::problem_datatypes::Solution;
@@ -717,9 +1266,14 @@ mod tests{
     use crate::problem_datatypes::Point;
     use crate::problem_datatypes::Constraints;
     use crate::problem_datatypes::ConstraintType;
+    use crate::problem_datatypes::ProblemInstance;
 
     use rand::rngs::StdRng;
+    use std::sync::Arc;
     use rand::SeedableRng;
+    use rand::Rng;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
 
     // Para comprobar que dos soluciones son practicamente iguales (ignorando problemas
     // del punto flotante)
@@ -751,7 +1305,8 @@ mod tests{
 
         let number_of_clusters = 4;
 
-        let solution = Solution::new(cluster_indexes, &data_points, &constraints, number_of_clusters);
+        let instance = Arc::new(ProblemInstance::new(data_points, constraints, number_of_clusters));
+        let solution = Solution::new(cluster_indexes, &instance);
         callback(&solution)
     }
 
@@ -772,7 +1327,7 @@ mod tests{
     fn test_lambda_is_correct_over_basic_sol(){
         generate_basic_solution(|solution| {
             let calculated_lambda = solution.get_lambda();
-            let expected_lambda = (2.0 as f64).sqrt() / 5.0;
+            let expected_lambda = 2.0_f64.sqrt() / 5.0;
             assert_approx_eq::assert_approx_eq!(calculated_lambda, expected_lambda, epsilon());
 
         });
@@ -791,7 +1346,7 @@ mod tests{
         generate_basic_solution(|solution| {
             // Modifico la solucion
             let cluster_indexes = vec![1, 1, 2, 3, 0, 1];
-            let other_solution = Solution::new(cluster_indexes, solution.data_points, solution.constraints, solution.number_of_clusters);
+            let other_solution = Solution::new(cluster_indexes, solution.get_instance());
 
             let calc_infea = other_solution.infeasibility();
             let exp_infea = 3; // Se violan las dos must link y una CannotLink
@@ -836,12 +1391,12 @@ mod tests{
         generate_basic_solution(|solution| {
             // Distancia intracluster del primer cluster
             let calc_intra = solution.intra_cluster_distance(0);
-            let exp_intra = 0.7071067811865476;
+            let exp_intra = std::f64::consts::FRAC_1_SQRT_2;
             assert_approx_eq!(calc_intra, exp_intra, epsilon());
 
             // Distancia intracluster del segundo cluster
             let calc_intra = solution.intra_cluster_distance(1);
-            let exp_intra = 0.7071067811865476;
+            let exp_intra = std::f64::consts::FRAC_1_SQRT_2;
             assert_approx_eq!(calc_intra, exp_intra, epsilon());
 
             // Distancia intracluster del tercer cluster
@@ -862,7 +1417,7 @@ mod tests{
     fn test_global_cluster_distance_over_basic_sol(){
         generate_basic_solution(|solution| {
             let calc_global_dist = solution.global_cluster_mean_distance();
-            let exp_global_dist = (0.7071067811865476 * 2.0) / 4.0;
+            let exp_global_dist = (std::f64::consts::FRAC_1_SQRT_2 * 2.0) / 4.0;
             assert_approx_eq!(calc_global_dist, exp_global_dist, epsilon());
         });
 
@@ -873,8 +1428,8 @@ mod tests{
         generate_basic_solution(|solution| {
             let calc_fitness = solution.fitness();
 
-            let exp_lambda = (2.0 as f64).sqrt() / 5.0;
-            let exp_global_dist = (0.7071067811865476 * 2.0) / 4.0;
+            let exp_lambda = 2.0_f64.sqrt() / 5.0;
+            let exp_global_dist = (std::f64::consts::FRAC_1_SQRT_2 * 2.0) / 4.0;
             let exp_infea = 2;
             let exp_fitness = exp_lambda * exp_infea as f64 + exp_global_dist;
 
@@ -884,12 +1439,38 @@ mod tests{
 
     }
 
+    #[test]
+    // Movemos puntos aleatoriamente y comprobamos que el fitness calculado de forma incremental
+    // coincide con el fitness calculado desde cero sobre la misma asignacion de clusters
+    fn test_incremental_fitness_matches_full_calculation(){
+        generate_basic_solution(|solution| {
+            let mut rng = StdRng::seed_from_u64(123456789);
+            let mut current = solution.clone();
+            current.fitness();
+
+            for _ in 0..max_test_iterations(){
+                let point_index = rng.gen_range(0..current.cluster_indexes.len());
+                let new_cluster = rng.gen_range(0..current.get_number_of_clusters()) as u32;
+                current.move_point(point_index, new_cluster);
+
+                let full = Solution::new(current.get_cluster_indexes(), solution.get_instance());
+                assert_eq!(current.infeasibility(), full.infeasibility());
+                assert_eq!(current.is_valid(), full.is_valid());
+
+                // Con clusters vacios el fitness no esta definido
+                if full.is_valid() == true{
+                    assert_approx_eq!(current.fitness(), full.fitness(), 1e-12);
+                }
+            }
+        });
+    }
+
     #[test]
     fn test_solutions_are_not_valid(){
         generate_basic_solution(|solution| {
             // A partir de la solucion basica, generamos una solucion que no sea valida
             let cluster_indexes = vec![0, 0, 0, 0, 1, 1];
-            let solution = Solution::new(cluster_indexes, &solution.data_points, &solution.constraints, solution.number_of_clusters);
+            let solution = Solution::new(cluster_indexes, solution.get_instance());
 
             let expected_is_valid = false;
             let calc_is_valid = solution.is_valid();
@@ -897,7 +1478,7 @@ mod tests{
 
             // Ahora generamos una solucion que no sea valida por el tamaño del vector (pequeño)
             let cluster_indexes = vec![0, 1, 2, 3];
-            let solution = Solution::new(cluster_indexes, &solution.data_points, &solution.constraints, solution.number_of_clusters);
+            let solution = Solution::new(cluster_indexes, solution.get_instance());
 
             let expected_is_valid = false;
             let calc_is_valid = solution.is_valid();
@@ -905,7 +1486,7 @@ mod tests{
 
             // Ahora generamos una solucion que no sea valida por el tamaño del vector (grande)
             let cluster_indexes = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-            let solution = Solution::new(cluster_indexes, &solution.data_points, &solution.constraints, solution.number_of_clusters);
+            let solution = Solution::new(cluster_indexes, solution.get_instance());
 
             let expected_is_valid = false;
             let calc_is_valid = solution.is_valid();
@@ -920,7 +1501,7 @@ mod tests{
             // A partir de la solucion basica, generamos una solucion que no sea valida, pero con
             // una dimensionalidad correcta
             let cluster_indexes = vec![0, 0, 0, 0, 1, 1];
-            let solution = Solution::new(cluster_indexes, &solution.data_points, &solution.constraints, solution.number_of_clusters);
+            let solution = Solution::new(cluster_indexes, solution.get_instance());
 
             // Reparamos la solucion. Al tener dependencia aleatoria, hacemos esto muchas veces
             // sobre la solucion mala original y vemos que se hace bien
@@ -936,7 +1517,7 @@ mod tests{
 
             // Generamos otra solucion que no sea valida, y vemos si se repara bien
             let cluster_indexes = vec![2, 1, 2, 0, 1, 1];
-            let solution = Solution::new(cluster_indexes, &solution.data_points, &solution.constraints, solution.number_of_clusters);
+            let solution = Solution::new(cluster_indexes, solution.get_instance());
 
             // Reparamos la solucion. Al tener dependencia aleatoria, hacemos esto muchas veces
             // sobre la solucion mala original y vemos que se hace bien
@@ -995,4 +1576,187 @@ mod tests{
 
         });
     }
+
+    #[test]
+    // La sacudida de tamaño k da una solucion valida que difiere exactamente en k posiciones
+    fn test_shaken_solution_differs_in_k_positions(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+
+        for _ in 0..100{
+            let mut solution = Solution::generate_random_solution(&instance, &mut rng);
+            solution.repair_solution(&mut rng);
+
+            let k = rng.gen_range(1..10);
+            let shaken = solution.shaken(k, &mut rng);
+            assert!(shaken.is_valid() == true);
+            assert_eq!(shaken.number_of_discrepancies(&solution), k as i32);
+        }
+    }
+
+    #[test]
+    // La forma canonica numera los clusters por orden de aparicion, y la igualdad y el hash no
+    // dependen de las etiquetas
+    fn test_canonical_form_equality_and_hash(){
+        generate_basic_solution(|solution| {
+            let permuted = Solution::new(vec![2, 0, 3, 1, 2, 0], solution.get_instance());
+            let different = Solution::new(vec![2, 0, 3, 1, 2, 2], solution.get_instance());
+
+            assert_eq!(permuted.canonical_cluster_indexes(), vec![0, 1, 2, 3, 0, 1]);
+            assert_eq!(permuted.canonicalized().get_cluster_indexes(), solution.get_cluster_indexes());
+            assert!(permuted == *solution);
+            assert!(different != *solution);
+
+            let hash = |solution: &Solution| -> u64 {
+                let mut hasher = DefaultHasher::new();
+                solution.hash(&mut hasher);
+                return hasher.finish();
+            };
+            assert_eq!(hash(&permuted), hash(solution));
+        });
+    }
+
+    #[test]
+    // La distancia de particion coincide con la minima distancia de Hamming probando todas las
+    // permutaciones de etiquetas, y la distancia de Rand con la que se obtiene recorriendo todas
+    // las parejas de puntos
+    fn test_partition_distances_match_brute_force(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let number_of_clusters = instance.get_number_of_clusters() as u32;
+
+        // Permutaciones de las etiquetas de los clusters
+        let mut permutations: Vec<Vec<u32>> = vec![vec![]];
+        for _ in 0..number_of_clusters{
+            permutations = permutations.iter()
+                .flat_map(|permutation| (0..number_of_clusters).filter(move |label| permutation.contains(label) == false).map(move |label| {
+                    let mut extended = permutation.clone();
+                    extended.push(label);
+                    return extended;
+                }))
+                .collect();
+        }
+
+        for _ in 0..50{
+            let mut first = Solution::generate_random_solution(&instance, &mut rng);
+            first.repair_solution(&mut rng);
+            let mut second = Solution::generate_random_solution(&instance, &mut rng);
+            second.repair_solution(&mut rng);
+            let first_indexes = first.get_cluster_indexes();
+            let second_indexes = second.get_cluster_indexes();
+
+            let expected_distance = permutations.iter()
+                .map(|permutation| first_indexes.iter().zip(second_indexes.iter()).filter(|(own, other)| **own != permutation[**other as usize]).count())
+                .min()
+                .unwrap();
+            assert_eq!(first.partition_distance(&second), expected_distance);
+            assert_eq!(second.partition_distance(&first), expected_distance);
+
+            let number_of_points = first_indexes.len();
+            let mut disagreements = 0;
+            for i in 0..number_of_points{
+                for j in (i + 1)..number_of_points{
+                    if (first_indexes[i] == first_indexes[j]) != (second_indexes[i] == second_indexes[j]){
+                        disagreements += 1;
+                    }
+                }
+            }
+            let expected_rand_distance = disagreements as f64 / (number_of_points * (number_of_points - 1) / 2) as f64;
+            assert_approx_eq!(first.rand_distance(&second), expected_rand_distance, 1e-12);
+        }
+    }
+
+    #[test]
+    // Renombrar las etiquetas no cambia la agrupacion, asi que la distancia entre una solucion y
+    // la misma solucion con las etiquetas permutadas es cero
+    fn test_relabeled_solution_has_zero_distance(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+
+        for _ in 0..100{
+            let mut solution = Solution::generate_random_solution(&instance, &mut rng);
+            solution.repair_solution(&mut rng);
+
+            let number_of_clusters = instance.get_number_of_clusters() as u32;
+            let shift = rng.gen_range(1..number_of_clusters.max(2));
+            let permuted_indexes = solution.get_cluster_indexes().iter().map(|cluster| (cluster + shift) % number_of_clusters).collect();
+            let permuted = Solution::new(permuted_indexes, &instance);
+
+            assert_eq!(permuted.relabeled_like(&solution).get_cluster_indexes(), solution.get_cluster_indexes());
+            assert_eq!(solution.partition_distance(&permuted), 0);
+
+            let shaken = solution.shaken(3, &mut rng);
+            assert!(solution.partition_distance(&shaken) <= 3);
+        }
+    }
+
+    #[test]
+    // Cada hijo del cruce HUX cambia exactamente la mitad de los puntos en los que difieren los
+    // padres, salvo que haya que repararlo. Con padres iguales, los hijos son copias de los padres
+    fn test_hux_cross_swaps_half_of_the_differences(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let mut unrepaired_crosses = 0;
+
+        for _ in 0..100{
+            let mut first = Solution::generate_random_solution(&instance, &mut rng);
+            first.repair_solution(&mut rng);
+            let second = first.shaken(10, &mut rng);
+            let distance = first.partition_distance(&second);
+
+            let (first_child, second_child) = Solution::hux_cross(&first, &second, &mut rng);
+            assert!(first_child.is_valid() == true);
+            assert!(second_child.is_valid() == true);
+            assert!(first_child.is_fitness_cached() == false);
+
+            // Los hijos estan en las etiquetas de first. Si todos los clusters de los padres
+            // tienen mas de distance / 2 puntos, ningun hijo puede quedarse con un cluster vacio,
+            // asi que no se reparan y la distancia es exacta
+            let second = second.relabeled_like(&first);
+            let smallest_cluster = |solution: &Solution| (0..solution.get_number_of_clusters() as u32)
+                .map(|cluster| solution.cluster_indexes.iter().filter(|assigned| **assigned == cluster).count())
+                .min()
+                .unwrap();
+
+            if smallest_cluster(&first) > distance / 2 && smallest_cluster(&second) > distance / 2{
+                assert_eq!(first_child.number_of_discrepancies(&first) as usize, distance / 2);
+                assert_eq!(second_child.number_of_discrepancies(&second) as usize, distance / 2);
+                assert_eq!(first_child.number_of_discrepancies(&second) as usize, distance - distance / 2);
+                unrepaired_crosses += 1;
+            }else{
+                assert!(first.partition_distance(&first_child) <= distance);
+            }
+
+            let (first_child, _) = Solution::hux_cross(&first, &first, &mut rng);
+            assert_eq!(first_child.get_cluster_indexes(), first.get_cluster_indexes());
+        }
+
+        // La mayoria de cruces deben comprobar la distancia exacta
+        assert!(unrepaired_crosses > 50);
+    }
+
+    #[test]
+    // Cruzando dos padres que representan la misma agrupacion con etiquetas distintas, los cruces
+    // alineados y el cruce por grupos devuelven esa misma agrupacion
+    fn test_label_invariant_crossovers_keep_equal_partitions(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let number_of_clusters = instance.get_number_of_clusters() as u32;
+
+        for _ in 0..100{
+            let mut first = Solution::generate_random_solution(&instance, &mut rng);
+            first.repair_solution(&mut rng);
+            let shift = rng.gen_range(1..number_of_clusters.max(2));
+            let permuted_indexes = first.get_cluster_indexes().iter().map(|cluster| (cluster + shift) % number_of_clusters).collect();
+            let second = Solution::new(permuted_indexes, &instance);
+
+            let aligned_uniform = Solution::aligned_uniform_cross(&first, &second, &mut rng);
+            let aligned_segment = Solution::aligned_cross_segment(&first, &second, &mut rng);
+            let group = Solution::group_cross(&first, &second, &mut rng);
+            for child in [aligned_uniform, aligned_segment, group]{
+                assert_eq!(child.get_cluster_indexes(), first.get_cluster_indexes());
+                assert!(child.is_fitness_cached() == false);
+            }
+        }
+    }
 }
@@ -0,0 +1,121 @@
+use crate::problem_datatypes::Solution;
+use crate::fitness_evolution::FitnessEvolution;
+
+use std::time::Duration;
+
+/// Resultado de lanzar uno de los algoritmos de busqueda
+///
+/// Guarda la solucion encontrada junto al desglose de su fitness, las evaluaciones del fitness
+/// consumidas, el tiempo empleado y la evolucion del fitness a lo largo de las iteraciones. Asi
+/// podemos usar los algoritmos desde otros programas sin tener que leer la salida por pantalla
+#[derive(Debug)]
+pub struct RunResult{
+    solution: Solution,
+
+    /// Desglose del fitness de la solucion
+    /// fitness = global_cluster_mean_distance + lambda * infeasibility
+    global_cluster_mean_distance: f64,
+    infeasibility: i32,
+    lambda: f64,
+    fitness: f64,
+
+    /// Evaluaciones del fitness que ha consumido el algoritmo
+    fitness_evaluations: u32,
+
+    /// Tiempo que ha tardado el algoritmo en ejecutarse
+    elapsed: Duration,
+
+    fitness_evolution: FitnessEvolution,
+}
+
+impl RunResult{
+    /// Construye el resultado, calculando el desglose del fitness de la solucion
+    pub fn new(solution: Solution, fitness_evaluations: u32, elapsed: Duration, fitness_evolution: FitnessEvolution) -> Self{
+        let global_cluster_mean_distance = solution.global_cluster_mean_distance();
+        let infeasibility = solution.infeasibility();
+        let lambda = solution.get_lambda();
+        let fitness = solution.fitness();
+
+        return Self{
+            solution,
+            global_cluster_mean_distance,
+            infeasibility,
+            lambda,
+            fitness,
+            fitness_evaluations,
+            elapsed,
+            fitness_evolution,
+        };
+    }
+
+    pub fn get_solution(&self) -> &Solution{
+        return &self.solution;
+    }
+
+    pub fn global_cluster_mean_distance(&self) -> f64{
+        return self.global_cluster_mean_distance;
+    }
+
+    pub fn infeasibility(&self) -> i32{
+        return self.infeasibility;
+    }
+
+    pub fn get_lambda(&self) -> f64{
+        return self.lambda;
+    }
+
+    pub fn fitness(&self) -> f64{
+        return self.fitness;
+    }
+
+    pub fn get_fitness_evaluations(&self) -> u32{
+        return self.fitness_evaluations;
+    }
+
+    pub fn get_elapsed(&self) -> Duration{
+        return self.elapsed;
+    }
+
+    /// Tiempo empleado en segundos, que es el formato que mostramos por pantalla
+    pub fn elapsed_seconds(&self) -> f64{
+        return self.elapsed.as_secs() as f64 + self.elapsed.subsec_nanos() as f64 * 1e-9;
+    }
+
+    pub fn get_fitness_evolution(&self) -> &FitnessEvolution{
+        return &self.fitness_evolution;
+    }
+
+    /// Devuelve la solucion, consumiendo el resultado
+    pub fn into_solution(self) -> Solution{
+        return self.solution;
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::algorithms::local_search;
+    use crate::acceptance_criterion::ImproveOnly;
+    use crate::problem_datatypes::ProblemInstance;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    // El desglose del fitness debe cuadrar con el fitness de la solucion, y no podemos consumir
+    // mas evaluaciones de las dadas
+    fn test_run_result_breakdown(){
+        let mut rng = StdRng::seed_from_u64(123456789);
+        let instance = ProblemInstance::random_instance(&mut rng);
+        let result = local_search::run(&instance, 500, &mut ImproveOnly, &mut rng);
+
+        let exp_fitness = result.global_cluster_mean_distance() + result.get_lambda() * result.infeasibility() as f64;
+        assert_approx_eq!(result.fitness(), exp_fitness);
+        assert_approx_eq!(result.fitness(), result.get_solution().fitness());
+        assert!(result.get_fitness_evaluations() <= 500);
+
+        // El ultimo valor de la evolucion del fitness es el de la solucion devuelta
+        let fitness_at_iteration = result.get_fitness_evolution().get_fitness_at_iteration();
+        assert_approx_eq!(*fitness_at_iteration.last().unwrap(), result.fitness());
+    }
+}
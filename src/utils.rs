@@ -1,15 +1,112 @@
-/// Funciones auxiliares
+//! Funciones auxiliares
 
-use crate::arg_parser::SearchType;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
+use rand::Rng;
+use rand::rngs::StdRng;
 
 
 /// Genera un nombre de fichero para los FitnessEvolution que guardamos. El nombre del fichero sera
-/// la carpeta, mas el tipo de busqueda, mas la fecha en la que se genero el archivo
-pub fn generate_file_name(search_type: SearchType) -> String{
+/// la carpeta, mas el nombre del algoritmo de busqueda, mas la fecha en la que se genero el archivo
+pub fn generate_file_name(search_type: &str) -> String{
     let dir_path = "./fitness_evolution_data/".to_string();
-    let search_type = format!("{:?}", search_type);
     let timestamp = format!("{}", Utc::now().format("%Y-%M-%d--%H:%M:%S"));
 
     return format!("{}/{}--{}", dir_path, search_type, timestamp);
 }
+
+/// Aplica function a cada elemento de items, repartiendo los elementos en bloques consecutivos
+/// entre number_of_workers hilos. Los resultados se devuelven en el mismo orden que items, por lo
+/// que el resultado no depende del numero de hilos usado
+///
+/// Con number_of_workers <= 1, o con un unico elemento, no se lanza ningun hilo
+pub fn parallel_map<T, R, F>(items: &[T], number_of_workers: usize, function: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    if number_of_workers <= 1 || items.len() <= 1{
+        return items.iter().map(function).collect();
+    }
+
+    // Cada hilo trabaja sobre un bloque consecutivo de elementos
+    let chunk_size = items.len().div_ceil(number_of_workers);
+    let function = &function;
+
+    return std::thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(function).collect::<Vec<R>>()))
+            .collect();
+
+        // Juntamos los resultados respetando el orden de los bloques
+        return workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Uno de los hilos de trabajo ha fallado"))
+            .collect();
+    });
+}
+
+/// Escoge count indices girando una ruleta count veces, donde cada indice tiene una probabilidad
+/// proporcional a su peso. Los pesos no pueden ser negativos y al menos uno debe ser positivo
+pub fn roulette_indexes(weights: &[f64], count: usize, rng: &mut StdRng) -> Vec<usize>{
+    let cumulative = cumulative_weights(weights);
+    let total = *cumulative.last().expect("La ruleta necesita al menos un peso");
+
+    return (0..count).map(|_| pick_from_cumulative(&cumulative, rng.gen::<f64>() * total)).collect();
+}
+
+/// Muestreo universal estocastico: escoge count indices girando la ruleta una unica vez, con
+/// count punteros equiespaciados. Cada indice se escoge un numero de veces que solo difiere en
+/// uno del esperado. Los indices se devuelven en orden creciente
+pub fn stochastic_universal_indexes(weights: &[f64], count: usize, rng: &mut StdRng) -> Vec<usize>{
+    if count == 0{
+        return vec![];
+    }
+
+    let cumulative = cumulative_weights(weights);
+    let total = *cumulative.last().expect("La ruleta necesita al menos un peso");
+    let step = total / count as f64;
+    let start = rng.gen::<f64>() * step;
+
+    return (0..count).map(|pointer| pick_from_cumulative(&cumulative, start + pointer as f64 * step)).collect();
+}
+
+/// Sumas acumuladas de los pesos
+fn cumulative_weights(weights: &[f64]) -> Vec<f64>{
+    debug_assert!(weights.iter().all(|&weight| weight >= 0.0), "Los pesos de la ruleta no pueden ser negativos");
+
+    let mut sum = 0.0;
+    return weights.iter().map(|weight| {
+        sum += weight;
+        return sum;
+    }).collect();
+}
+
+/// Primer indice cuya suma acumulada supera al valor dado. Por errores de redondeo el valor puede
+/// llegar a la suma total, en cuyo caso devolvemos el ultimo indice con peso
+fn pick_from_cumulative(cumulative: &[f64], value: f64) -> usize{
+    let index = cumulative.partition_point(|&sum| sum <= value);
+    if index < cumulative.len(){
+        return index;
+    }
+
+    let total = cumulative[cumulative.len() - 1];
+    return cumulative.partition_point(|&sum| sum < total);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::utils::parallel_map;
+
+    #[test]
+    fn test_parallel_map_keeps_order(){
+        let items: Vec<u32> = (0..103).collect();
+        let exp_result: Vec<u32> = items.iter().map(|x| x * x).collect();
+
+        for number_of_workers in 0..8{
+            let calc_result = parallel_map(&items, number_of_workers, |x| x * x);
+            assert_eq!(calc_result, exp_result);
+        }
+    }
+}